@@ -0,0 +1,173 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::Mutex;
+
+use winapi::um::dbghelp::AddrModeFlat;
+use winapi::um::dbghelp::StackWalk64;
+use winapi::um::dbghelp::SymCleanup;
+use winapi::um::dbghelp::SymFunctionTableAccess64;
+use winapi::um::dbghelp::SymGetModuleBase64;
+use winapi::um::dbghelp::SymInitializeW;
+use winapi::um::dbghelp::STACKFRAME64;
+use winapi::um::winnt::IMAGE_FILE_MACHINE_AMD64;
+use winapi::um::winnt::IMAGE_FILE_MACHINE_I386;
+use winapi::um::winnt::THREAD_GET_CONTEXT;
+use winapi::um::winnt::THREAD_SUSPEND_RESUME;
+
+use crate::Process;
+use crate::Thread;
+use crate::ThreadContext;
+
+/// Frames walked at most, corrupted stacks can loop
+const MAX_FRAMES: usize = 256;
+
+/// dbghelp isn't thread-safe, every call into it holds this
+static DBGHELP: Mutex<()> = Mutex::new(());
+
+/// Stack frame of a remote thread, see [`Thread::backtrace`]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Frame {
+  pub(crate) instruction_pointer: usize,
+  pub(crate) stack_pointer: usize,
+  pub(crate) module: Option<String>,
+  pub(crate) offset: Option<usize>
+}
+
+impl Frame {
+  /// Returns address executed in this frame, the return address for callers
+  pub fn get_instruction_pointer(&self) -> &usize {
+    &self.instruction_pointer
+  }
+
+  /// Returns stack pointer of the frame
+  pub fn get_stack_pointer(&self) -> &usize {
+    &self.stack_pointer
+  }
+
+  /// Returns name of the module containing the instruction pointer
+  pub fn get_module(&self) -> Option<&str> {
+    self.module.as_deref()
+  }
+
+  /// Returns offset of the instruction pointer inside its module
+  pub fn get_offset(&self) -> Option<usize> {
+    self.offset
+  }
+}
+
+impl Display for Frame {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match (&self.module, self.offset) {
+      (Some(module), Some(offset)) => write!(f, "{}+0x{:X}", module, offset),
+      _ => write!(f, "0x{:X}", self.instruction_pointer)
+    }
+  }
+}
+
+impl Debug for Frame {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Keeps dbghelp symbol handling of a process initialized
+struct SymbolSession<'p> {
+  process: &'p Process
+}
+
+impl<'p> SymbolSession<'p> {
+  fn new(process: &'p Process) -> io::Result<Self> {
+    // invading loads every module so unwind data of x64 frames can be found
+    if unsafe { SymInitializeW(process.handle.get(), ptr::null(), 1) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(Self { process })
+  }
+}
+
+impl Drop for SymbolSession<'_> {
+  fn drop(&mut self) {
+    unsafe { SymCleanup(self.process.handle.get()) };
+  }
+}
+
+impl Thread {
+  /// Walks the stack of the thread with `StackWalk64`, innermost frame first
+  /// 
+  /// The thread is suspended meanwhile. Memory of the target is read with
+  /// `ReadProcessMemory` through `process`, which must own the thread, and
+  /// WOW64 threads are walked as x86. Frames are attributed to modules with
+  /// [`Process::module_at`]. Only available to x64 callers.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.threads().expect("Couldn't get threads").remove(0);
+  /// for frame in thread.backtrace(&process).expect("Couldn't walk the stack") {
+  ///   println!("{:?}", frame);
+  /// }
+  /// ```
+  pub fn backtrace(&self, process: &Process) -> io::Result<Vec<Frame>> {
+    if process.id != self.process_id {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{:?} doesn't belong to {}({})", self, process.name, process.id)
+      ));
+    }
+
+    let handle = self.open(THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT)?;
+    let _guard = self.suspend_guard()?;
+    let mut context = self.context()?;
+    let machine = match context {
+      ThreadContext::Native(_) => IMAGE_FILE_MACHINE_AMD64,
+      ThreadContext::Wow64(_) => IMAGE_FILE_MACHINE_I386
+    };
+
+    let mut frame = unsafe { mem::zeroed::<STACKFRAME64>() };
+    frame.AddrPC.Offset = context.instruction_pointer() as u64;
+    frame.AddrPC.Mode = AddrModeFlat;
+    frame.AddrFrame.Offset = context.frame_pointer() as u64;
+    frame.AddrFrame.Mode = AddrModeFlat;
+    frame.AddrStack.Offset = context.stack_pointer() as u64;
+    frame.AddrStack.Mode = AddrModeFlat;
+
+    let _lock = DBGHELP.lock().unwrap_or_else(|error| error.into_inner());
+    let _session = SymbolSession::new(process)?;
+
+    let mut frames = Vec::new();
+    while frames.len() < MAX_FRAMES {
+      let walked = unsafe {
+        StackWalk64(
+          machine as u32,
+          process.handle.get(),
+          handle,
+          &mut frame,
+          context.as_mut_ptr(),
+          None,
+          Some(SymFunctionTableAccess64),
+          Some(SymGetModuleBase64),
+          None
+        )
+      };
+      if walked == 0 || frame.AddrPC.Offset == 0 {
+        break;
+      }
+
+      let instruction_pointer = frame.AddrPC.Offset as usize;
+      let module = process.module_at(instruction_pointer).ok().flatten();
+      frames.push(Frame {
+        instruction_pointer,
+        stack_pointer: frame.AddrStack.Offset as usize,
+        offset: module.as_ref().map(|module| instruction_pointer - module.address),
+        module: module.map(|module| module.name)
+      });
+    }
+
+    Ok(frames)
+  }
+}
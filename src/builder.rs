@@ -0,0 +1,154 @@
+use std::io;
+
+use winapi::um::winnt::PROCESS_ALL_ACCESS;
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+use crate::CuralError;
+use crate::Process;
+use crate::ProcessEntry;
+
+/// Configures which processes to open and how, see [`Process::builder`]
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// use winapi::um::winnt::PROCESS_VM_READ;
+/// let process = Process::builder()
+///   .access(PROCESS_VM_READ)
+///   .name("game.exe")
+///   .case_insensitive(true)
+///   .open()
+///   .expect("no such process");
+/// ```
+#[derive(Clone, Debug)]
+pub struct ProcessBuilder {
+  access: u32,
+  inherit: bool,
+  id: Option<u32>,
+  name: Option<String>,
+  substring: bool,
+  case_insensitive: bool
+}
+
+impl Default for ProcessBuilder {
+  fn default() -> Self {
+    Self { access: PROCESS_ALL_ACCESS, inherit: false, id: None, name: None, substring: false, case_insensitive: false }
+  }
+}
+
+impl ProcessBuilder {
+  /// Sets `PROCESS_*` access rights of opened handles, `PROCESS_ALL_ACCESS` by default
+  /// 
+  /// `PROCESS_QUERY_LIMITED_INFORMATION` is always added, opening needs it to
+  /// query the bitness
+  pub fn access(mut self, access: u32) -> Self {
+    self.access = access;
+    self
+  }
+
+  /// Sets whether child processes inherit opened handles, off by default
+  /// 
+  /// Only children created with `bInheritHandles`, as `std::process::Command`
+  /// does, inherit them
+  pub fn inherit_handle(mut self, inherit: bool) -> Self {
+    self.inherit = inherit;
+    self
+  }
+
+  /// Matches only the process with this id
+  pub fn id(mut self, id: u32) -> Self {
+    self.id = Some(id);
+    self
+  }
+
+  /// Matches processes by image name
+  pub fn name(mut self, name: &str) -> Self {
+    self.name = Some(name.to_string());
+    self
+  }
+
+  /// Matches names containing the given one instead of equal to it
+  pub fn substring(mut self, substring: bool) -> Self {
+    self.substring = substring;
+    self
+  }
+
+  /// Ignores ASCII case while matching names
+  pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+    self.case_insensitive = case_insensitive;
+    self
+  }
+
+  /// Opens the first matching process
+  pub fn open(&self) -> io::Result<Process> {
+    let mut last_error = None;
+    for entry in Process::iter()?.filter(|entry| self.matches(entry)) {
+      match self.open_entry(&entry) {
+        Ok(process) => return Ok(process),
+        Err(error) => last_error = Some(error)
+      }
+    }
+
+    Err(last_error.unwrap_or_else(|| CuralError::ProcessNotFound(format!("matching {}", self.describe())).into()))
+  }
+
+  /// Opens every matching process, skipping those which can't be opened
+  pub fn open_all(&self) -> io::Result<Vec<Process>> {
+    Ok(
+      Process::iter()?
+        .filter(|entry| self.matches(entry))
+        .filter_map(|entry| self.open_entry(&entry).ok())
+        .collect()
+    )
+  }
+
+  fn open_entry(&self, entry: &ProcessEntry) -> io::Result<Process> {
+    Process::open_entry(entry, self.access | PROCESS_QUERY_LIMITED_INFORMATION, self.inherit)
+  }
+
+  fn matches(&self, entry: &ProcessEntry) -> bool {
+    if self.id.is_some_and(|id| id != entry.id) {
+      return false;
+    }
+
+    let Some(name) = &self.name else {
+      return true;
+    };
+    let (name, found) = match self.case_insensitive {
+      true => (name.to_ascii_lowercase(), entry.name.to_ascii_lowercase()),
+      false => (name.clone(), entry.name.clone())
+    };
+
+    match self.substring {
+      true => found.contains(&name),
+      false => found == name
+    }
+  }
+
+  fn describe(&self) -> String {
+    match (&self.name, self.id) {
+      (Some(name), Some(id)) => format!("{}({})", name, id),
+      (Some(name), None) => name.clone(),
+      (None, Some(id)) => format!("id {}", id),
+      (None, None) => "anything".to_string()
+    }
+  }
+}
+
+impl Process {
+  /// Starts configuring which processes to open, for access rights, handle
+  /// inheritance and name matching beyond [`Process::find`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let processes = Process::builder()
+  ///   .name("chrome")
+  ///   .substring(true)
+  ///   .open_all()
+  ///   .expect("Couldn't create snapshot");
+  /// ```
+  pub fn builder() -> ProcessBuilder {
+    ProcessBuilder::default()
+  }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::ptr;
+
+use crate::Pod;
+use crate::Process;
+
+/// Memoizes reads of a process until [`ReadCache::flush`] is called
+/// 
+/// Reads are keyed by (address, size) and store raw bytes, so repeat reads
+/// within one tick don't touch the target again
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// let process = Process::find("process.exe").expect("no such process");
+/// let mut cache = process.cached();
+/// loop {
+///   let health = cache.read::<i32>(0x1000).expect("Couldn't read memory");
+///   let again = cache.read::<i32>(0x1000).expect("Couldn't read memory"); // cached
+///   cache.flush();
+/// }
+/// ```
+pub struct ReadCache<'p> {
+  process: &'p Process,
+  entries: HashMap<(usize, usize), Vec<u8>>
+}
+
+impl<'p> ReadCache<'p> {
+  pub(crate) fn new(process: &'p Process) -> Self {
+    Self { process, entries: HashMap::new() }
+  }
+
+  /// Reads bytes, returning cached data for repeat reads
+  pub fn read_bytes(&mut self, address: usize, len: usize) -> io::Result<&[u8]> {
+    let key = (address, len);
+    if !self.entries.contains_key(&key) {
+      let bytes = self.process.read_bytes(address, len)?;
+      self.entries.insert(key, bytes);
+    }
+
+    Ok(&self.entries[&key])
+  }
+
+  /// Reads [`Pod`] value, returning cached data for repeat reads
+  pub fn read<T: Pod>(&mut self, address: usize) -> io::Result<T> {
+    let bytes = self.read_bytes(address, mem::size_of::<T>())?;
+    Ok(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) })
+  }
+
+  /// Drops every cached read
+  pub fn flush(&mut self) {
+    self.entries.clear();
+  }
+
+  /// Returns process the cache reads from
+  pub fn get_process(&self) -> &'p Process {
+    self.process
+  }
+}
+
+impl Process {
+  /// Creates read cache over the process, see [`ReadCache`]
+  pub fn cached(&self) -> ReadCache<'_> {
+    ReadCache::new(self)
+  }
+}
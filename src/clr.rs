@@ -0,0 +1,162 @@
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+use winapi::um::winver::GetFileVersionInfoSizeW;
+use winapi::um::winver::GetFileVersionInfoW;
+use winapi::um::winver::VerQueryValueW;
+
+use crate::Module;
+use crate::Process;
+
+/// `VS_FIXEDFILEINFO.dwSignature`
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF04BD;
+/// Exported by coreclr.dll and single-file hosts of .NET 5+ for debuggers
+const RUNTIME_INFO_EXPORT: &str = "DotNetRuntimeInfo";
+
+/// Start of `VS_FIXEDFILEINFO`, with the file version
+#[repr(C)]
+struct FixedFileInfo {
+  signature: u32,
+  struct_version: u32,
+  file_version_ms: u32,
+  file_version_ls: u32
+}
+
+/// Which CLR a managed process runs on, see [`Process::clr_info`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ClrFlavor {
+  /// .NET Framework, `clr.dll` from 4.0 on and `mscorwks.dll` before
+  Framework,
+  /// .NET Core and .NET 5+, `coreclr.dll`
+  Core,
+  /// Self-contained single-file app, the runtime is linked into the executable
+  SingleFile
+}
+
+/// Runtime loaded by a managed process
+#[derive(Clone)]
+pub struct ClrInfo {
+  pub(crate) flavor: ClrFlavor,
+  pub(crate) version: Option<[u16; 4]>,
+  pub(crate) module: Module
+}
+
+impl ClrInfo {
+  /// Returns which runtime the process uses
+  pub fn get_flavor(&self) -> &ClrFlavor {
+    &self.flavor
+  }
+
+  /// Returns file version of the runtime module as major, minor, build and
+  /// revision, `None` when it has no version resource or is a single-file
+  /// executable, whose version is the app's own
+  pub fn get_version(&self) -> Option<[u16; 4]> {
+    self.version
+  }
+
+  /// Returns module the runtime lives in, the main module for single-file apps
+  pub fn get_module(&self) -> &Module {
+    &self.module
+  }
+}
+
+impl Display for ClrInfo {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.version {
+      Some([major, minor, build, revision]) => {
+        write!(f, "{:?} {}.{}.{}.{} in {}", self.flavor, major, minor, build, revision, self.module.name)
+      },
+      None => write!(f, "{:?} in {}", self.flavor, self.module.name)
+    }
+  }
+}
+
+impl Debug for ClrInfo {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Reads file version out of the version resource of the file on disk
+fn file_version(path: &Path) -> Option<[u16; 4]> {
+  let path = OsStr::new(path).encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+
+  let len = unsafe { GetFileVersionInfoSizeW(path.as_ptr(), ptr::null_mut()) };
+  if len == 0 {
+    return None;
+  }
+  let mut buffer = vec![0u8; len as usize];
+  if unsafe { GetFileVersionInfoW(path.as_ptr(), 0, len, buffer.as_mut_ptr() as *mut _) } == 0 {
+    return None;
+  }
+
+  let root = [b'\\' as u16, 0];
+  let mut info = ptr::null_mut();
+  let mut info_len = 0;
+  if unsafe { VerQueryValueW(buffer.as_ptr() as *const _, root.as_ptr(), &mut info, &mut info_len) } == 0
+    || (info_len as usize) < mem::size_of::<FixedFileInfo>() {
+    return None;
+  }
+
+  let info = unsafe { ptr::read_unaligned(info as *const FixedFileInfo) };
+  if info.signature != FIXED_FILE_INFO_SIGNATURE {
+    return None;
+  }
+
+  Some([
+    (info.file_version_ms >> 16) as u16,
+    info.file_version_ms as u16,
+    (info.file_version_ls >> 16) as u16,
+    info.file_version_ls as u16
+  ])
+}
+
+impl Process {
+  /// Detects the .NET runtime of the process, `None` for native processes
+  /// 
+  /// Looks for `coreclr.dll`, then `clr.dll` and `mscorwks.dll` in the
+  /// module list, so a process hosting both .NET Core and Framework reports
+  /// Core. Without any of them the main module is checked for the
+  /// `DotNetRuntimeInfo` export of self-contained single-file apps. Versions
+  /// are read from the module files on disk.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// if let Some(clr) = process.clr_info().expect("Couldn't get modules") {
+  ///   println!("managed code, signatures won't be stable: {:?}", clr);
+  /// }
+  /// ```
+  pub fn clr_info(&self) -> io::Result<Option<ClrInfo>> {
+    let modules = self.get_all_modules()?;
+
+    let runtimes = [
+      ("coreclr.dll", ClrFlavor::Core),
+      ("clr.dll", ClrFlavor::Framework),
+      ("mscorwks.dll", ClrFlavor::Framework)
+    ];
+    for (name, flavor) in runtimes {
+      if let Some(module) = modules.iter().find(|module| module.name.eq_ignore_ascii_case(name)) {
+        return Ok(Some(ClrInfo {
+          flavor,
+          version: file_version(&module.path),
+          module: module.clone()
+        }));
+      }
+    }
+
+    let main = self.main_module()?;
+    // a native exe without exports is the common case, not an error
+    match main.bind(self).get_export(RUNTIME_INFO_EXPORT) {
+      Ok(_) => Ok(Some(ClrInfo { flavor: ClrFlavor::SingleFile, version: None, module: main })),
+      Err(_) => Ok(None)
+    }
+  }
+}
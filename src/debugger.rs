@@ -0,0 +1,726 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use winapi::shared::ntstatus::STATUS_WX86_BREAKPOINT;
+use winapi::shared::ntstatus::STATUS_WX86_SINGLE_STEP;
+use winapi::shared::winerror::ERROR_SEM_TIMEOUT;
+use winapi::um::debugapi::ContinueDebugEvent;
+use winapi::um::debugapi::DebugActiveProcess;
+use winapi::um::debugapi::DebugActiveProcessStop;
+use winapi::um::debugapi::WaitForDebugEvent;
+use winapi::um::fileapi::GetFinalPathNameByHandleW;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::minwinbase::CREATE_PROCESS_DEBUG_EVENT;
+use winapi::um::minwinbase::CREATE_THREAD_DEBUG_EVENT;
+use winapi::um::minwinbase::DEBUG_EVENT;
+use winapi::um::minwinbase::EXCEPTION_BREAKPOINT;
+use winapi::um::minwinbase::EXCEPTION_DEBUG_EVENT;
+use winapi::um::minwinbase::EXCEPTION_SINGLE_STEP;
+use winapi::um::minwinbase::EXIT_PROCESS_DEBUG_EVENT;
+use winapi::um::minwinbase::EXIT_THREAD_DEBUG_EVENT;
+use winapi::um::minwinbase::LOAD_DLL_DEBUG_EVENT;
+use winapi::um::minwinbase::OUTPUT_DEBUG_STRING_EVENT;
+use winapi::um::minwinbase::OUTPUT_DEBUG_STRING_INFO;
+use winapi::um::minwinbase::RIP_EVENT;
+use winapi::um::minwinbase::UNLOAD_DLL_DEBUG_EVENT;
+use winapi::um::winbase::DebugSetProcessKillOnExit;
+use winapi::um::winbase::INFINITE;
+use winapi::um::winnt::DBG_CONTINUE;
+use winapi::um::winnt::DBG_EXCEPTION_NOT_HANDLED;
+use winapi::um::winnt::HANDLE;
+use winapi::um::winnt::THREAD_GET_CONTEXT;
+use winapi::um::winnt::THREAD_SET_CONTEXT;
+
+use crate::Process;
+use crate::Thread;
+use crate::ThreadContext;
+
+/// `int3`
+const INT3: u8 = 0xCC;
+
+/// Trap flag of `eflags`, single-steps the thread
+const TRAP_FLAG: u32 = 0x100;
+
+/// Longest x86 instruction
+const MAX_INSTRUCTION_LEN: usize = 15;
+
+/// Exception codes of the 32-bit side of WOW64 processes
+const WX86_BREAKPOINT: u32 = STATUS_WX86_BREAKPOINT as u32;
+const WX86_SINGLE_STEP: u32 = STATUS_WX86_SINGLE_STEP as u32;
+
+/// Returns is the event a single-step exception
+fn is_single_step(event: &DEBUG_EVENT) -> bool {
+  event.dwDebugEventCode == EXCEPTION_DEBUG_EVENT && matches!(
+    unsafe { event.u.Exception() }.ExceptionRecord.ExceptionCode,
+    EXCEPTION_SINGLE_STEP | WX86_SINGLE_STEP
+  )
+}
+
+/// Returns length of the call instruction at the start of `code`, `None` for other instructions
+/// 
+/// Knows `E8 rel32` and `FF /2` with legacy and (for `x64`) REX prefixes
+fn call_len(code: &[u8], x64: bool) -> Option<usize> {
+  let mut at = 0;
+  let mut address_size = false;
+  while let Some(&byte) = code.get(at) {
+    match byte {
+      0x66 | 0xF2 | 0xF3 | 0x2E | 0x36 | 0x3E | 0x26 | 0x64 | 0x65 => at += 1,
+      0x67 => {
+        address_size = true;
+        at += 1;
+      },
+      0x40..=0x4F if x64 => at += 1,
+      _ => break
+    }
+  }
+
+  match *code.get(at)? {
+    0xE8 => Some(at + 5),
+    0xFF => {
+      let modrm = *code.get(at + 1)?;
+      if (modrm >> 3) & 0b111 != 2 {
+        return None;
+      }
+      // 16-bit addressing of x86 isn't worth decoding
+      if address_size && !x64 {
+        return None;
+      }
+
+      let mode = modrm >> 6;
+      let rm = modrm & 0b111;
+      let mut len = at + 2;
+      if mode != 0b11 && rm == 0b100 {
+        let sib = *code.get(len)?;
+        len += 1;
+        if mode == 0b00 && sib & 0b111 == 0b101 {
+          len += 4;
+        }
+      }
+      len += match (mode, rm) {
+        (0b00, 0b101) => 4,
+        (0b01, _) => 1,
+        (0b10, _) => 4,
+        _ => 0
+      };
+
+      Some(len)
+    },
+    _ => None
+  }
+}
+
+/// Returns path of a file handle from a debug event and closes the handle, which is ours to close
+fn take_file_path(file: HANDLE) -> Option<PathBuf> {
+  if file.is_null() {
+    return None;
+  }
+
+  let mut buffer = vec![0u16; 32 * 1024];
+  let len = unsafe { GetFinalPathNameByHandleW(file, buffer.as_mut_ptr(), buffer.len() as u32, 0) } as usize;
+  unsafe { CloseHandle(file) };
+  if len == 0 || len > buffer.len() {
+    return None;
+  }
+
+  let path = String::from_utf16_lossy(&buffer[..len]);
+  Some(PathBuf::from(path.strip_prefix(r"\\?\").unwrap_or(&path)))
+}
+
+/// Software breakpoint of a [`Debugger`], see [`Debugger::set_breakpoint`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BreakpointId(u32);
+
+/// Thread stopped on a software breakpoint, see [`Debugger::wait_breakpoint`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreakpointHit {
+  pub(crate) thread_id: u32,
+  pub(crate) address: usize,
+  pub(crate) ids: Vec<BreakpointId>
+}
+
+impl BreakpointHit {
+  /// Returns id of the stopped thread
+  pub fn get_thread_id(&self) -> &u32 {
+    &self.thread_id
+  }
+
+  /// Returns address of the breakpoint, where the thread stopped
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns every breakpoint set at the address
+  pub fn get_ids(&self) -> &[BreakpointId] {
+    &self.ids
+  }
+}
+
+/// Event reported by [`Debugger::wait_event`], the target stays stopped until
+/// [`Debugger::continue_event`] or the next wait
+/// 
+/// Attaching reports [`DebugEvent::ProcessCreate`], then a [`DebugEvent::ThreadCreate`]
+/// and [`DebugEvent::ModuleLoad`] for every thread and module which already exist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DebugEvent {
+  /// Exception raised in the target, `first_chance` is false once the target's
+  /// own handlers passed on it. Hits of a [`crate::HwBreakpoint`] arrive as
+  /// `EXCEPTION_SINGLE_STEP`, see [`crate::HwBreakpoint::is_hit_by`].
+  Exception { thread_id: u32, code: u32, address: usize, first_chance: bool },
+  /// Thread stopped on a software breakpoint, see [`Debugger::set_breakpoint`]
+  Breakpoint(BreakpointHit),
+  ProcessCreate { thread_id: u32, base: usize },
+  ProcessExit { exit_code: u32 },
+  ThreadCreate { thread_id: u32, start_address: usize },
+  ThreadExit { thread_id: u32, exit_code: u32 },
+  /// `path` is `None` when the file of the module couldn't be resolved
+  ModuleLoad { thread_id: u32, base: usize, path: Option<PathBuf> },
+  ModuleUnload { thread_id: u32, base: usize },
+  /// Text passed to `OutputDebugString`
+  OutputString { thread_id: u32, text: String },
+  /// The process died out of the debugger's control with this system error
+  Rip { thread_id: u32, error: u32 }
+}
+
+/// Where a thread stopped after [`Debugger::single_step`] or [`Debugger::step_over`]
+#[derive(Clone, Debug)]
+pub struct StepResult {
+  pub(crate) thread_id: u32,
+  pub(crate) instruction_pointer: usize,
+  pub(crate) context: ThreadContext
+}
+
+impl StepResult {
+  /// Returns id of the stepped thread
+  pub fn get_thread_id(&self) -> &u32 {
+    &self.thread_id
+  }
+
+  /// Returns address of the next instruction to execute
+  pub fn get_instruction_pointer(&self) -> &usize {
+    &self.instruction_pointer
+  }
+
+  /// Returns registers of the thread after the step
+  pub fn get_context(&self) -> &ThreadContext {
+    &self.context
+  }
+}
+
+/// `int3` patched into the target, shared by every [`BreakpointId`] at its address
+struct SoftwareBreakpoint {
+  original: u8,
+  refs: usize
+}
+
+/// Debugger attached to a process, see [`Process::attach_debugger`]
+/// 
+/// Windows delivers debug events only to the thread which attached, so the
+/// debugger can't be sent to other threads. Dropping it detaches and leaves
+/// the process running, restoring every patched byte first.
+pub struct Debugger {
+  process: Process,
+  attached: bool,
+  /// Whether the process exit was reported, after which nothing is left to debug
+  exited: bool,
+  /// Whether the breakpoint the system raises on attach was passed already
+  attach_breakpoint: bool,
+  /// Event reported but not continued yet as (process id, thread id, default continue status)
+  pending: Option<(u32, u32, u32)>,
+  breakpoints: HashMap<usize, SoftwareBreakpoint>,
+  ids: HashMap<BreakpointId, usize>,
+  next_id: u32,
+  /// Threads single-stepping over a disarmed breakpoint with its address
+  rearm: Vec<(u32, usize)>,
+  _not_send: PhantomData<*const ()>
+}
+
+impl Debugger {
+  /// Returns the debugged process
+  pub fn get_process(&self) -> &Process {
+    &self.process
+  }
+
+  /// Sets an `int3` breakpoint at `address` and returns its id
+  /// 
+  /// The original byte is saved and patched through a temporary protection
+  /// change. Setting another breakpoint at the same address only counts it,
+  /// the byte is restored once every breakpoint there is removed.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut debugger = process.attach_debugger().expect("Couldn't attach");
+  /// let breakpoint = debugger.set_breakpoint(0x7FF600001000).expect("Couldn't set breakpoint");
+  /// let hit = debugger.wait_breakpoint(None).expect("Couldn't wait");
+  /// println!("thread {} stopped at 0x{:X}", hit.get_thread_id(), hit.get_address());
+  /// debugger.remove_breakpoint(breakpoint).expect("Couldn't remove breakpoint");
+  /// ```
+  pub fn set_breakpoint(&mut self, address: usize) -> io::Result<BreakpointId> {
+    match self.breakpoints.get_mut(&address) {
+      Some(breakpoint) => breakpoint.refs += 1,
+      None => {
+        let original = self.process.try_read::<u8>(address)?;
+        self.process.write_code(address, &[INT3])?;
+        self.breakpoints.insert(address, SoftwareBreakpoint { original, refs: 1 });
+      }
+    }
+
+    let id = BreakpointId(self.next_id);
+    self.next_id += 1;
+    self.ids.insert(id, address);
+    Ok(id)
+  }
+
+  /// Removes a breakpoint, restoring the original byte when it was the last at its address
+  pub fn remove_breakpoint(&mut self, id: BreakpointId) -> io::Result<()> {
+    let address = self.ids.remove(&id).ok_or_else(|| io::Error::new(
+      io::ErrorKind::NotFound,
+      format!("no breakpoint {:?}", id)
+    ))?;
+
+    let breakpoint = self.breakpoints.get_mut(&address).expect("breakpoint id without breakpoint");
+    breakpoint.refs -= 1;
+    if breakpoint.refs == 0 {
+      let original = breakpoint.original;
+      self.breakpoints.remove(&address);
+      self.process.write_code(address, &[original])?;
+    }
+
+    Ok(())
+  }
+
+  /// Runs the target until the next debug event, see [`DebugEvent`]
+  /// 
+  /// An event which wasn't continued with [`Debugger::continue_event`] is
+  /// continued first, exceptions as unhandled. Hits of software breakpoints are
+  /// rewound onto the original instruction, which is single-stepped and re-armed
+  /// transparently once continued. Gives up after `timeout` with an
+  /// [`io::ErrorKind::TimedOut`] error, `None` waits forever.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{DebugEvent, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut debugger = process.attach_debugger().expect("Couldn't attach");
+  /// loop {
+  ///   match debugger.wait_event(None).expect("Couldn't wait") {
+  ///     DebugEvent::OutputString { text, .. } => println!("{}", text),
+  ///     DebugEvent::ProcessExit { .. } => break,
+  ///     _ => {}
+  ///   }
+  ///   debugger.continue_event(false).expect("Couldn't continue");
+  /// }
+  /// ```
+  pub fn wait_event(&mut self, timeout: Option<Duration>) -> io::Result<DebugEvent> {
+    loop {
+      let event = self.next_event(timeout)?;
+      if let Some(event) = self.translate(&event)? {
+        return Ok(event);
+      }
+    }
+  }
+
+  /// Resumes the target after the last event, `handled` tells whether an
+  /// exception was dealt with or should go on to the target's handlers
+  /// 
+  /// Fails with [`io::ErrorKind::InvalidInput`] when no event is waiting
+  pub fn continue_event(&mut self, handled: bool) -> io::Result<()> {
+    if self.pending.is_none() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "no debug event to continue"));
+    }
+
+    self.continue_pending(Some(match handled {
+      true => DBG_CONTINUE,
+      false => DBG_EXCEPTION_NOT_HANDLED
+    }))
+  }
+
+  /// Runs the target until a thread hits one of the software breakpoints,
+  /// continuing every other event as [`Debugger::wait_event`] would
+  pub fn wait_breakpoint(&mut self, timeout: Option<Duration>) -> io::Result<BreakpointHit> {
+    loop {
+      if let DebugEvent::Breakpoint(hit) = self.wait_event(timeout)? {
+        return Ok(hit);
+      }
+    }
+  }
+
+  /// Executes one instruction of `thread` through the trap flag and returns where it stopped
+  /// 
+  /// Needs the target stopped at an event from [`Debugger::wait_event`], which
+  /// is continued like the next wait would. A software breakpoint under the
+  /// instruction pointer is disarmed for the step, so the original instruction
+  /// runs, and re-armed after. Events of other threads meanwhile are continued
+  /// without being reported.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut debugger = process.attach_debugger().expect("Couldn't attach");
+  /// debugger.set_breakpoint(0x7FF600001000).expect("Couldn't set breakpoint");
+  /// let hit = debugger.wait_breakpoint(None).expect("Couldn't wait");
+  /// let thread = process.threads().expect("Couldn't get threads")
+  ///   .into_iter()
+  ///   .find(|thread| thread.get_id() == hit.get_thread_id())
+  ///   .expect("no such thread");
+  /// let step = debugger.single_step(&thread).expect("Couldn't step");
+  /// println!("now at 0x{:X}", step.get_instruction_pointer());
+  /// ```
+  pub fn single_step(&mut self, thread: &Thread) -> io::Result<StepResult> {
+    if self.pending.is_none() {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "the target must be stopped at a debug event to step"
+      ));
+    }
+
+    let mut context = self.thread_context(thread.id)?;
+    let address = context.instruction_pointer();
+    let stepping_off = self.rearm.iter().any(|(id, _)| id == &thread.id);
+    if !stepping_off && self.breakpoints.contains_key(&address) {
+      self.process.write_code(address, &[self.breakpoints[&address].original])?;
+      self.rearm.push((thread.id, address));
+    }
+    context.set_flags(context.flags() | TRAP_FLAG);
+    self.set_thread_context(thread.id, &context)?;
+
+    loop {
+      let event = self.next_event(None)?;
+      if is_single_step(&event) && event.dwThreadId == thread.id {
+        if self.rearm.iter().any(|(id, _)| id == &thread.id) {
+          self.rearm_breakpoint(thread.id)?;
+        }
+
+        let context = self.thread_context(thread.id)?;
+        return Ok(StepResult { thread_id: thread.id, instruction_pointer: context.instruction_pointer(), context });
+      }
+
+      if let Some(DebugEvent::ProcessExit { .. }) = self.translate(&event)? {
+        return Err(io::Error::new(
+          io::ErrorKind::NotFound,
+          format!("{}({}) exited while stepping", self.process.name, self.process.id)
+        ));
+      }
+    }
+  }
+
+  /// Same as [`Debugger::single_step`], but runs a call instruction until it returns
+  /// 
+  /// Calls are recognized by their encoding (`E8` and `FF /2`, with prefixes),
+  /// a temporary breakpoint at the return address stops the thread once it's
+  /// back in the same frame. Other instructions are single-stepped.
+  pub fn step_over(&mut self, thread: &Thread) -> io::Result<StepResult> {
+    if self.pending.is_none() {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "the target must be stopped at a debug event to step"
+      ));
+    }
+
+    let context = self.thread_context(thread.id)?;
+    let address = context.instruction_pointer();
+    let stack = context.stack_pointer();
+
+    let mut code = self.process.read_bytes(address, MAX_INSTRUCTION_LEN)?;
+    // a breakpoint under the instruction shows as int3 in memory
+    if let Some(breakpoint) = self.breakpoints.get(&address) {
+      code[0] = breakpoint.original;
+    }
+    let len = match call_len(&code, !thread.wow64) {
+      Some(len) => len,
+      None => return self.single_step(thread)
+    };
+
+    // step off a breakpoint at the call itself first, it'd hit when resuming otherwise
+    if self.breakpoints.contains_key(&address) {
+      self.single_step(thread)?;
+    }
+
+    let returned = self.set_breakpoint(address + len)?;
+    let result = loop {
+      match self.wait_event(None) {
+        Ok(DebugEvent::Breakpoint(hit)) if hit.thread_id == thread.id && hit.ids.contains(&returned) => {
+          match self.thread_context(thread.id) {
+            // deeper recursion of the same call when the stack is still below the caller's
+            Ok(context) if context.stack_pointer() < stack => continue,
+            Ok(context) => break Ok(StepResult { thread_id: thread.id, instruction_pointer: context.instruction_pointer(), context }),
+            Err(error) => break Err(error)
+          }
+        },
+        Ok(DebugEvent::ProcessExit { .. }) => break Err(io::Error::new(
+          io::ErrorKind::NotFound,
+          format!("{}({}) exited while stepping", self.process.name, self.process.id)
+        )),
+        Ok(_) => continue,
+        Err(error) => break Err(error)
+      }
+    };
+    let removed = self.remove_breakpoint(returned);
+
+    result.and_then(|result| removed.map(|_| result))
+  }
+
+  /// Detaches, leaving the process running, see [`Debugger`]
+  pub fn detach(mut self) -> io::Result<()> {
+    self.detach_inner()
+  }
+
+  /// Continues the pending event and waits for the next one
+  fn next_event(&mut self, timeout: Option<Duration>) -> io::Result<DEBUG_EVENT> {
+    self.continue_pending(None)?;
+    if self.exited {
+      return Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{}({}) exited", self.process.name, self.process.id)
+      ));
+    }
+
+    let millis = timeout.map_or(INFINITE, |timeout| timeout.as_millis().min(INFINITE as u128 - 1) as u32);
+    let mut event = unsafe { mem::zeroed::<DEBUG_EVENT>() };
+    if unsafe { WaitForDebugEvent(&mut event, millis) } == 0 {
+      let error = io::Error::last_os_error();
+      return Err(match error.raw_os_error() == Some(ERROR_SEM_TIMEOUT as i32) {
+        true => io::Error::new(io::ErrorKind::TimedOut, "no debug event in time"),
+        false => error
+      });
+    }
+
+    self.pending = Some((event.dwProcessId, event.dwThreadId, DBG_CONTINUE));
+    Ok(event)
+  }
+
+  /// Continues the pending event with `status`, or its default one
+  fn continue_pending(&mut self, status: Option<u32>) -> io::Result<()> {
+    if let Some((process_id, thread_id, default)) = self.pending.take() {
+      if unsafe { ContinueDebugEvent(process_id, thread_id, status.unwrap_or(default)) } == 0 {
+        return Err(io::Error::last_os_error());
+      }
+    }
+
+    Ok(())
+  }
+
+  fn set_pending_status(&mut self, status: u32) {
+    if let Some((_, _, default)) = &mut self.pending {
+      *default = status;
+    }
+  }
+
+  /// Converts a raw event, `None` for events the debugger handles itself
+  fn translate(&mut self, event: &DEBUG_EVENT) -> io::Result<Option<DebugEvent>> {
+    let thread_id = event.dwThreadId;
+
+    let translated = match event.dwDebugEventCode {
+      EXCEPTION_DEBUG_EVENT => {
+        let info = unsafe { event.u.Exception() };
+        let record = info.ExceptionRecord;
+        let address = record.ExceptionAddress as usize;
+        let first_chance = info.dwFirstChance != 0;
+
+        match record.ExceptionCode {
+          EXCEPTION_BREAKPOINT | WX86_BREAKPOINT if first_chance && self.breakpoints.contains_key(&address) => {
+            DebugEvent::Breakpoint(self.stop_at_breakpoint(thread_id, address)?)
+          },
+          EXCEPTION_BREAKPOINT | WX86_BREAKPOINT if !self.attach_breakpoint => {
+            self.attach_breakpoint = true;
+            return Ok(None);
+          },
+          EXCEPTION_SINGLE_STEP | WX86_SINGLE_STEP if self.rearm.iter().any(|(id, _)| id == &thread_id) => {
+            self.rearm_breakpoint(thread_id)?;
+            return Ok(None);
+          },
+          code => {
+            self.set_pending_status(DBG_EXCEPTION_NOT_HANDLED);
+            DebugEvent::Exception { thread_id, code, address, first_chance }
+          }
+        }
+      },
+      CREATE_PROCESS_DEBUG_EVENT => {
+        let info = unsafe { event.u.CreateProcessInfo() };
+        take_file_path(info.hFile);
+        DebugEvent::ProcessCreate { thread_id, base: info.lpBaseOfImage as usize }
+      },
+      EXIT_PROCESS_DEBUG_EVENT => {
+        self.exited = true;
+        DebugEvent::ProcessExit { exit_code: unsafe { event.u.ExitProcess() }.dwExitCode }
+      },
+      CREATE_THREAD_DEBUG_EVENT => DebugEvent::ThreadCreate {
+        thread_id,
+        start_address: unsafe { event.u.CreateThread() }.lpStartAddress.map_or(0, |start| start as usize)
+      },
+      EXIT_THREAD_DEBUG_EVENT => {
+        self.rearm.retain(|(id, _)| id != &thread_id);
+        DebugEvent::ThreadExit { thread_id, exit_code: unsafe { event.u.ExitThread() }.dwExitCode }
+      },
+      LOAD_DLL_DEBUG_EVENT => {
+        let info = unsafe { event.u.LoadDll() };
+        DebugEvent::ModuleLoad { thread_id, base: info.lpBaseOfDll as usize, path: take_file_path(info.hFile) }
+      },
+      UNLOAD_DLL_DEBUG_EVENT => DebugEvent::ModuleUnload {
+        thread_id,
+        base: unsafe { event.u.UnloadDll() }.lpBaseOfDll as usize
+      },
+      OUTPUT_DEBUG_STRING_EVENT => DebugEvent::OutputString {
+        thread_id,
+        text: self.read_debug_string(unsafe { event.u.DebugString() })?
+      },
+      RIP_EVENT => DebugEvent::Rip { thread_id, error: unsafe { event.u.RipInfo() }.dwError },
+      _ => return Ok(None)
+    };
+
+    Ok(Some(translated))
+  }
+
+  fn read_debug_string(&self, info: &OUTPUT_DEBUG_STRING_INFO) -> io::Result<String> {
+    let address = info.lpDebugStringData as usize;
+    let len = info.nDebugStringLength as usize;
+
+    let text = match info.fUnicode != 0 {
+      true => {
+        let bytes = self.process.read_bytes(address, len * 2)?;
+        let wide = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect::<Vec<_>>();
+        String::from_utf16_lossy(&wide)
+      },
+      false => String::from_utf8_lossy(&self.process.read_bytes(address, len)?).into_owned()
+    };
+
+    Ok(text.trim_end_matches('\0').to_string())
+  }
+
+  /// Restores the original byte, rewinds the thread onto it and sets it up to single-step
+  fn stop_at_breakpoint(&mut self, thread_id: u32, address: usize) -> io::Result<BreakpointHit> {
+    let original = self.breakpoints[&address].original;
+    self.process.write_code(address, &[original])?;
+
+    let mut context = self.thread_context(thread_id)?;
+    context.set_instruction_pointer(address);
+    context.set_flags(context.flags() | TRAP_FLAG);
+    self.set_thread_context(thread_id, &context)?;
+    self.rearm.push((thread_id, address));
+
+    let mut ids = self.ids.iter()
+      .filter(|(_, breakpoint)| *breakpoint == &address)
+      .map(|(id, _)| *id)
+      .collect::<Vec<_>>();
+    ids.sort_by_key(|id| id.0);
+
+    Ok(BreakpointHit { thread_id, address, ids })
+  }
+
+  /// Patches `int3` back after the thread stepped over the original instruction
+  fn rearm_breakpoint(&mut self, thread_id: u32) -> io::Result<()> {
+    let index = self.rearm.iter().position(|(id, _)| id == &thread_id).expect("thread isn't re-arming");
+    let (_, address) = self.rearm.remove(index);
+
+    // removed meanwhile, or disarmed again by another thread which hit it
+    let armed_elsewhere = self.rearm.iter().any(|(_, other)| other == &address);
+    if self.breakpoints.contains_key(&address) && !armed_elsewhere {
+      self.process.write_code(address, &[INT3])?;
+    }
+
+    Ok(())
+  }
+
+  fn thread(&self, thread_id: u32) -> Thread {
+    Thread::new(thread_id, self.process.id, cfg!(target_pointer_width = "64") && !self.process.is_x64().unwrap_or(true))
+  }
+
+  /// Threads stopped by a debug event are frozen already, so no suspending here
+  fn thread_context(&self, thread_id: u32) -> io::Result<ThreadContext> {
+    let thread = self.thread(thread_id);
+    ThreadContext::get(thread.open(THREAD_GET_CONTEXT)?, thread.wow64)
+  }
+
+  fn set_thread_context(&self, thread_id: u32, context: &ThreadContext) -> io::Result<()> {
+    context.set(self.thread(thread_id).open(THREAD_SET_CONTEXT)?)
+  }
+
+  fn detach_inner(&mut self) -> io::Result<()> {
+    if !self.attached {
+      return Ok(());
+    }
+
+    // a pending trap flag would kill the thread once nobody handles the step
+    for (thread_id, _) in mem::take(&mut self.rearm) {
+      if let Ok(mut context) = self.thread_context(thread_id) {
+        context.set_flags(context.flags() & !TRAP_FLAG);
+        let _ = self.set_thread_context(thread_id, &context);
+      }
+    }
+    for (address, breakpoint) in mem::take(&mut self.breakpoints) {
+      let _ = self.process.write_code(address, &[breakpoint.original]);
+    }
+    self.ids.clear();
+
+    self.continue_pending(None)?;
+    self.attached = false;
+    // an exited process detached by itself
+    if self.exited {
+      return Ok(());
+    }
+    if unsafe { DebugActiveProcessStop(self.process.id) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+  }
+}
+
+impl Drop for Debugger {
+  fn drop(&mut self) {
+    let _ = self.detach_inner();
+  }
+}
+
+impl Display for Debugger {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "debugger of {}({})", self.process.name, self.process.id)
+  }
+}
+
+impl Debug for Debugger {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Attaches as a debugger with `DebugActiveProcess`, see [`Debugger`]
+  /// 
+  /// Detaching, explicitly or by dropping, leaves the process running
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let debugger = process.attach_debugger().expect("Couldn't attach");
+  /// debugger.detach().expect("Couldn't detach");
+  /// ```
+  pub fn attach_debugger(&self) -> io::Result<Debugger> {
+    if unsafe { DebugActiveProcess(self.id) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+    unsafe { DebugSetProcessKillOnExit(0) };
+
+    Ok(Debugger {
+      process: self.clone(),
+      attached: true,
+      exited: false,
+      attach_breakpoint: false,
+      pending: None,
+      breakpoints: HashMap::new(),
+      ids: HashMap::new(),
+      next_id: 0,
+      rearm: Vec::new(),
+      _not_send: PhantomData
+    })
+  }
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::io;
+use std::ops::Range;
+
+use winapi::um::winnt::IMAGE_DIRECTORY_ENTRY_IAT;
+use winapi::um::winnt::IMAGE_SCN_MEM_EXECUTE;
+
+use crate::pe::apply_relocation;
+use crate::pe::relocations;
+use crate::pe::rva_to_offset;
+use crate::pe::NtHeaders;
+use crate::BoundModule;
+
+/// Range of bytes in memory which differ from the module file on disk
+#[derive(Clone, Debug)]
+pub struct CodeDiff {
+  pub(crate) address: usize,
+  pub(crate) disk_bytes: Vec<u8>,
+  pub(crate) memory_bytes: Vec<u8>
+}
+
+impl CodeDiff {
+  /// Returns absolute address of the first differing byte
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns number of differing bytes
+  pub fn len(&self) -> usize {
+    self.memory_bytes.len()
+  }
+
+  /// Returns is range empty
+  pub fn is_empty(&self) -> bool {
+    self.memory_bytes.is_empty()
+  }
+
+  /// Returns bytes from the file (relocated to the actual base)
+  pub fn get_disk_bytes(&self) -> &[u8] {
+    &self.disk_bytes
+  }
+
+  /// Returns bytes from the process memory
+  pub fn get_memory_bytes(&self) -> &[u8] {
+    &self.memory_bytes
+  }
+}
+
+/// Options of [`BoundModule::diff_against_disk_with`]
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+  /// Report differences inside the IAT, which legitimately differs after imports are resolved
+  pub include_iat: bool
+}
+
+impl BoundModule<'_> {
+  /// Compares executable sections in memory against the module file on disk
+  /// 
+  /// Relocations are applied to the file bytes for the actual base, so
+  /// only real patches and hooks are reported. The IAT is excluded.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let diffs = main.bind(&process).diff_against_disk().expect("Couldn't compare");
+  /// ```
+  pub fn diff_against_disk(&self) -> io::Result<Vec<CodeDiff>> {
+    self.diff_against_disk_with(DiffOptions::default())
+  }
+
+  /// Compares executable sections in memory against the module file on disk
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{DiffOptions, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let diffs = main.bind(&process)
+  ///   .diff_against_disk_with(DiffOptions { include_iat: true })
+  ///   .expect("Couldn't compare");
+  /// ```
+  pub fn diff_against_disk_with(&self, options: DiffOptions) -> io::Result<Vec<CodeDiff>> {
+    let file = fs::read(&self.path)?;
+    let headers = NtHeaders::read(file.as_slice(), 0)?;
+    let sections = headers.section_headers(file.as_slice())?;
+
+    let delta = (self.address as u64).wrapping_sub(headers.image_base());
+    let relocations = relocations(file.as_slice(), &headers, |rva| rva_to_offset(&sections, rva))?;
+    let iat = headers.data_directory(IMAGE_DIRECTORY_ENTRY_IAT)
+      .filter(|_| !options.include_iat)
+      .map(|directory| {
+        let start = directory.VirtualAddress as usize;
+        start..start + directory.Size as usize
+      });
+
+    let mut result = Vec::new();
+    for section in sections.iter().filter(|section| section.Characteristics & IMAGE_SCN_MEM_EXECUTE != 0) {
+      let rva = section.VirtualAddress as usize;
+      let len = (unsafe { *section.Misc.VirtualSize() } as usize).min(section.SizeOfRawData as usize);
+      let raw = section.PointerToRawData as usize;
+
+      let mut disk = file.get(raw..raw + len)
+        .ok_or_else(|| io::Error::new(
+          io::ErrorKind::InvalidData,
+          "section raw data is outside of the file"
+        ))?
+        .to_vec();
+      let memory = self.process.read_bytes(self.address + rva, len)?;
+
+      let mut ignored = Vec::<Range<usize>>::new();
+      for relocation in relocations.iter().filter(|relocation| (rva..rva + len).contains(&relocation.rva)) {
+        let offset = relocation.rva - rva;
+        if !apply_relocation(&mut disk, offset, relocation, delta) {
+          ignored.push(offset..offset + relocation.size());
+        }
+      }
+      if let Some(iat) = &iat {
+        ignored.push(iat.start.saturating_sub(rva)..iat.end.saturating_sub(rva));
+      }
+
+      let mut offset = 0;
+      while offset < len {
+        if disk[offset] == memory[offset] || ignored.iter().any(|range| range.contains(&offset)) {
+          offset += 1;
+          continue;
+        }
+
+        let start = offset;
+        while offset < len
+          && disk[offset] != memory[offset]
+          && !ignored.iter().any(|range| range.contains(&offset)) {
+          offset += 1;
+        }
+
+        result.push(CodeDiff {
+          address: self.address + rva + start,
+          disk_bytes: disk[start..offset].to_vec(),
+          memory_bytes: memory[start..offset].to_vec()
+        });
+      }
+    }
+
+    Ok(result)
+  }
+}
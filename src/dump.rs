@@ -0,0 +1,126 @@
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+use winapi::um::winnt::IMAGE_FILE_HEADER;
+use winapi::um::winnt::IMAGE_SECTION_HEADER;
+
+use crate::pe::NtHeaders;
+use crate::Module;
+use crate::Process;
+
+/// Offset of `SizeOfRawData` in `IMAGE_SECTION_HEADER`
+const SIZE_OF_RAW_DATA: usize = 16;
+/// Offset of `PointerToRawData` in `IMAGE_SECTION_HEADER`
+const POINTER_TO_RAW_DATA: usize = 20;
+/// Offset of `ImageBase` in the PE32 and PE32+ optional headers
+const IMAGE_BASE_PE32: usize = 28;
+const IMAGE_BASE_PE64: usize = 24;
+
+/// Options of [`Process::dump_module_with`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DumpOptions {
+  /// Rewrites section headers so raw offsets equal virtual addresses and
+  /// `ImageBase` to where the module is loaded, so static analysis tools map
+  /// the memory layout of the dump correctly
+  pub fix_headers: bool,
+  /// Keeps the process suspended while the image is read, so pages written
+  /// meanwhile, like unpacked code, don't tear
+  pub suspend: bool
+}
+
+/// Makes the in-memory layout valid as a file layout, see [`DumpOptions::fix_headers`]
+fn fix_headers(image: &mut [u8], base: usize) -> io::Result<()> {
+  let headers = NtHeaders::read(image, 0)?;
+  let sections = headers.section_headers(image)?;
+
+  let optional = headers.address + mem::size_of::<u32>() + mem::size_of::<IMAGE_FILE_HEADER>();
+  match headers.is_pe64() {
+    true => put(image, optional + IMAGE_BASE_PE64, &(base as u64).to_le_bytes())?,
+    false => put(image, optional + IMAGE_BASE_PE32, &(base as u32).to_le_bytes())?
+  }
+
+  let first = optional + headers.file_header().SizeOfOptionalHeader as usize;
+  for (index, section) in sections.iter().enumerate() {
+    let header = first + index * mem::size_of::<IMAGE_SECTION_HEADER>();
+    let size = match unsafe { *section.Misc.VirtualSize() } {
+      0 => section.SizeOfRawData,
+      size => size
+    };
+
+    put(image, header + SIZE_OF_RAW_DATA, &size.to_le_bytes())?;
+    put(image, header + POINTER_TO_RAW_DATA, &section.VirtualAddress.to_le_bytes())?;
+  }
+
+  Ok(())
+}
+
+fn put(image: &mut [u8], offset: usize, bytes: &[u8]) -> io::Result<()> {
+  match image.get_mut(offset..offset + bytes.len()) {
+    Some(target) => {
+      target.copy_from_slice(bytes);
+      Ok(())
+    },
+    None => Err(io::Error::new(
+      io::ErrorKind::UnexpectedEof,
+      format!("offset 0x{:X} is out of image bounds", offset)
+    ))
+  }
+}
+
+impl Process {
+  /// Writes the module's image as it is in memory to `out`, see [`Process::dump_module_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// process.dump_module(&main, Path::new("dump.bin")).expect("Couldn't dump");
+  /// ```
+  pub fn dump_module(&self, module: &Module, out: &Path) -> io::Result<()> {
+    self.dump_module_with(module, out, DumpOptions::default())
+  }
+
+  /// Reads `size` bytes from the module's base and writes them to `out`
+  /// 
+  /// Pages which can't be read, like discarded or no-access ones, are dumped
+  /// as zeroes. Without [`DumpOptions::fix_headers`] the dump keeps the
+  /// virtual layout under headers which still describe the raw one. With
+  /// [`DumpOptions::suspend`] the process is resumed once the image is read.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::{DumpOptions, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// process.dump_module_with(&main, Path::new("dump.exe"), DumpOptions { fix_headers: true, ..Default::default() }).expect("Couldn't dump");
+  /// ```
+  pub fn dump_module_with(&self, module: &Module, out: &Path, options: DumpOptions) -> io::Result<()> {
+    let mut image = vec![0u8; module.size];
+
+    let guard = match options.suspend {
+      true => Some(self.suspend()?),
+      false => None
+    };
+    let mut offset = 0;
+    while offset < image.len() {
+      let region = self.query(module.address + offset)?;
+      let len = (region.address + region.size - (module.address + offset)).min(image.len() - offset);
+      if region.is_committed() && region.is_readable() {
+        let _ = self.read_into(module.address + offset, &mut image[offset..offset + len]);
+      }
+      offset += len;
+    }
+    drop(guard);
+
+    if options.fix_headers {
+      fix_headers(&mut image, module.address)?;
+    }
+
+    fs::write(out, image)
+  }
+}
@@ -0,0 +1,46 @@
+use std::io;
+
+use crate::Process;
+
+macro_rules! endian_access {
+  ($ty:ty, $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident) => {
+    #[doc = concat!("Reads little-endian `", stringify!($ty), "`, regardless of the host layout")]
+    pub fn $read_le(&self, address: usize) -> io::Result<$ty> {
+      Ok(<$ty>::from_le_bytes(self.read_array_n(address)?))
+    }
+
+    #[doc = concat!("Reads big-endian `", stringify!($ty), "`, regardless of the host layout")]
+    pub fn $read_be(&self, address: usize) -> io::Result<$ty> {
+      Ok(<$ty>::from_be_bytes(self.read_array_n(address)?))
+    }
+
+    #[doc = concat!("Writes `", stringify!($ty), "` as little-endian bytes, fails unless all of them were written")]
+    pub fn $write_le(&self, address: usize, value: $ty) -> io::Result<()> {
+      self.write_all(address, &value.to_le_bytes())
+    }
+
+    #[doc = concat!("Writes `", stringify!($ty), "` as big-endian bytes, fails unless all of them were written")]
+    pub fn $write_be(&self, address: usize, value: $ty) -> io::Result<()> {
+      self.write_all(address, &value.to_be_bytes())
+    }
+  };
+}
+
+/// Explicit-endianness integer access
+/// 
+/// Windows targets are little-endian on every architecture, so the `_le`
+/// variants match [`Process::try_read`], these just make intent explicit for
+/// serialized or foreign data.
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// let process = Process::find("process.exe").expect("no such process");
+/// let port = process.read_u16_be(0x1000).expect("Couldn't read memory");
+/// process.write_u32_le(0x1004, 0xDEADBEEF).expect("Couldn't write memory");
+/// ```
+impl Process {
+  endian_access!(u16, read_u16_le, read_u16_be, write_u16_le, write_u16_be);
+  endian_access!(u32, read_u32_le, read_u32_be, write_u32_le, write_u32_be);
+  endian_access!(u64, read_u64_le, read_u64_be, write_u64_le, write_u64_be);
+}
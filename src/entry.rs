@@ -0,0 +1,174 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+
+use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::um::processthreadsapi::ProcessIdToSessionId;
+use winapi::um::tlhelp32::CreateToolhelp32Snapshot;
+use winapi::um::tlhelp32::PROCESSENTRY32;
+use winapi::um::tlhelp32::Process32First;
+use winapi::um::tlhelp32::Process32Next;
+use winapi::um::tlhelp32::TH32CS_SNAPPROCESS;
+
+use crate::handle::HandleGuard;
+use crate::CuralError;
+use crate::Process;
+
+/// Returns session of the process by id alone, without opening it
+pub(crate) fn session_of(id: u32) -> io::Result<u32> {
+  let mut session = 0;
+  if unsafe { ProcessIdToSessionId(id, &mut session) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  Ok(session)
+}
+
+/// Lightweight snapshot entry of a process, no handle is opened for it
+#[derive(Clone)]
+pub struct ProcessEntry {
+  pub(crate) id: u32,
+  pub(crate) parent_id: u32,
+  pub(crate) name: String,
+  pub(crate) session: Option<u32>
+}
+
+impl ProcessEntry {
+  /// Opens full process from the entry
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let entry = Process::iter().expect("Couldn't create snapshot")
+  ///   .find(|entry| entry.get_name() == "process.exe")
+  ///   .expect("no such process");
+  /// let process = entry.open().expect("Couldn't open process");
+  /// ```
+  pub fn open(&self) -> io::Result<Process> {
+    Process::from_entry(self)
+  }
+
+  /// Returns id field of process
+  pub fn get_id(&self) -> &u32 {
+    &self.id
+  }
+
+  /// Returns id of the process which created this one
+  pub fn parent_id(&self) -> u32 {
+    self.parent_id
+  }
+
+  /// Returns name field of process
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  /// Returns terminal services session of the process, 0 for services
+  /// 
+  /// Queried when the snapshot was taken, fails with
+  /// [`io::ErrorKind::NotFound`] if it couldn't be then
+  pub fn session_id(&self) -> io::Result<u32> {
+    self.session.ok_or_else(|| io::Error::new(
+      io::ErrorKind::NotFound,
+      format!("session of {} couldn't be queried", self)
+    ))
+  }
+}
+
+impl From<&PROCESSENTRY32> for ProcessEntry {
+  fn from(entry: &PROCESSENTRY32) -> Self {
+    let c_name = entry.szExeFile.into_iter()
+      .take_while(|byte| byte != &0)
+      .map(|byte| byte as u8 as char)
+      .collect::<String>();
+
+    Self {
+      id: entry.th32ProcessID,
+      parent_id: entry.th32ParentProcessID,
+      name: c_name,
+      session: session_of(entry.th32ProcessID).ok()
+    }
+  }
+}
+
+impl Display for ProcessEntry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}({})", self.name, self.id)
+  }
+}
+
+impl Debug for ProcessEntry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.session {
+      Some(session) => write!(f, "{} session {}", self, session),
+      None => Display::fmt(self, f)
+    }
+  }
+}
+
+/// Lazy iterator over process snapshot, see [`Process::iter`]
+pub struct ProcessIter {
+  snapshot: HandleGuard,
+  entry: PROCESSENTRY32,
+  started: bool
+}
+
+impl ProcessIter {
+  pub(crate) fn new() -> io::Result<Self> {
+    let mut entry = unsafe { mem::zeroed::<PROCESSENTRY32>() };
+    entry.dwSize = mem::size_of::<PROCESSENTRY32>() as u32;
+
+    let snapshot = unsafe {
+      CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+    };
+
+    if snapshot == INVALID_HANDLE_VALUE {
+      return Err(CuralError::Snapshot(io::Error::last_os_error()).into());
+    }
+
+    Ok(Self { snapshot: HandleGuard(snapshot), entry, started: false })
+  }
+}
+
+impl Iterator for ProcessIter {
+  type Item = ProcessEntry;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    // the entry is reused, the call could have clobbered its size
+    self.entry.dwSize = mem::size_of::<PROCESSENTRY32>() as u32;
+
+    let found = if self.started {
+      unsafe { Process32Next(self.snapshot.get(), &mut self.entry) }
+    } else {
+      self.started = true;
+      unsafe { Process32First(self.snapshot.get(), &mut self.entry) }
+    };
+
+    if found == 0 {
+      return None;
+    }
+
+    Some(ProcessEntry::from(&self.entry))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Process;
+
+  #[test]
+  fn all_contains_current_process() {
+    let processes = Process::all().expect("Couldn't get any process");
+    assert!(processes.iter().any(|process| process.get_id() == &std::process::id()));
+  }
+
+  #[test]
+  fn entry_debug_is_stable() {
+    let entry = Process::iter().expect("Couldn't create snapshot")
+      .find(|entry| entry.get_id() == &std::process::id())
+      .expect("no current process");
+    assert_eq!(format!("{:?}", entry), format!("{:?}", entry));
+    assert!(entry.session_id().is_ok());
+  }
+}
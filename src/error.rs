@@ -0,0 +1,113 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::io;
+
+/// Failure of a cural operation
+/// 
+/// Operations keep returning [`io::Result`], these travel inside the
+/// [`io::Error`] under a fitting [`io::ErrorKind`] so callers can match on them
+/// through [`CuralError::of`] or by converting with `CuralError::from`.
+/// 
+/// # Examples
+/// ```
+/// use cural::{CuralError, Process};
+/// match Process::find("process.exe") {
+///   Ok(process) => println!("found {}", process),
+///   Err(error) => match CuralError::from(error) {
+///     CuralError::ProcessNotFound(query) => println!("nothing matched {}", query),
+///     error => println!("couldn't look for it: {}", error)
+///   }
+/// }
+/// ```
+#[derive(Debug)]
+pub enum CuralError {
+  /// No process matched, holds what was looked for (`id 1234`, `name process.exe`)
+  ProcessNotFound(String),
+  /// No module matched, holds what was looked for (`name kernel32.dll`, `path C:\...`)
+  ModuleNotFound(String),
+  /// Read at `address` stopped after `got` of `wanted` bytes
+  ShortRead { address: usize, wanted: usize, got: usize },
+  /// Write at `address` stopped after `got` of `wanted` bytes
+  ShortWrite { address: usize, wanted: usize, got: usize },
+  /// Process `id` exited with `exit_code`, so it can't be read or written anymore
+  ProcessExited { id: u32, exit_code: u32 },
+  /// Token of process `id` couldn't be opened for querying
+  TokenAccessDenied(u32),
+  /// Pointer chain couldn't read the pointer of hop `hop` at `address`
+  BrokenChain { hop: usize, address: usize, error: io::Error },
+  /// `CreateToolhelp32Snapshot` failed
+  Snapshot(io::Error),
+  /// Any other OS error
+  Os(io::Error)
+}
+
+impl CuralError {
+  /// Returns the [`CuralError`] carried by an `io::Error` from this crate, if any
+  pub fn of(error: &io::Error) -> Option<&CuralError> {
+    error.get_ref().and_then(|inner| inner.downcast_ref::<CuralError>())
+  }
+
+  /// Returns the [`io::ErrorKind`] the error is reported with
+  pub fn kind(&self) -> io::ErrorKind {
+    match self {
+      Self::ProcessNotFound(_) | Self::ModuleNotFound(_) => io::ErrorKind::NotFound,
+      Self::ShortRead { .. } | Self::ShortWrite { .. } => io::ErrorKind::UnexpectedEof,
+      Self::ProcessExited { .. } => io::ErrorKind::BrokenPipe,
+      Self::TokenAccessDenied(_) => io::ErrorKind::PermissionDenied,
+      Self::BrokenChain { error, .. } | Self::Snapshot(error) | Self::Os(error) => error.kind()
+    }
+  }
+}
+
+impl Display for CuralError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::ProcessNotFound(query) => write!(f, "no process found with {}", query),
+      Self::ModuleNotFound(query) => write!(f, "no module with {}", query),
+      Self::ShortRead { address, wanted, got } => {
+        write!(f, "read at 0x{:X} stopped after 0x{:X} of 0x{:X} bytes", address, got, wanted)
+      },
+      Self::ShortWrite { address, wanted, got } => {
+        write!(f, "write at 0x{:X} stopped after 0x{:X} of 0x{:X} bytes", address, got, wanted)
+      },
+      Self::ProcessExited { id, exit_code } => write!(f, "process {} exited with 0x{:X}", id, exit_code),
+      Self::TokenAccessDenied(id) => write!(f, "access to the token of process {} is denied", id),
+      Self::BrokenChain { hop, address, error } => {
+        write!(f, "pointer chain broke at hop {} reading 0x{:X}: {}", hop, address, error)
+      },
+      Self::Snapshot(error) => write!(f, "Couldn't create snapshot: {}", error),
+      Self::Os(error) => write!(f, "{}", error)
+    }
+  }
+}
+
+impl Error for CuralError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      Self::BrokenChain { error, .. } | Self::Snapshot(error) | Self::Os(error) => Some(error),
+      _ => None
+    }
+  }
+}
+
+impl From<io::Error> for CuralError {
+  fn from(error: io::Error) -> Self {
+    if CuralError::of(&error).is_none() {
+      return Self::Os(error);
+    }
+
+    match error.into_inner().map(|inner| inner.downcast::<CuralError>()) {
+      Some(Ok(error)) => *error,
+      _ => unreachable!("checked to carry a CuralError")
+    }
+  }
+}
+
+impl From<CuralError> for io::Error {
+  fn from(error: CuralError) -> Self {
+    match error {
+      CuralError::Os(error) => error,
+      error => io::Error::new(error.kind(), error)
+    }
+  }
+}
@@ -0,0 +1,21 @@
+use winapi::shared::ntdef::HANDLE;
+use winapi::um::handleapi::CloseHandle;
+
+/// Closes the wrapped handle when dropped, so every exit path releases it
+pub(crate) struct HandleGuard(pub(crate) HANDLE);
+
+// Kernel handles aren't tied to the thread which opened them
+unsafe impl Send for HandleGuard {}
+unsafe impl Sync for HandleGuard {}
+
+impl HandleGuard {
+  pub(crate) fn get(&self) -> HANDLE {
+    self.0
+  }
+}
+
+impl Drop for HandleGuard {
+  fn drop(&mut self) {
+    unsafe { CloseHandle(self.0) };
+  }
+}
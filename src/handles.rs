@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::ptr;
+use std::time::Duration;
+
+use winapi::um::handleapi::DuplicateHandle;
+use winapi::um::processthreadsapi::GetCurrentProcess;
+
+use crate::handle::HandleGuard;
+use crate::ntdll;
+use crate::Process;
+
+/// How long a file name query may block before its worker is terminated
+const FILE_NAME_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Handle open in a process, see [`Process::handles`]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct HandleInfo {
+  pub(crate) value: usize,
+  pub(crate) granted_access: u32,
+  pub(crate) attributes: u32,
+  pub(crate) type_index: u16,
+  pub(crate) type_name: Option<String>,
+  pub(crate) name: Option<String>
+}
+
+impl HandleInfo {
+  /// Returns handle value as the process uses it
+  pub fn get_value(&self) -> &usize {
+    &self.value
+  }
+
+  /// Returns access mask the handle was opened with
+  pub fn get_granted_access(&self) -> &u32 {
+    &self.granted_access
+  }
+
+  /// Returns `OBJ_*` attributes, like `OBJ_INHERIT`
+  pub fn get_attributes(&self) -> &u32 {
+    &self.attributes
+  }
+
+  /// Returns index of the object type, the same for every handle of a type
+  pub fn get_type_index(&self) -> &u16 {
+    &self.type_index
+  }
+
+  /// Returns object type name like `File`, `Key` or `Event`, `None` when the
+  /// handle couldn't be duplicated
+  pub fn get_type_name(&self) -> Option<&str> {
+    self.type_name.as_deref()
+  }
+
+  /// Returns object name like `\Device\HarddiskVolume1\file.txt`, `None` for
+  /// unnamed objects and names which couldn't be queried in time
+  pub fn get_name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+}
+
+impl Display for HandleInfo {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "0x{:X} {} {}",
+      self.value,
+      self.type_name.as_deref().unwrap_or("?"),
+      self.name.as_deref().unwrap_or("")
+    )
+  }
+}
+
+impl Debug for HandleInfo {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Lists handles the process has open, with their object types and names
+  /// 
+  /// Handles come from the system-wide handle table and are duplicated into
+  /// the current process without access to query them, which needs
+  /// `PROCESS_DUP_HANDLE`. Handles which can't be duplicated are listed
+  /// without type and name. File names are queried on a worker thread
+  /// terminated after a short timeout, since synchronous pipes block the
+  /// query, so those are listed without a name.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// for handle in process.handles().expect("Couldn't list handles") {
+  ///   if handle.get_type_name() == Some("File") {
+  ///     println!("{:?}", handle);
+  ///   }
+  /// }
+  /// ```
+  pub fn handles(&self) -> io::Result<Vec<HandleInfo>> {
+    let entries = ntdll::process_handles(self.id)?;
+
+    Ok(
+      entries.into_iter()
+        .map(|entry| {
+          let (type_name, name) = match self.duplicate(entry.handle_value) {
+            Ok(local) => {
+              let type_name = ntdll::object_type_name(local.get()).ok();
+              let timeout = match type_name.as_deref() {
+                Some("File") => Some(FILE_NAME_TIMEOUT),
+                _ => None
+              };
+              (type_name, ntdll::object_name(local.get(), timeout).ok().flatten())
+            },
+            Err(_) => (None, None)
+          };
+
+          HandleInfo {
+            value: entry.handle_value,
+            granted_access: entry.granted_access,
+            attributes: entry.handle_attributes,
+            type_index: entry.object_type_index,
+            type_name,
+            name
+          }
+        })
+        .collect()
+    )
+  }
+
+  /// Lists handles like [`Process::handles`], grouped by type name
+  /// 
+  /// Handles without a known type are grouped under `#` and their type index
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// for (type_name, handles) in process.handles_by_type().expect("Couldn't list handles") {
+  ///   println!("{}: {}", type_name, handles.len());
+  /// }
+  /// ```
+  pub fn handles_by_type(&self) -> io::Result<BTreeMap<String, Vec<HandleInfo>>> {
+    let mut groups: BTreeMap<String, Vec<HandleInfo>> = BTreeMap::new();
+    for handle in self.handles()? {
+      let key = match &handle.type_name {
+        Some(type_name) => type_name.clone(),
+        None => format!("#{}", handle.type_index)
+      };
+      groups.entry(key).or_default().push(handle);
+    }
+
+    Ok(groups)
+  }
+
+  /// Duplicates a handle of the process into the current one without access
+  fn duplicate(&self, handle: usize) -> io::Result<HandleGuard> {
+    let mut local = ptr::null_mut();
+    if unsafe {
+      DuplicateHandle(
+        self.handle.get(),
+        handle as *mut _,
+        GetCurrentProcess(),
+        &mut local,
+        0,
+        0,
+        0
+      )
+    } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(HandleGuard(local))
+  }
+}
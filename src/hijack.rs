@@ -0,0 +1,359 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::Module;
+use crate::Process;
+
+/// Options of [`Process::inject_dll_hijack_with`]
+#[derive(Clone, Copy, Debug)]
+pub struct HijackOptions {
+  /// Try threads which aren't waiting first, they reach the stub sooner and
+  /// are less likely to hold loader or heap locks inside ntdll
+  pub prefer_running: bool,
+  /// How long to wait for the hijacked thread to run the stub
+  pub timeout: Duration
+}
+
+impl Default for HijackOptions {
+  fn default() -> Self {
+    Self { prefer_running: true, timeout: Duration::from_secs(5) }
+  }
+}
+
+impl Process {
+  /// Loads dll into the target by hijacking one of its threads, see [`Process::inject_dll_hijack_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let module = process.inject_dll_hijack(Path::new("payload.dll")).expect("Couldn't inject");
+  /// ```
+  pub fn inject_dll_hijack(&self, path: &Path) -> io::Result<Module> {
+    self.inject_dll_hijack_with(path, HijackOptions::default())
+  }
+
+  /// Loads dll into the target by redirecting an existing thread to a stub
+  /// which calls `LoadLibraryW` and returns to where the thread was
+  /// 
+  /// The thread is suspended, its instruction pointer is pointed at the stub
+  /// and the original one is pushed as the stub's return address. The stub
+  /// saves flags, volatile registers and `xmm0`-`xmm5`, so the thread continues
+  /// unaffected. Both x64 and WOW64 targets are supported from an x64 process,
+  /// other hosts fail with [`io::ErrorKind::Unsupported`].
+  /// 
+  /// A waiting thread only runs the stub once its wait completes, so the call
+  /// gives up after `timeout`. If the stub didn't start by then the original
+  /// context is restored. If the thread is still inside the stub once the dll
+  /// loaded or timed out, its memory is leaked rather than freed under it.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use std::time::Duration;
+  /// use cural::{HijackOptions, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let options = HijackOptions { prefer_running: true, timeout: Duration::from_secs(10) };
+  /// let module = process.inject_dll_hijack_with(Path::new("payload.dll"), options).expect("Couldn't inject");
+  /// ```
+  pub fn inject_dll_hijack_with(&self, path: &Path, options: HijackOptions) -> io::Result<Module> {
+    #[cfg(target_arch = "x86_64")]
+    return x64::inject(self, path, options);
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+      let _ = (path, options);
+      Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "thread hijacking is only supported from x64 processes"
+      ))
+    }
+  }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x64 {
+  use std::io;
+  use std::path::Path;
+  use std::thread;
+  use std::time::Duration;
+  use std::time::Instant;
+
+  use winapi::um::processthreadsapi::FlushInstructionCache;
+  use winapi::um::processthreadsapi::OpenThread;
+  use winapi::um::processthreadsapi::ResumeThread;
+  use winapi::um::processthreadsapi::SuspendThread;
+  use winapi::um::winbase::Wow64SuspendThread;
+  use winapi::um::winnt::HANDLE;
+  use winapi::um::winnt::PAGE_EXECUTE_READ;
+  use winapi::um::winnt::PAGE_READWRITE;
+  use winapi::um::winnt::THREAD_GET_CONTEXT;
+  use winapi::um::winnt::THREAD_SET_CONTEXT;
+  use winapi::um::winnt::THREAD_SUSPEND_RESUME;
+  use winapi::um::winuser::PostThreadMessageW;
+  use winapi::um::winuser::WM_NULL;
+
+  use super::HijackOptions;
+  use crate::handle::HandleGuard;
+  use crate::inject::absolute_path;
+  use crate::inject::check_dll_bitness;
+  use crate::ntdll;
+  use crate::thread::thread_ids;
+  use crate::thread::ThreadContext;
+  use crate::Module;
+  use crate::Process;
+
+  /// Offsets inside the data block: done flag, `LoadLibraryW` result, then the path
+  const DONE: usize = 0;
+  const RESULT: usize = 8;
+  const PATH: usize = 16;
+
+  /// How many times to check that the thread left the stub before leaking it
+  const LEAVE_ATTEMPTS: usize = 50;
+  const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+  fn save_xmm(stub: &mut Vec<u8>, store: bool) {
+    for register in 0..6u8 {
+      // movdqu [rsp + register * 16], xmmN / movdqu xmmN, [rsp + register * 16]
+      stub.extend_from_slice(&[0xF3, 0x0F, if store { 0x7F } else { 0x6F }, 0x44 | register << 3, 0x24, register * 16]);
+    }
+  }
+
+  fn x64_stub(load_library: usize, data: usize) -> Vec<u8> {
+    let mut stub = Vec::new();
+    // pushfq; push rax, rcx, rdx, r8, r9, r10, r11, rbx
+    stub.extend_from_slice(&[0x9C, 0x50, 0x51, 0x52, 0x41, 0x50, 0x41, 0x51, 0x41, 0x52, 0x41, 0x53, 0x53]);
+    // mov rbx, rsp; and rsp, -16; sub rsp, 0x60
+    stub.extend_from_slice(&[0x48, 0x89, 0xE3, 0x48, 0x83, 0xE4, 0xF0, 0x48, 0x83, 0xEC, 0x60]);
+    save_xmm(&mut stub, true);
+    // sub rsp, 0x20
+    stub.extend_from_slice(&[0x48, 0x83, 0xEC, 0x20]);
+    // mov rcx, path; mov rax, LoadLibraryW; call rax
+    stub.extend_from_slice(&[0x48, 0xB9]);
+    stub.extend_from_slice(&((data + PATH) as u64).to_le_bytes());
+    stub.extend_from_slice(&[0x48, 0xB8]);
+    stub.extend_from_slice(&(load_library as u64).to_le_bytes());
+    stub.extend_from_slice(&[0xFF, 0xD0]);
+    // mov [result], rax; mov al, 1; mov [done], al
+    stub.extend_from_slice(&[0x48, 0xA3]);
+    stub.extend_from_slice(&((data + RESULT) as u64).to_le_bytes());
+    stub.extend_from_slice(&[0xB0, 0x01, 0xA2]);
+    stub.extend_from_slice(&((data + DONE) as u64).to_le_bytes());
+    // add rsp, 0x20
+    stub.extend_from_slice(&[0x48, 0x83, 0xC4, 0x20]);
+    save_xmm(&mut stub, false);
+    // mov rsp, rbx; pop rbx, r11, r10, r9, r8, rdx, rcx, rax; popfq; ret
+    stub.extend_from_slice(&[0x48, 0x89, 0xDC]);
+    stub.extend_from_slice(&[0x5B, 0x41, 0x5B, 0x41, 0x5A, 0x41, 0x59, 0x41, 0x58, 0x5A, 0x59, 0x58, 0x9D, 0xC3]);
+    stub
+  }
+
+  fn x86_stub(load_library: usize, data: usize) -> Vec<u8> {
+    let mut stub = Vec::new();
+    // pushfd; pushad; mov ebp, esp; and esp, -16; sub esp, 0x60
+    stub.extend_from_slice(&[0x9C, 0x60, 0x89, 0xE5, 0x83, 0xE4, 0xF0, 0x83, 0xEC, 0x60]);
+    save_xmm(&mut stub, true);
+    // push path; mov eax, LoadLibraryW; call eax
+    stub.push(0x68);
+    stub.extend_from_slice(&((data + PATH) as u32).to_le_bytes());
+    stub.push(0xB8);
+    stub.extend_from_slice(&(load_library as u32).to_le_bytes());
+    stub.extend_from_slice(&[0xFF, 0xD0]);
+    // mov [result], eax; mov al, 1; mov [done], al
+    stub.push(0xA3);
+    stub.extend_from_slice(&((data + RESULT) as u32).to_le_bytes());
+    stub.extend_from_slice(&[0xB0, 0x01, 0xA2]);
+    stub.extend_from_slice(&((data + DONE) as u32).to_le_bytes());
+    save_xmm(&mut stub, false);
+    // mov esp, ebp; popad; popfd; ret
+    stub.extend_from_slice(&[0x89, 0xEC, 0x61, 0x9D, 0xC3]);
+    stub
+  }
+
+  /// Returns threads to try, ones which aren't waiting first when preferred
+  fn candidates(process: &Process, prefer_running: bool) -> io::Result<Vec<u32>> {
+    let mut threads = thread_ids(process.id)?;
+
+    if prefer_running {
+      if let Ok((_, information)) = ntdll::process_information(process.id) {
+        let waiting = |id: &u32| information.iter().any(|thread| {
+          thread.unique_thread as usize == *id as usize && thread.thread_state == ntdll::THREAD_STATE_WAITING
+        });
+        threads.sort_by_key(waiting);
+      }
+    }
+
+    Ok(threads)
+  }
+
+  fn suspend(thread: HANDLE, wow64: bool) -> io::Result<()> {
+    let count = if wow64 {
+      unsafe { Wow64SuspendThread(thread) }
+    } else {
+      unsafe { SuspendThread(thread) }
+    };
+
+    if count == u32::MAX {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+  }
+
+  fn resume(thread: HANDLE) {
+    unsafe { ResumeThread(thread) };
+  }
+
+  /// Waits until the thread isn't executing inside `range`, returns false if it never left
+  fn wait_outside(thread: HANDLE, wow64: bool, range: std::ops::Range<usize>) -> bool {
+    for _ in 0..LEAVE_ATTEMPTS {
+      if suspend(thread, wow64).is_err() {
+        return false;
+      }
+      let outside = ThreadContext::get(thread, wow64)
+        .map(|context| !range.contains(&context.instruction_pointer()));
+      resume(thread);
+
+      if outside.unwrap_or(false) {
+        return true;
+      }
+      thread::sleep(POLL_INTERVAL);
+    }
+
+    false
+  }
+
+  pub(super) fn inject(process: &Process, path: &Path, options: HijackOptions) -> io::Result<Module> {
+    let path = absolute_path(path)?;
+    check_dll_bitness(process, &path)?;
+
+    let wow64 = !process.is_x64()?;
+    let pointer_size = if wow64 { 4 } else { 8 };
+    let load_library = process.get_proc_address("kernel32.dll", "LoadLibraryW")?;
+
+    let (thread, id) = candidates(process, options.prefer_running)?
+      .into_iter()
+      .find_map(|id| {
+        let thread = unsafe { OpenThread(THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT | THREAD_SET_CONTEXT, 0, id) };
+        (!thread.is_null()).then(|| (HandleGuard(thread), id))
+      })
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("couldn't open any thread of {}({}) for hijacking", process.name, process.id)
+      ))?;
+
+    let wide = path.to_string_lossy();
+    let data = process.alloc(PATH + (wide.encode_utf16().count() + 1) * 2, PAGE_READWRITE)?;
+    if let Err(error) = process.write_wstring(data + PATH, &wide, true) {
+      let _ = process.free(data);
+      return Err(error);
+    }
+
+    let stub = if wow64 { x86_stub(load_library, data) } else { x64_stub(load_library, data) };
+    let code = match process.alloc(stub.len(), PAGE_READWRITE) {
+      Ok(code) => code,
+      Err(error) => {
+        let _ = process.free(data);
+        return Err(error);
+      }
+    };
+    let free = || {
+      let _ = process.free(code);
+      let _ = process.free(data);
+    };
+
+    let prepared = process.write_all(code, &stub)
+      .and_then(|_| process.protect(code, stub.len(), PAGE_EXECUTE_READ))
+      .map(|_| unsafe { FlushInstructionCache(process.handle.get(), code as *const _, stub.len()) });
+    if let Err(error) = prepared {
+      free();
+      return Err(error);
+    }
+
+    if let Err(error) = suspend(thread.get(), wow64) {
+      free();
+      return Err(error);
+    }
+
+    let original = match ThreadContext::get(thread.get(), wow64) {
+      Ok(context) => context,
+      Err(error) => {
+        resume(thread.get());
+        free();
+        return Err(error);
+      }
+    };
+
+    let mut hijacked = original.clone();
+    let return_address = original.stack_pointer() - pointer_size;
+    let redirected = if wow64 {
+      process.try_write(original.instruction_pointer() as u32, return_address)
+    } else {
+      process.try_write(original.instruction_pointer() as u64, return_address)
+    }
+    .and_then(|_| {
+      hijacked.set_stack_pointer(return_address);
+      hijacked.set_instruction_pointer(code);
+      hijacked.set(thread.get())
+    });
+    if let Err(error) = redirected {
+      let _ = original.set(thread.get());
+      resume(thread.get());
+      free();
+      return Err(error);
+    }
+    resume(thread.get());
+
+    let start = Instant::now();
+    let failure = loop {
+      // wakes up threads waiting for messages
+      unsafe { PostThreadMessageW(id, WM_NULL, 0, 0) };
+
+      match process.try_read::<u8>(data + DONE) {
+        Ok(0) => {},
+        Ok(_) => break None,
+        Err(error) => break Some(error)
+      }
+
+      if start.elapsed() >= options.timeout {
+        break Some(io::Error::new(
+          io::ErrorKind::TimedOut,
+          format!("thread {} didn't run the LoadLibraryW stub in time", id)
+        ));
+      }
+      thread::sleep(POLL_INTERVAL);
+    };
+
+    if let Some(error) = failure {
+      // the stub's memory can only be freed while the thread hasn't entered it
+      if suspend(thread.get(), wow64).is_ok() {
+        let not_started = ThreadContext::get(thread.get(), wow64)
+          .map(|context| context.instruction_pointer() == code)
+          .unwrap_or(false);
+        if not_started && original.set(thread.get()).is_ok() {
+          resume(thread.get());
+          free();
+        } else {
+          resume(thread.get());
+        }
+      }
+
+      return Err(error);
+    }
+
+    let result = process.try_read::<u64>(data + RESULT)?;
+    if wait_outside(thread.get(), wow64, code..code + stub.len()) {
+      free();
+    }
+
+    if result == 0 {
+      return Err(io::Error::other(
+        format!("LoadLibraryW failed for {} in the target", path.display())
+      ));
+    }
+
+    process.get_module_by_path(&path)
+  }
+}
@@ -0,0 +1,181 @@
+use std::ffi::CString;
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use winapi::shared::minwindef::FARPROC;
+use winapi::shared::minwindef::HMODULE;
+use winapi::shared::windef::HHOOK;
+use winapi::um::libloaderapi::FreeLibrary;
+use winapi::um::libloaderapi::GetProcAddress;
+use winapi::um::libloaderapi::LoadLibraryW;
+use winapi::um::winuser::PostThreadMessageW;
+use winapi::um::winuser::SetWindowsHookExW;
+use winapi::um::winuser::UnhookWindowsHookEx;
+use winapi::um::winuser::WH_GETMESSAGE;
+use winapi::um::winuser::HOOKPROC;
+use winapi::um::winuser::WM_NULL;
+
+use crate::inject::absolute_path;
+use crate::inject::check_dll_bitness;
+use crate::window::top_level_windows;
+use crate::window::window_owner;
+use crate::Module;
+use crate::Process;
+
+/// How long to wait for the hooked thread to load the dll
+const HOOK_WAIT: Duration = Duration::from_secs(5);
+/// Delay between module list polls, the trigger message is reposted each time
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// `WH_GETMESSAGE` hook installed by [`Process::inject_dll_hook`]
+/// 
+/// Dropping it unhooks and unloads the dll from the current process, the
+/// target usually unloads it the next time the hooked thread pumps messages
+pub struct HookInjection {
+  pub(crate) hook: HHOOK,
+  pub(crate) library: HMODULE,
+  pub(crate) module: Module,
+  pub(crate) thread_id: u32
+}
+
+impl HookInjection {
+  /// Returns dll module loaded in the target
+  pub fn get_module(&self) -> &Module {
+    &self.module
+  }
+
+  /// Returns id of the hooked target thread
+  pub fn get_thread_id(&self) -> &u32 {
+    &self.thread_id
+  }
+}
+
+impl Drop for HookInjection {
+  fn drop(&mut self) {
+    unsafe {
+      UnhookWindowsHookEx(self.hook);
+      FreeLibrary(self.library);
+    }
+  }
+}
+
+impl Display for HookInjection {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} hooked on thread {}", self.module.name, self.thread_id)
+  }
+}
+
+impl Debug for HookInjection {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Loads dll into the target with a `WH_GETMESSAGE` hook on a thread which owns a window
+  /// 
+  /// The dll is loaded into the current process first to resolve `export`, which
+  /// must be a `HOOKPROC` (it should call `CallNextHookEx`), so its `DllMain` runs
+  /// here too. The hook is scoped to one thread of the target owning a top-level
+  /// window, a `WM_NULL` message is posted to trigger it and the target's module
+  /// list is polled until the dll shows up. Hooks can't cross bitness, so the
+  /// current process, the target and the dll must all match.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find("notepad.exe").expect("no such process");
+  /// let injection = process.inject_dll_hook(Path::new("payload.dll"), "GetMsgProc").expect("Couldn't inject");
+  /// ```
+  pub fn inject_dll_hook(&self, path: &Path, export: &str) -> io::Result<HookInjection> {
+    if self.is_x64()? != cfg!(target_pointer_width = "64") {
+      return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("can't hook {}({}), its bitness differs from the current process", self.name, self.id)
+      ));
+    }
+
+    let path = absolute_path(path)?;
+    check_dll_bitness(self, &path)?;
+
+    let thread_id = top_level_windows()?
+      .into_iter()
+      .map(window_owner)
+      .find(|(process_id, _)| process_id == &self.id)
+      .map(|(_, thread_id)| thread_id)
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{}({}) owns no top-level window", self.name, self.id)
+      ))?;
+
+    let wide = OsStr::new(&path)
+      .encode_wide()
+      .chain(Some(0))
+      .collect::<Vec<u16>>();
+    let library = unsafe { LoadLibraryW(wide.as_ptr()) };
+    if library.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+
+    let name = CString::new(export).map_err(|_| io::Error::new(
+      io::ErrorKind::InvalidInput,
+      "export name contains a nul byte"
+    ));
+    let procedure = name.and_then(|name| {
+      let procedure = unsafe { GetProcAddress(library, name.as_ptr()) };
+      if procedure.is_null() {
+        return Err(io::Error::new(
+          io::ErrorKind::NotFound,
+          format!("no export with name {} in {}", export, path.display())
+        ));
+      }
+      Ok(procedure)
+    });
+    let procedure = match procedure {
+      Ok(procedure) => procedure,
+      Err(error) => {
+        unsafe { FreeLibrary(library) };
+        return Err(error);
+      }
+    };
+
+    let hook = unsafe {
+      SetWindowsHookExW(WH_GETMESSAGE, mem::transmute::<FARPROC, HOOKPROC>(procedure), library, thread_id)
+    };
+    if hook.is_null() {
+      let error = io::Error::last_os_error();
+      unsafe { FreeLibrary(library) };
+      return Err(error);
+    }
+
+    let start = Instant::now();
+    loop {
+      unsafe { PostThreadMessageW(thread_id, WM_NULL, 0, 0) };
+
+      if let Ok(module) = self.get_module_by_path(&path) {
+        return Ok(HookInjection { hook, library, module, thread_id });
+      }
+
+      if start.elapsed() >= HOOK_WAIT {
+        unsafe {
+          UnhookWindowsHookEx(hook);
+          FreeLibrary(library);
+        }
+        return Err(io::Error::new(
+          io::ErrorKind::TimedOut,
+          format!("{} didn't load into thread {} in time", path.display(), thread_id)
+        ));
+      }
+      thread::sleep(HOOK_POLL_INTERVAL);
+    }
+  }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::Process;
+use crate::Thread;
+
+/// Number of address slots, `Dr0`-`Dr3`
+const SLOTS: usize = 4;
+
+/// Access which triggers a hardware breakpoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HwBpKind {
+  /// Executing the instruction at the address, needs [`HwBpSize::Byte`]
+  Execute,
+  /// Writing any byte of the watched range
+  Write,
+  /// Reading or writing any byte of the watched range
+  ReadWrite
+}
+
+impl HwBpKind {
+  /// `Dr7` R/W bits
+  fn bits(&self) -> usize {
+    match self {
+      Self::Execute => 0b00,
+      Self::Write => 0b01,
+      Self::ReadWrite => 0b11
+    }
+  }
+}
+
+/// Length of the range watched by a hardware breakpoint, the address must be aligned to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HwBpSize {
+  Byte,
+  Word,
+  Dword,
+  /// Only available for x64 threads
+  Qword
+}
+
+impl HwBpSize {
+  /// Returns length in bytes
+  pub fn bytes(&self) -> usize {
+    match self {
+      Self::Byte => 1,
+      Self::Word => 2,
+      Self::Dword => 4,
+      Self::Qword => 8
+    }
+  }
+
+  /// `Dr7` LEN bits
+  fn bits(&self) -> usize {
+    match self {
+      Self::Byte => 0b00,
+      Self::Word => 0b01,
+      Self::Dword => 0b11,
+      Self::Qword => 0b10
+    }
+  }
+}
+
+/// Threads a hardware breakpoint is installed on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThreadSelection {
+  /// Every thread of the process at the time of installation
+  All,
+  /// Threads with these ids
+  Only(Vec<u32>)
+}
+
+/// Returns `Dr7` mask of the enable and control bits of `slot`
+fn slot_mask(slot: usize) -> usize {
+  (0b11 << (slot * 2)) | (0b1111 << (16 + slot * 4))
+}
+
+/// Breakpoint installation shared with the auto-apply thread
+struct Installation {
+  process: Process,
+  address: usize,
+  kind: HwBpKind,
+  size: HwBpSize,
+  /// Threads holding the breakpoint with their slot
+  applied: Mutex<Vec<(Thread, usize)>>,
+  /// Ids of threads installation was attempted on
+  tried: Mutex<HashSet<u32>>
+}
+
+impl Installation {
+  /// Installs into a free slot of the thread, returns the slot
+  fn apply(&self, thread: &Thread) -> io::Result<usize> {
+    self.tried.lock().unwrap_or_else(|error| error.into_inner()).insert(thread.id);
+
+    let _guard = thread.suspend_guard()?;
+    let mut context = thread.context()?;
+    if context.is_wow64() && self.size == HwBpSize::Qword {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{:?} is x86, which has no 8-byte hardware breakpoints", thread)
+      ));
+    }
+
+    let control = context.debug_register(7).unwrap_or_default();
+    let slot = (0..SLOTS)
+      .find(|slot| control & (0b11 << (slot * 2)) == 0)
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::ResourceBusy,
+        format!("all debug registers of {:?} are in use", thread)
+      ))?;
+
+    let control = (control & !slot_mask(slot))
+      | (1 << (slot * 2))
+      | (self.kind.bits() << (16 + slot * 4))
+      | (self.size.bits() << (18 + slot * 4));
+    context.set_debug_register(slot, self.address);
+    context.set_debug_register(7, control);
+    thread.set_context(&context)?;
+
+    self.applied.lock().unwrap_or_else(|error| error.into_inner()).push((thread.clone(), slot));
+    Ok(slot)
+  }
+
+  /// Clears the slot if it still holds this breakpoint
+  fn clear(&self, thread: &Thread, slot: usize) -> io::Result<()> {
+    let _guard = thread.suspend_guard()?;
+    let mut context = thread.context()?;
+    if context.debug_register(slot) != Some(self.address) {
+      return Ok(());
+    }
+
+    let control = context.debug_register(7).unwrap_or_default();
+    let status = context.debug_register(6).unwrap_or_default();
+    context.set_debug_register(slot, 0);
+    context.set_debug_register(7, control & !slot_mask(slot));
+    context.set_debug_register(6, status & !(1 << slot));
+    thread.set_context(&context)
+  }
+
+  /// Installs on threads not tried yet, returns how many got it
+  fn apply_new(&self) -> io::Result<usize> {
+    let mut applied = 0;
+    for thread in self.process.threads()? {
+      let tried = self.tried.lock().unwrap_or_else(|error| error.into_inner()).contains(&thread.id);
+      if !tried && self.apply(&thread).is_ok() {
+        applied += 1;
+      }
+    }
+
+    Ok(applied)
+  }
+}
+
+/// Hardware breakpoint from [`Process::set_hardware_breakpoint`], dropping it clears
+/// the debug registers again
+/// 
+/// A hit raises `EXCEPTION_SINGLE_STEP` in the hitting thread, the process dies from
+/// it unless a debugger is attached or the target handles the exception itself.
+/// With [`Process::attach_debugger`] it's reported as a [`crate::DebugEvent::Exception`],
+/// continue it as handled to let the thread go on.
+pub struct HwBreakpoint {
+  installation: Arc<Installation>,
+  stop: Option<mpsc::Sender<()>>,
+  watcher: Option<JoinHandle<()>>
+}
+
+impl HwBreakpoint {
+  /// Returns watched address
+  pub fn get_address(&self) -> &usize {
+    &self.installation.address
+  }
+
+  /// Returns access which triggers the breakpoint
+  pub fn get_kind(&self) -> &HwBpKind {
+    &self.installation.kind
+  }
+
+  /// Returns length of the watched range
+  pub fn get_size(&self) -> &HwBpSize {
+    &self.installation.size
+  }
+
+  /// Returns ids of threads holding the breakpoint
+  pub fn thread_ids(&self) -> Vec<u32> {
+    self.installation.applied
+      .lock()
+      .unwrap_or_else(|error| error.into_inner())
+      .iter()
+      .map(|(thread, _)| thread.id)
+      .collect()
+  }
+
+  /// Returns did the last debug exception of the thread come from this breakpoint
+  /// 
+  /// Reads the `Dr6` status of the thread, so ask while it's stopped at the
+  /// `EXCEPTION_SINGLE_STEP` of a debug event. False for threads without the breakpoint.
+  pub fn is_hit_by(&self, thread_id: u32) -> io::Result<bool> {
+    let slot = self.installation.applied
+      .lock()
+      .unwrap_or_else(|error| error.into_inner())
+      .iter()
+      .find(|(thread, _)| thread.id == thread_id)
+      .map(|(thread, slot)| (thread.clone(), *slot));
+
+    match slot {
+      Some((thread, slot)) => Ok(thread.context()?.debug_register(6).unwrap_or_default() & (1 << slot) != 0),
+      None => Ok(false)
+    }
+  }
+
+  /// Installs the breakpoint on threads created since, returns how many got it
+  /// 
+  /// Threads which already failed once, like ones without a free slot, aren't retried
+  pub fn apply_new_threads(&self) -> io::Result<usize> {
+    self.installation.apply_new()
+  }
+
+  /// Calls [`HwBreakpoint::apply_new_threads`] every `interval` on a background
+  /// thread until the breakpoint is dropped
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::{HwBpKind, HwBpSize, Process, ThreadSelection};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut breakpoint = process.set_hardware_breakpoint(0x1000, HwBpKind::Write, HwBpSize::Dword, ThreadSelection::All)
+  ///   .expect("Couldn't set breakpoint");
+  /// breakpoint.auto_apply(Duration::from_millis(100));
+  /// ```
+  pub fn auto_apply(&mut self, interval: Duration) {
+    self.stop_watcher();
+
+    let installation = self.installation.clone();
+    let (stop, stopped) = mpsc::channel::<()>();
+    self.watcher = Some(thread::spawn(move || loop {
+      let _ = installation.apply_new();
+      match stopped.recv_timeout(interval) {
+        Err(RecvTimeoutError::Timeout) => continue,
+        _ => break
+      }
+    }));
+    self.stop = Some(stop);
+  }
+
+  fn stop_watcher(&mut self) {
+    // disconnecting wakes the thread up from its wait
+    self.stop.take();
+    if let Some(watcher) = self.watcher.take() {
+      let _ = watcher.join();
+    }
+  }
+}
+
+impl Drop for HwBreakpoint {
+  fn drop(&mut self) {
+    self.stop_watcher();
+
+    let applied = std::mem::take(&mut *self.installation.applied.lock().unwrap_or_else(|error| error.into_inner()));
+    for (thread, slot) in applied {
+      // exited threads took their registers with them
+      let _ = self.installation.clear(&thread, slot);
+    }
+  }
+}
+
+impl Display for HwBreakpoint {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{:?} {:?} breakpoint @ 0x{:X}",
+      self.installation.kind, self.installation.size, self.installation.address
+    )
+  }
+}
+
+impl Debug for HwBreakpoint {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Sets a hardware breakpoint on `address` through debug registers `Dr0`-`Dr3`/`Dr7`
+  /// of the selected threads, see [`HwBreakpoint`]
+  /// 
+  /// Each thread gets the breakpoint in its first free slot. Fails with
+  /// [`io::ErrorKind::ResourceBusy`] when a thread has all four slots in use and
+  /// with [`io::ErrorKind::InvalidInput`] for misaligned addresses or sized execute
+  /// breakpoints. Threads which don't exist anymore are skipped, any other failure
+  /// clears the threads done so far. Only available to x64 callers.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{HwBpKind, HwBpSize, Process, ThreadSelection};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let breakpoint = process.set_hardware_breakpoint(0x1000, HwBpKind::Write, HwBpSize::Dword, ThreadSelection::All)
+  ///   .expect("Couldn't set breakpoint");
+  /// println!("{:?} set on {:?}", breakpoint, breakpoint.thread_ids());
+  /// ```
+  pub fn set_hardware_breakpoint(&self, address: usize, kind: HwBpKind, size: HwBpSize, threads: ThreadSelection) -> io::Result<HwBreakpoint> {
+    if kind == HwBpKind::Execute && size != HwBpSize::Byte {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "execute breakpoints must be HwBpSize::Byte"
+      ));
+    }
+    if !address.is_multiple_of(size.bytes()) {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("0x{:X} isn't aligned to {} bytes", address, size.bytes())
+      ));
+    }
+
+    let selected = match threads {
+      ThreadSelection::All => self.threads()?,
+      ThreadSelection::Only(ids) => self.threads()?
+        .into_iter()
+        .filter(|thread| ids.contains(&thread.id))
+        .collect()
+    };
+
+    let breakpoint = HwBreakpoint {
+      installation: Arc::new(Installation {
+        process: self.clone(),
+        address,
+        kind,
+        size,
+        applied: Mutex::new(Vec::new()),
+        tried: Mutex::new(HashSet::new())
+      }),
+      stop: None,
+      watcher: None
+    };
+
+    for thread in selected {
+      if let Err(error) = breakpoint.installation.apply(&thread) {
+        if thread.is_alive().unwrap_or(true) {
+          // dropping clears the threads done so far
+          return Err(error);
+        }
+      }
+    }
+
+    Ok(breakpoint)
+  }
+}
@@ -0,0 +1,348 @@
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::libloaderapi::GetProcAddress;
+use winapi::um::processthreadsapi::OpenThread;
+use winapi::um::processthreadsapi::QueueUserAPC;
+use winapi::um::winnt::PAPCFUNC;
+use winapi::um::winnt::PAGE_READWRITE;
+use winapi::um::winnt::THREAD_SET_CONTEXT;
+
+use crate::handle::HandleGuard;
+use crate::ntdll;
+use crate::pe::NtHeaders;
+use crate::thread::thread_ids;
+use crate::Module;
+use crate::Process;
+
+/// Options of [`Process::eject_dll_with`]
+#[derive(Clone, Copy, Debug)]
+pub struct EjectOptions {
+  /// How long to wait for each remote `FreeLibrary` call, `None` waits forever
+  pub timeout: Option<Duration>,
+  /// How many times `FreeLibrary` is called for modules with a load count above one
+  pub max_attempts: u32
+}
+
+impl Default for EjectOptions {
+  fn default() -> Self {
+    Self { timeout: Some(Duration::from_secs(5)), max_attempts: 16 }
+  }
+}
+
+/// Options of [`Process::inject_dll_apc_with`]
+#[derive(Clone, Copy, Debug)]
+pub struct ApcOptions {
+  /// Queue the APC to every thread instead of only the first one
+  pub all_threads: bool,
+  /// Only queue to threads currently in a wait which may be alertable
+  pub alertable_only: bool,
+  /// How long to poll the module list for the dll
+  pub wait: Duration,
+  /// Delay between module list polls
+  pub poll_interval: Duration
+}
+
+impl Default for ApcOptions {
+  fn default() -> Self {
+    Self {
+      all_threads: true,
+      alertable_only: false,
+      wait: Duration::from_secs(5),
+      poll_interval: Duration::from_millis(50)
+    }
+  }
+}
+
+/// Makes path absolute and drops the `\\?\` prefix `canonicalize` adds,
+/// so it compares equal to module paths reported by snapshots
+pub(crate) fn absolute_path(path: &Path) -> io::Result<PathBuf> {
+  let path = fs::canonicalize(path)?;
+
+  Ok(match path.to_str().and_then(|path| path.strip_prefix(r"\\?\")) {
+    Some(stripped) if !stripped.starts_with("UNC") => PathBuf::from(stripped),
+    _ => path
+  })
+}
+
+/// Fails unless the dll file has the same bitness as the target
+pub(crate) fn check_dll_bitness(process: &Process, path: &Path) -> io::Result<()> {
+  let file = fs::read(path)?;
+  let dll_x64 = NtHeaders::read(file.as_slice(), 0)?.is_pe64();
+  let target_x64 = process.is_x64()?;
+
+  if dll_x64 != target_x64 {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidInput,
+      format!(
+        "can't inject {} dll {} into {} process {}",
+        if dll_x64 { "x64" } else { "x86" },
+        path.display(),
+        if target_x64 { "x64" } else { "x86" },
+        process.id
+      )
+    ));
+  }
+
+  Ok(())
+}
+
+fn local_load_library() -> Option<usize> {
+  let kernel32 = "kernel32.dll".encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+  let module = unsafe { GetModuleHandleW(kernel32.as_ptr()) };
+  if module.is_null() {
+    return None;
+  }
+
+  match unsafe { GetProcAddress(module, c"LoadLibraryW".as_ptr()) } as usize {
+    0 => None,
+    address => Some(address)
+  }
+}
+
+impl Process {
+  /// Unloads module from the target by calling `FreeLibrary` on a remote thread
+  /// 
+  /// Fails if `FreeLibrary` reports failure, e.g. when the module refuses to unload
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let module = process.get_module("payload.dll").expect("no such dll");
+  /// process.free_library(&module).expect("Couldn't unload");
+  /// ```
+  pub fn free_library(&self, module: &Module) -> io::Result<()> {
+    let free_library = self.get_proc_address("kernel32.dll", "FreeLibrary")?;
+
+    if self.call_routine(free_library, module.address)? == 0 {
+      return Err(io::Error::other(
+        format!("FreeLibrary failed for {} in the target", module.name)
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Loads dll into the target with `LoadLibraryW` on a remote thread and
+  /// returns the loaded module
+  /// 
+  /// The path is made absolute and the dll bitness is checked against the
+  /// target before injecting. The remote path buffer is freed afterwards.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let module = process.inject_dll(Path::new("payload.dll")).expect("Couldn't inject");
+  /// ```
+  pub fn inject_dll(&self, path: &Path) -> io::Result<Module> {
+    let path = absolute_path(path)?;
+    check_dll_bitness(self, &path)?;
+
+    self.load_library(&path.to_string_lossy())?;
+    self.get_module_by_path(&path)
+  }
+
+  /// Loads dll into the target by queueing `LoadLibraryW` APCs, see [`Process::inject_dll_apc_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// process.inject_dll_apc(Path::new("payload.dll")).expect("Couldn't inject");
+  /// ```
+  pub fn inject_dll_apc(&self, path: &Path) -> io::Result<()> {
+    self.inject_dll_apc_with(path, ApcOptions::default()).map(|_| ())
+  }
+
+  /// Loads dll into the target by queueing `LoadLibraryW` APCs to its threads
+  /// instead of creating a remote thread, returns the loaded module
+  /// 
+  /// This is best-effort: an APC only runs once its thread enters an alertable
+  /// wait (`SleepEx`, `WaitForSingleObjectEx`, ... with `bAlertable`), which
+  /// many threads never do. The module list is polled for `wait` and an
+  /// [`io::ErrorKind::TimedOut`] error is returned if the dll didn't show up,
+  /// it may still load later. Threads which can't be opened with
+  /// `THREAD_SET_CONTEXT` are skipped, failing with [`io::ErrorKind::NotFound`]
+  /// when no APC could be queued. `alertable_only` relies on the current wait
+  /// reason of each thread and can't tell alertable waits from plain ones.
+  /// Targets whose bitness differs from the current process fail with
+  /// [`io::ErrorKind::Unsupported`].
+  /// 
+  /// The remote path buffer is never freed since pending APCs may still read it.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::{ApcOptions, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let options = ApcOptions { alertable_only: true, ..Default::default() };
+  /// let module = process.inject_dll_apc_with(Path::new("payload.dll"), options).expect("Couldn't inject");
+  /// ```
+  pub fn inject_dll_apc_with(&self, path: &Path, options: ApcOptions) -> io::Result<Module> {
+    // a WOW64 target would run the x86 LoadLibraryW as a native x64 APC routine
+    if self.is_x64()? != cfg!(target_pointer_width = "64") {
+      return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("can't queue APCs to {}({}), its bitness differs from the current process", self.name, self.id)
+      ));
+    }
+
+    let path = absolute_path(path)?;
+    check_dll_bitness(self, &path)?;
+
+    let mut threads = thread_ids(self.id)?;
+    if options.alertable_only {
+      let (_, information) = ntdll::process_information(self.id)?;
+      threads.retain(|id| information.iter().any(|thread| {
+        thread.unique_thread as usize == *id as usize
+          && thread.thread_state == ntdll::THREAD_STATE_WAITING
+          && ntdll::ALERTABLE_WAIT_REASONS.contains(&thread.wait_reason)
+      }));
+    }
+
+    let load_library = self.get_proc_address("kernel32.dll", "LoadLibraryW")?;
+    let wide = path.to_string_lossy();
+    let buffer = self.alloc((wide.encode_utf16().count() + 1) * 2, PAGE_READWRITE)?;
+    if let Err(error) = self.write_wstring(buffer, &wide, true) {
+      let _ = self.free(buffer);
+      return Err(error);
+    }
+
+    let routine = unsafe { mem::transmute::<usize, PAPCFUNC>(load_library) };
+    let mut queued = 0;
+    for id in threads {
+      let thread = unsafe { OpenThread(THREAD_SET_CONTEXT, 0, id) };
+      if thread.is_null() {
+        continue;
+      }
+      let thread = HandleGuard(thread);
+
+      if unsafe { QueueUserAPC(routine, thread.get(), buffer) } != 0 {
+        queued += 1;
+        if !options.all_threads {
+          break;
+        }
+      }
+    }
+
+    if queued == 0 {
+      let _ = self.free(buffer);
+      return Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("couldn't queue an APC to any thread of {}", self.id)
+      ));
+    }
+
+    let start = Instant::now();
+    loop {
+      if let Ok(module) = self.get_module_by_path(&path) {
+        return Ok(module);
+      }
+
+      if start.elapsed() >= options.wait {
+        return Err(io::Error::new(
+          io::ErrorKind::TimedOut,
+          format!("{} didn't load after APCs were queued to {} threads", path.display(), queued)
+        ));
+      }
+      thread::sleep(options.poll_interval);
+    }
+  }
+
+  /// Calls `LoadLibraryW` on a remote thread, returns the low 32 bits of the
+  /// loaded module handle
+  pub(crate) fn load_library(&self, name: &str) -> io::Result<u32> {
+    let load_library = match self.get_proc_address("kernel32.dll", "LoadLibraryW") {
+      Ok(address) => address,
+      // kernel32 isn't listed yet in a process spawned suspended, but it's mapped
+      // at the same address as here once the loader runs
+      Err(error) if self.is_x64()? == cfg!(target_pointer_width = "64") => {
+        local_load_library().ok_or(error)?
+      },
+      Err(error) => return Err(error)
+    };
+
+    let buffer = self.alloc((name.encode_utf16().count() + 1) * 2, PAGE_READWRITE)?;
+    let loaded = self.write_wstring(buffer, name, true)
+      .and_then(|_| self.call_routine(load_library, buffer));
+    self.free(buffer)?;
+
+    match loaded? {
+      0 => Err(io::Error::other(
+        format!("LoadLibraryW failed for {} in the target", name)
+      )),
+      handle => Ok(handle)
+    }
+  }
+
+  /// Unloads dll from the target and verifies it's gone, see [`Process::eject_dll_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let module = process.inject_dll(Path::new("payload.dll")).expect("Couldn't inject");
+  /// process.eject_dll(&module).expect("Couldn't eject");
+  /// ```
+  pub fn eject_dll(&self, module: &Module) -> io::Result<()> {
+    self.eject_dll_with(module, EjectOptions::default())
+  }
+
+  /// Unloads dll from the target and verifies it's gone
+  /// 
+  /// `FreeLibrary` is called on a remote thread until a fresh module snapshot
+  /// no longer contains the module, at most `max_attempts` times. Ejecting
+  /// the main executable module is refused.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::{EjectOptions, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let module = process.get_module("payload.dll").expect("no such dll");
+  /// let options = EjectOptions { timeout: Some(Duration::from_secs(1)), max_attempts: 4 };
+  /// process.eject_dll_with(&module, options).expect("Couldn't eject");
+  /// ```
+  pub fn eject_dll_with(&self, module: &Module, options: EjectOptions) -> io::Result<()> {
+    if self.base_address()? == module.address {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("refusing to eject main module {}", module.name)
+      ));
+    }
+
+    let free_library = self.get_proc_address("kernel32.dll", "FreeLibrary")?;
+
+    for _ in 0..options.max_attempts {
+      if self.call_routine_timeout(free_library, module.address, options.timeout)? == 0 {
+        return Err(io::Error::other(
+          format!("FreeLibrary failed for {} in the target", module.name)
+        ));
+      }
+
+      let loaded = self.get_all_modules()?
+        .into_iter()
+        .any(|loaded| loaded.address == module.address);
+      if !loaded {
+        return Ok(());
+      }
+    }
+
+    Err(io::Error::other(format!(
+      "{} is still loaded after {} FreeLibrary calls",
+      module.name, options.max_attempts
+    )))
+  }
+}
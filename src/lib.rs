@@ -2,8 +2,144 @@
 mod process;
 #[cfg(target_os = "windows")]
 mod module;
+#[cfg(all(target_os = "windows", target_arch = "x86_64"))]
+mod backtrace;
+#[cfg(target_os = "windows")]
+mod builder;
+#[cfg(target_os = "windows")]
+mod cache;
+#[cfg(target_os = "windows")]
+mod clr;
+#[cfg(all(target_os = "windows", target_arch = "x86_64"))]
+mod debugger;
+#[cfg(target_os = "windows")]
+mod diff;
+#[cfg(target_os = "windows")]
+mod dump;
+#[cfg(target_os = "windows")]
+mod endian;
+#[cfg(target_os = "windows")]
+mod entry;
+#[cfg(target_os = "windows")]
+mod error;
+#[cfg(target_os = "windows")]
+mod handle;
+#[cfg(target_os = "windows")]
+mod handles;
+#[cfg(target_os = "windows")]
+mod hijack;
+#[cfg(target_os = "windows")]
+mod hook;
+#[cfg(all(target_os = "windows", target_arch = "x86_64"))]
+mod hw_breakpoint;
+#[cfg(target_os = "windows")]
+mod inject;
+#[cfg(target_os = "windows")]
+mod logging;
+#[cfg(target_os = "windows")]
+mod manual_map;
+#[cfg(target_os = "windows")]
+mod mitigation;
+#[cfg(all(target_os = "windows", feature = "async"))]
+mod nonblocking;
+#[cfg(target_os = "windows")]
+mod ntdll;
+#[cfg(target_os = "windows")]
+mod patch;
+#[cfg(target_os = "windows")]
+mod pe;
+#[cfg(target_os = "windows")]
+mod peb;
+#[cfg(target_os = "windows")]
+mod pod;
+#[cfg(target_os = "windows")]
+mod region;
+#[cfg(target_os = "windows")]
+mod remote;
+#[cfg(target_os = "windows")]
+mod remote_box;
+#[cfg(target_os = "windows")]
+mod scan;
+#[cfg(target_os = "windows")]
+mod spawn;
+#[cfg(target_os = "windows")]
+mod suspend;
+#[cfg(target_os = "windows")]
+mod thread;
+#[cfg(target_os = "windows")]
+mod token;
+#[cfg(target_os = "windows")]
+mod tree;
+#[cfg(target_os = "windows")]
+mod watch;
+#[cfg(target_os = "windows")]
+mod window;
 
 #[cfg(target_os = "windows")]
-pub use process::Process;
+pub use process::{Process, WaitResult};
+#[cfg(target_os = "windows")]
+pub use module::{BoundModule, Module, ModuleEnumStrategy, ModuleFilter, Modules, SnapshotOptions};
+#[cfg(target_os = "windows")]
+pub use entry::{ProcessEntry, ProcessIter};
+#[cfg(target_os = "windows")]
+pub use pe::{Export, ImportName, ImportedFunction, ImportedModule, Machine, PeInfo, Section};
+#[cfg(target_os = "windows")]
+pub use scan::{CodeCave, Scan, ScanOptions};
+#[cfg(target_os = "windows")]
+pub use region::MemoryRegion;
+#[cfg(target_os = "windows")]
+pub use diff::{CodeDiff, DiffOptions};
+#[cfg(target_os = "windows")]
+pub use cache::ReadCache;
+#[cfg(target_os = "windows")]
+pub use inject::{ApcOptions, EjectOptions};
+#[cfg(target_os = "windows")]
+pub use manual_map::ManualMapOptions;
+#[cfg(target_os = "windows")]
+pub use pod::Pod;
+#[cfg(target_os = "windows")]
+pub use remote::{CallingConvention, RemoteArg, ShellcodeOptions};
+#[cfg(target_os = "windows")]
+pub use thread::{MainThreadStrategy, RemoteThread, SuspendGuard, Thread, ThreadInfo, ThreadTimes};
+#[cfg(target_os = "windows")]
+pub use hook::HookInjection;
+#[cfg(target_os = "windows")]
+pub use hijack::HijackOptions;
+#[cfg(target_os = "windows")]
+pub use spawn::{SpawnOptions, SpawnedProcess};
+#[cfg(target_os = "windows")]
+pub use remote_box::{RemoteArray, RemoteBox};
+#[cfg(target_os = "windows")]
+pub use builder::ProcessBuilder;
+#[cfg(target_os = "windows")]
+pub use suspend::ProcessSuspendGuard;
+#[cfg(all(target_os = "windows", any(target_arch = "x86_64", target_arch = "x86")))]
+pub use thread::{AlignedContext, ThreadContext};
+#[cfg(target_os = "windows")]
+pub use watch::WatchHandle;
+#[cfg(all(target_os = "windows", target_arch = "x86_64"))]
+pub use backtrace::Frame;
+#[cfg(all(target_os = "windows", target_arch = "x86_64"))]
+pub use hw_breakpoint::{HwBpKind, HwBpSize, HwBreakpoint, ThreadSelection};
+#[cfg(all(target_os = "windows", feature = "async"))]
+pub use nonblocking::WatchStream;
+#[cfg(all(target_os = "windows", target_arch = "x86_64"))]
+pub use debugger::{BreakpointHit, BreakpointId, DebugEvent, Debugger, StepResult};
+#[cfg(target_os = "windows")]
+pub use error::CuralError;
+#[cfg(target_os = "windows")]
+pub use patch::Patch;
+#[cfg(target_os = "windows")]
+pub use tree::{process_tree, KillReport, ProcessTree};
+#[cfg(target_os = "windows")]
+pub use token::{current_process_is_elevated, IntegrityLevel, Owner};
+#[cfg(target_os = "windows")]
+pub use dump::DumpOptions;
+#[cfg(target_os = "windows")]
+pub use mitigation::{Mitigations, SignaturePolicy};
+#[cfg(target_os = "windows")]
+pub use clr::{ClrFlavor, ClrInfo};
+#[cfg(target_os = "windows")]
+pub use peb::{LdrEntry, LdrModules, Peb};
 #[cfg(target_os = "windows")]
-pub use module::Module;
\ No newline at end of file
+pub use handles::HandleInfo;
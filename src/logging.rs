@@ -0,0 +1,15 @@
+use std::io;
+
+/// Records failed memory operation when the `log` feature is enabled and
+/// returns the error back
+#[cfg(feature = "log")]
+pub(crate) fn failed(operation: &str, address: usize, size: usize, error: io::Error) -> io::Error {
+  log::debug!("{} of 0x{:X} bytes at 0x{:X} failed: {}", operation, size, address, error);
+  error
+}
+
+#[cfg(not(feature = "log"))]
+#[inline(always)]
+pub(crate) fn failed(_operation: &str, _address: usize, _size: usize, error: io::Error) -> io::Error {
+  error
+}
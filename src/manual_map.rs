@@ -0,0 +1,330 @@
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+use winapi::um::winnt::DLL_PROCESS_ATTACH;
+use winapi::um::winnt::IMAGE_DIRECTORY_ENTRY_EXCEPTION;
+use winapi::um::winnt::IMAGE_DIRECTORY_ENTRY_IMPORT;
+use winapi::um::winnt::IMAGE_DIRECTORY_ENTRY_TLS;
+use winapi::um::winnt::IMAGE_FILE_MACHINE_AMD64;
+use winapi::um::winnt::IMAGE_IMPORT_DESCRIPTOR;
+use winapi::um::winnt::IMAGE_ORDINAL_FLAG64;
+use winapi::um::winnt::IMAGE_SCN_MEM_EXECUTE;
+use winapi::um::winnt::IMAGE_SCN_MEM_READ;
+use winapi::um::winnt::IMAGE_SCN_MEM_WRITE;
+use winapi::um::winnt::IMAGE_SECTION_HEADER;
+use winapi::um::winnt::IMAGE_TLS_DIRECTORY64;
+use winapi::um::winnt::PAGE_EXECUTE;
+use winapi::um::winnt::PAGE_EXECUTE_READ;
+use winapi::um::winnt::PAGE_EXECUTE_READWRITE;
+use winapi::um::winnt::PAGE_NOACCESS;
+use winapi::um::winnt::PAGE_READONLY;
+use winapi::um::winnt::PAGE_READWRITE;
+
+use crate::pe::apply_relocation;
+use crate::pe::relocations;
+use crate::pe::is_api_set;
+use crate::pe::ImageSource;
+use crate::pe::NtHeaders;
+use crate::pe::MAX_NAME_LEN;
+use crate::Process;
+use crate::ShellcodeOptions;
+
+/// Size of one x64 `RUNTIME_FUNCTION` entry of the exception directory
+const RUNTIME_FUNCTION_SIZE: usize = 12;
+
+/// Options of [`Process::manual_map`]
+#[derive(Clone, Copy, Debug)]
+pub struct ManualMapOptions {
+  /// Run TLS callbacks with `DLL_PROCESS_ATTACH` before `DllMain`
+  pub tls_callbacks: bool,
+  /// Call `DllMain` with `DLL_PROCESS_ATTACH`
+  pub dll_main: bool
+}
+
+impl Default for ManualMapOptions {
+  fn default() -> Self {
+    Self { tls_callbacks: true, dll_main: true }
+  }
+}
+
+/// Returns `PAGE_*` protection matching section characteristics
+fn section_protection(characteristics: u32) -> u32 {
+  let execute = characteristics & IMAGE_SCN_MEM_EXECUTE != 0;
+  let read = characteristics & IMAGE_SCN_MEM_READ != 0;
+  let write = characteristics & IMAGE_SCN_MEM_WRITE != 0;
+
+  match (execute, read, write) {
+    (true, _, true) => PAGE_EXECUTE_READWRITE,
+    (true, true, false) => PAGE_EXECUTE_READ,
+    (true, false, false) => PAGE_EXECUTE,
+    (false, _, true) => PAGE_READWRITE,
+    (false, true, false) => PAGE_READONLY,
+    (false, false, false) => PAGE_NOACCESS
+  }
+}
+
+/// Reads nul-terminated string out of a local image
+fn image_c_string(image: &[u8], offset: usize) -> io::Result<String> {
+  let bytes = image.get(offset..)
+    .ok_or_else(|| io::Error::new(
+      io::ErrorKind::UnexpectedEof,
+      format!("offset 0x{:X} is out of image bounds", offset)
+    ))?;
+  let bytes = &bytes[..bytes.len().min(MAX_NAME_LEN)];
+  let len = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+
+  Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+}
+
+/// Lays out headers and sections of the file at their rvas
+fn build_image(file: &[u8], headers: &NtHeaders, sections: &[IMAGE_SECTION_HEADER]) -> io::Result<Vec<u8>> {
+  let mut image = vec![0u8; headers.size_of_image() as usize];
+
+  let headers_len = (headers.size_of_headers() as usize).min(file.len()).min(image.len());
+  image[..headers_len].copy_from_slice(&file[..headers_len]);
+
+  for section in sections.iter().filter(|section| section.SizeOfRawData != 0) {
+    let raw = section.PointerToRawData as usize;
+    let rva = section.VirtualAddress as usize;
+    let len = section.SizeOfRawData as usize;
+
+    let data = raw.checked_add(len)
+      .and_then(|end| file.get(raw..end))
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        "section raw data is outside of the file"
+      ))?;
+    let target = image.get_mut(rva..)
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("section at rva 0x{:X} is outside of the image", rva)
+      ))?;
+    let len = len.min(target.len());
+    target[..len].copy_from_slice(&data[..len]);
+  }
+
+  Ok(image)
+}
+
+/// Appends `mov reg, imm64` for rcx, rdx, r8 and rax, then `call rax`
+fn push_call(stub: &mut Vec<u8>, function: u64, args: [u64; 3]) {
+  for (prefix, arg) in [[0x48, 0xB9], [0x48, 0xBA], [0x49, 0xB8]].iter().zip(args) {
+    stub.extend_from_slice(prefix);
+    stub.extend_from_slice(&arg.to_le_bytes());
+  }
+
+  stub.extend_from_slice(&[0x48, 0xB8]);
+  stub.extend_from_slice(&function.to_le_bytes());
+  stub.extend_from_slice(&[0xFF, 0xD0]);
+}
+
+impl Process {
+  /// Maps dll into the target without `LoadLibrary` and returns the remote image base
+  /// 
+  /// Headers and sections are copied into freshly allocated memory, relocations
+  /// are applied for the actual base, imports are resolved against modules loaded
+  /// in the target (missing ones are loaded with `LoadLibraryW`) and sections get
+  /// their protections. A small bootstrap registers the exception table, runs TLS
+  /// callbacks and calls `DllMain` on a remote thread. The module is not linked into
+  /// the loader lists, so it doesn't show up in module snapshots.
+  /// 
+  /// Only x64 dlls mapped from an x64 process into an x64 target are supported,
+  /// other combinations fail with [`io::ErrorKind::Unsupported`]. Static TLS data
+  /// (`__declspec(thread)`) isn't set up.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::{ManualMapOptions, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let base = process.manual_map(Path::new("payload.dll"), ManualMapOptions::default()).expect("Couldn't map");
+  /// ```
+  pub fn manual_map(&self, dll_path: &Path, opts: ManualMapOptions) -> io::Result<usize> {
+    let file = fs::read(dll_path)?;
+    let headers = NtHeaders::read(file.as_slice(), 0)?;
+
+    if !cfg!(target_pointer_width = "64")
+      || !self.is_x64()?
+      || !headers.is_pe64()
+      || headers.file_header().Machine != IMAGE_FILE_MACHINE_AMD64 {
+      return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("manual mapping {} is only supported for x64 dlls in x64 targets", dll_path.display())
+      ));
+    }
+
+    let sections = headers.section_headers(file.as_slice())?;
+    let mut image = build_image(&file, &headers, &sections)?;
+
+    let base = self.alloc(image.len(), PAGE_READWRITE)?;
+    let mapped = self.map_image(&mut image, &headers, &sections, base, opts);
+    if mapped.is_err() {
+      let _ = self.free(base);
+    }
+
+    mapped.map(|_| base)
+  }
+
+  fn map_image(
+    &self,
+    image: &mut [u8],
+    headers: &NtHeaders,
+    sections: &[IMAGE_SECTION_HEADER],
+    base: usize,
+    opts: ManualMapOptions
+  ) -> io::Result<()> {
+    let delta = (base as u64).wrapping_sub(headers.image_base());
+    let image_len = image.len();
+    let relocations = relocations(image, headers, |rva| Some(rva).filter(|rva| *rva < image_len))?;
+    if relocations.is_empty() && delta != 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "dll has no relocations and can't be mapped at another base"
+      ));
+    }
+    for relocation in &relocations {
+      if !apply_relocation(image, relocation.rva, relocation, delta) {
+        return Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("unsupported relocation of kind {} at 0x{:X}", relocation.kind, relocation.rva)
+        ));
+      }
+    }
+
+    self.resolve_image_imports(image, headers)?;
+
+    self.write_all(base, image)?;
+    self.protect(base, headers.size_of_headers() as usize, PAGE_READONLY)?;
+    for section in sections {
+      let size = match unsafe { *section.Misc.VirtualSize() } {
+        0 => section.SizeOfRawData,
+        size => size
+      } as usize;
+      if size != 0 {
+        self.protect(base + section.VirtualAddress as usize, size, section_protection(section.Characteristics))?;
+      }
+    }
+
+    self.run_bootstrap(image, headers, base, opts)
+  }
+
+  /// Writes addresses of imported functions into the IAT of a local image
+  fn resolve_image_imports(&self, image: &mut [u8], headers: &NtHeaders) -> io::Result<()> {
+    let directory = match headers.data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT) {
+      Some(directory) => directory,
+      None => return Ok(())
+    };
+
+    let mut descriptor_offset = directory.VirtualAddress as usize;
+    loop {
+      let descriptor = image.read_image::<IMAGE_IMPORT_DESCRIPTOR>(descriptor_offset)?;
+      descriptor_offset += mem::size_of::<IMAGE_IMPORT_DESCRIPTOR>();
+      if descriptor.Name == 0 && descriptor.FirstThunk == 0 {
+        break;
+      }
+
+      let name = image_c_string(image, descriptor.Name as usize)?;
+      let module = self.import_module_name(&name)?;
+
+      let iat = descriptor.FirstThunk as usize;
+      let lookup = match unsafe { *descriptor.u.OriginalFirstThunk() } as usize {
+        0 => iat,
+        lookup => lookup
+      };
+
+      for index in 0.. {
+        let thunk = image.read_image::<u64>(lookup + index * 8)?;
+        if thunk == 0 {
+          break;
+        }
+
+        let address = if thunk & IMAGE_ORDINAL_FLAG64 != 0 {
+          self.get_proc_address_ordinal(&module, thunk as u16)?
+        } else {
+          // IMAGE_IMPORT_BY_NAME: u16 hint followed by the name
+          let function = image_c_string(image, (thunk as u32) as usize + 2)?;
+          self.get_proc_address(&module, &function)?
+        };
+
+        let offset = iat + index * 8;
+        image.get_mut(offset..offset + 8)
+          .ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("IAT entry at 0x{:X} is out of image bounds", offset)
+          ))?
+          .copy_from_slice(&(address as u64).to_le_bytes());
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Returns name of the loaded module satisfying an import, loading it when needed
+  fn import_module_name(&self, name: &str) -> io::Result<String> {
+    // api sets resolve to a differently named host module
+    let name = match is_api_set(name) {
+      true => self.api_set_host(name, "")?.ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("api set {} has no host", name)
+      ))?,
+      false => name.to_owned()
+    };
+
+    if let Ok(module) = self.get_module(&name) {
+      return Ok(module.name);
+    }
+
+    self.load_library(&name)?;
+    Ok(self.get_module(&name)?.name)
+  }
+
+  /// Registers exception table, runs TLS callbacks and `DllMain` of a mapped image
+  fn run_bootstrap(&self, image: &[u8], headers: &NtHeaders, base: usize, opts: ManualMapOptions) -> io::Result<()> {
+    let base_arg = base as u64;
+    // sub rsp, 0x28
+    let mut stub = vec![0x48, 0x83, 0xEC, 0x28];
+
+    if let Some(directory) = headers.data_directory(IMAGE_DIRECTORY_ENTRY_EXCEPTION) {
+      let add_function_table = self.get_proc_address("ntdll.dll", "RtlAddFunctionTable")?;
+      push_call(&mut stub, add_function_table as u64, [
+        base_arg + directory.VirtualAddress as u64,
+        (directory.Size as usize / RUNTIME_FUNCTION_SIZE) as u64,
+        base_arg
+      ]);
+    }
+
+    if opts.tls_callbacks {
+      if let Some(directory) = headers.data_directory(IMAGE_DIRECTORY_ENTRY_TLS) {
+        let tls = image.read_image::<IMAGE_TLS_DIRECTORY64>(directory.VirtualAddress as usize)?;
+        // already relocated, so these are addresses in the target
+        let mut callbacks = tls.AddressOfCallBacks;
+        while callbacks != 0 {
+          let callback = image.read_image::<u64>(callbacks.wrapping_sub(base_arg) as usize)?;
+          if callback == 0 {
+            break;
+          }
+
+          push_call(&mut stub, callback, [base_arg, DLL_PROCESS_ATTACH as u64, 0]);
+          callbacks += 8;
+        }
+      }
+    }
+
+    let call_dll_main = opts.dll_main && headers.entry_point() != 0;
+    if call_dll_main {
+      push_call(&mut stub, base_arg + headers.entry_point() as u64, [base_arg, DLL_PROCESS_ATTACH as u64, 0]);
+    }
+
+    // add rsp, 0x28; ret
+    stub.extend_from_slice(&[0x48, 0x83, 0xC4, 0x28, 0xC3]);
+
+    let code = self.execute_shellcode_with(&stub, ShellcodeOptions { timeout: None, ..Default::default() })?;
+    if call_dll_main && code == 0 {
+      return Err(io::Error::other("DllMain of the mapped dll returned FALSE"));
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+
+use winapi::shared::basetsd::SIZE_T;
+use winapi::shared::minwindef::BOOL;
+use winapi::shared::minwindef::FARPROC;
+use winapi::shared::ntdef::PVOID;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::libloaderapi::GetProcAddress;
+use winapi::um::winnt::ProcessASLRPolicy;
+use winapi::um::winnt::ProcessControlFlowGuardPolicy;
+use winapi::um::winnt::ProcessDEPPolicy;
+use winapi::um::winnt::ProcessDynamicCodePolicy;
+use winapi::um::winnt::ProcessExtensionPointDisablePolicy;
+use winapi::um::winnt::ProcessSignaturePolicy;
+use winapi::um::winnt::HANDLE;
+use winapi::um::winnt::PROCESS_MITIGATION_ASLR_POLICY;
+use winapi::um::winnt::PROCESS_MITIGATION_BINARY_SIGNATURE_POLICY;
+use winapi::um::winnt::PROCESS_MITIGATION_CONTROL_FLOW_GUARD_POLICY;
+use winapi::um::winnt::PROCESS_MITIGATION_DEP_POLICY;
+use winapi::um::winnt::PROCESS_MITIGATION_DYNAMIC_CODE_POLICY;
+use winapi::um::winnt::PROCESS_MITIGATION_EXTENSION_POINT_DISABLE_POLICY;
+use winapi::um::winnt::PROCESS_MITIGATION_POLICY;
+
+use crate::Process;
+
+type GetProcessMitigationPolicy = unsafe extern "system" fn(HANDLE, PROCESS_MITIGATION_POLICY, PVOID, SIZE_T) -> BOOL;
+
+/// Which images the process may load, from its binary signature policy
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SignaturePolicy {
+  /// Any image, signed or not
+  Any,
+  /// Only images signed by Microsoft
+  MicrosoftSigned,
+  /// Only images signed by Microsoft or the Windows Store
+  StoreSigned
+}
+
+/// Mitigation policies of a process, see [`Process::mitigations`]
+/// 
+/// Fields of newer policies are `None` when those couldn't be queried, e.g.
+/// because the Windows version predates them
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mitigations {
+  pub(crate) dep: bool,
+  pub(crate) dep_permanent: bool,
+  pub(crate) bottom_up_aslr: Option<bool>,
+  pub(crate) high_entropy_aslr: Option<bool>,
+  pub(crate) force_relocate_images: Option<bool>,
+  pub(crate) control_flow_guard: Option<bool>,
+  pub(crate) dynamic_code_prohibited: Option<bool>,
+  pub(crate) signature_policy: Option<SignaturePolicy>,
+  pub(crate) extension_points_disabled: Option<bool>
+}
+
+/// Looks up `GetProcessMitigationPolicy`, missing before Windows 8
+fn get_policy_function() -> Option<GetProcessMitigationPolicy> {
+  let kernel32 = "kernel32.dll".encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+  let module = unsafe { GetModuleHandleW(kernel32.as_ptr()) };
+  if module.is_null() {
+    return None;
+  }
+
+  let function = unsafe { GetProcAddress(module, c"GetProcessMitigationPolicy".as_ptr()) };
+  if function.is_null() {
+    return None;
+  }
+
+  Some(unsafe { mem::transmute::<FARPROC, GetProcessMitigationPolicy>(function) })
+}
+
+/// Queries one policy, `None` when the OS doesn't know it or refuses
+fn policy<T>(function: GetProcessMitigationPolicy, process: HANDLE, class: PROCESS_MITIGATION_POLICY) -> Option<T> {
+  let mut policy = unsafe { mem::zeroed::<T>() };
+  match unsafe { function(process, class, &mut policy as *mut T as *mut _, mem::size_of::<T>()) } {
+    0 => None,
+    _ => Some(policy)
+  }
+}
+
+impl Mitigations {
+  /// Returns is data execution prevention enabled
+  pub fn get_dep(&self) -> bool {
+    self.dep
+  }
+
+  /// Returns can DEP no longer be turned off for the process
+  pub fn get_dep_permanent(&self) -> bool {
+    self.dep_permanent
+  }
+
+  /// Returns are bottom-up allocations randomized
+  pub fn get_bottom_up_aslr(&self) -> Option<bool> {
+    self.bottom_up_aslr
+  }
+
+  /// Returns is bottom-up randomization using the 64-bit address space
+  pub fn get_high_entropy_aslr(&self) -> Option<bool> {
+    self.high_entropy_aslr
+  }
+
+  /// Returns are images without dynamic base relocated anyway
+  pub fn get_force_relocate_images(&self) -> Option<bool> {
+    self.force_relocate_images
+  }
+
+  /// Returns is Control Flow Guard enabled
+  pub fn get_control_flow_guard(&self) -> Option<bool> {
+    self.control_flow_guard
+  }
+
+  /// Returns is creating or modifying executable memory prohibited
+  pub fn get_dynamic_code_prohibited(&self) -> Option<bool> {
+    self.dynamic_code_prohibited
+  }
+
+  /// Returns which images the process may load
+  pub fn get_signature_policy(&self) -> Option<SignaturePolicy> {
+    self.signature_policy
+  }
+
+  /// Returns are legacy extension points like AppInit dlls disabled
+  pub fn get_extension_points_disabled(&self) -> Option<bool> {
+    self.extension_points_disabled
+  }
+
+  /// Returns will running remote code in the process likely fail
+  /// 
+  /// Prohibited dynamic code makes executable `VirtualAllocEx` allocations and
+  /// code patches fail, a signature policy makes `LoadLibrary` of an unsigned
+  /// dll fail. Unknown policies don't count.
+  pub fn blocks_remote_code(&self) -> bool {
+    self.dynamic_code_prohibited == Some(true)
+      || matches!(self.signature_policy, Some(SignaturePolicy::MicrosoftSigned | SignaturePolicy::StoreSigned))
+  }
+}
+
+impl Display for Mitigations {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "dep: {:?}, aslr: {:?}/{:?}/{:?}, cfg: {:?}, dynamic code prohibited: {:?}, signature: {:?}, extension points disabled: {:?}",
+      self.dep,
+      self.bottom_up_aslr,
+      self.high_entropy_aslr,
+      self.force_relocate_images,
+      self.control_flow_guard,
+      self.dynamic_code_prohibited,
+      self.signature_policy,
+      self.extension_points_disabled
+    )
+  }
+}
+
+impl Debug for Mitigations {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Queries mitigation policies of the process, needs `PROCESS_QUERY_INFORMATION`
+  /// 
+  /// Policies are queried one by one, newer ones which fail are left `None`
+  /// instead of failing the call. Fails only when the DEP policy, which every
+  /// supported Windows version knows, can't be queried, e.g. without access.
+  /// Fails with [`io::ErrorKind::Unsupported`] before Windows 8, which has no
+  /// `GetProcessMitigationPolicy`.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mitigations = process.mitigations().expect("Couldn't query policies");
+  /// if mitigations.blocks_remote_code() {
+  ///   println!("injecting into {} will likely fail: {:?}", process.get_name(), mitigations);
+  /// }
+  /// ```
+  pub fn mitigations(&self) -> io::Result<Mitigations> {
+    let function = get_policy_function().ok_or_else(|| io::Error::new(
+      io::ErrorKind::Unsupported,
+      "GetProcessMitigationPolicy is missing from kernel32"
+    ))?;
+    let handle = self.handle.get();
+
+    let dep = policy::<PROCESS_MITIGATION_DEP_POLICY>(function, handle, ProcessDEPPolicy)
+      .ok_or_else(io::Error::last_os_error)?;
+    let aslr = policy::<PROCESS_MITIGATION_ASLR_POLICY>(function, handle, ProcessASLRPolicy);
+    let cfg = policy::<PROCESS_MITIGATION_CONTROL_FLOW_GUARD_POLICY>(function, handle, ProcessControlFlowGuardPolicy);
+    let dynamic_code = policy::<PROCESS_MITIGATION_DYNAMIC_CODE_POLICY>(function, handle, ProcessDynamicCodePolicy);
+    let signature = policy::<PROCESS_MITIGATION_BINARY_SIGNATURE_POLICY>(function, handle, ProcessSignaturePolicy);
+    let extension_points = policy::<PROCESS_MITIGATION_EXTENSION_POINT_DISABLE_POLICY>(function, handle, ProcessExtensionPointDisablePolicy);
+
+    Ok(Mitigations {
+      dep: dep.Enable() != 0,
+      dep_permanent: dep.Permanent != 0,
+      bottom_up_aslr: aslr.map(|aslr| aslr.EnableBottomUpRandomization() != 0),
+      high_entropy_aslr: aslr.map(|aslr| aslr.EnableHighEntropy() != 0),
+      force_relocate_images: aslr.map(|aslr| aslr.EnableForceRelocateImages() != 0),
+      control_flow_guard: cfg.map(|cfg| cfg.EnableControlFlowGuard() != 0),
+      dynamic_code_prohibited: dynamic_code.map(|dynamic_code| dynamic_code.ProhibitDynamicCode() != 0),
+      signature_policy: signature.map(|signature| {
+        if signature.MicrosoftSignedOnly() != 0 {
+          SignaturePolicy::MicrosoftSigned
+        } else if signature.StoreSignedOnly() != 0 {
+          SignaturePolicy::StoreSigned
+        } else {
+          SignaturePolicy::Any
+        }
+      }),
+      extension_points_disabled: extension_points.map(|policy| policy.DisableExtensionPoints() != 0)
+    })
+  }
+}
@@ -1,8 +1,45 @@
 use std::fmt::Debug;
+use std::fmt::Display;
+use std::ffi::OsString;
+use std::io;
+use std::mem;
+use std::os::windows::ffi::OsStringExt;
+use std::ops::Deref;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
 
+use winapi::shared::winerror::ERROR_BAD_LENGTH;
+use winapi::shared::winerror::ERROR_NO_MORE_FILES;
+use winapi::shared::winerror::ERROR_PARTIAL_COPY;
+use winapi::shared::minwindef::HMODULE;
+use winapi::shared::minwindef::MAX_PATH;
+use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::um::psapi::EnumProcessModulesEx;
+use winapi::um::psapi::GetModuleBaseNameW;
+use winapi::um::psapi::GetModuleFileNameExW;
+use winapi::um::psapi::GetModuleInformation;
+use winapi::um::psapi::LIST_MODULES_ALL;
+use winapi::um::psapi::MODULEINFO;
+use winapi::um::tlhelp32::CreateToolhelp32Snapshot;
+use winapi::um::tlhelp32::MODULEENTRY32W;
+use winapi::um::tlhelp32::Module32FirstW;
+use winapi::um::tlhelp32::Module32NextW;
+use winapi::um::tlhelp32::TH32CS_SNAPMODULE;
+use winapi::um::tlhelp32::TH32CS_SNAPMODULE32;
+
+use crate::handle::HandleGuard;
+use crate::CuralError;
+use crate::Pod;
+use crate::Process;
+
+#[derive(Clone)]
 pub struct Module {
   pub(crate) name: String,
-  pub(crate) address: usize
+  pub(crate) path: PathBuf,
+  pub(crate) address: usize,
+  pub(crate) size: usize
 }
 
 impl Module {
@@ -11,20 +48,353 @@ impl Module {
       &self.address
     }
 
+    /// Returns size of the module image in bytes
+    pub fn get_size(&self) -> &usize {
+      &self.size
+    }
+
+    /// Returns is address inside of the module image
+    pub fn contains(&self, address: usize) -> bool {
+      self.address <= address && address < self.address + self.size
+    }
+
     /// Returns name
     pub fn get_name(&self) -> &str {
       &self.name
     }
+
+    /// Returns full path of the module file
+    pub fn get_path(&self) -> &Path {
+      &self.path
+    }
+
+    /// Binds module to the process it was loaded in, for reading its image
+    /// 
+    /// # Examples
+    /// ```
+    /// use cural::Process;
+    /// let process = Process::find("process.exe").expect("no such process");
+    /// let kernel = process.get_module("KERNEL32.DLL").expect("no such dll");
+    /// let exports = kernel.bind(&process).exports().expect("Couldn't read exports");
+    /// ```
+    pub fn bind<'p>(&self, process: &'p Process) -> BoundModule<'p> {
+      BoundModule { module: self.clone(), process }
+    }
+}
+
+/// Returns the part of a wide buffer before its NUL
+fn until_nul(buffer: &[u16]) -> &[u16] {
+  let len = buffer.iter().position(|unit| unit == &0).unwrap_or(buffer.len());
+  &buffer[..len]
+}
+
+impl From<&MODULEENTRY32W> for Module {
+  fn from(entry: &MODULEENTRY32W) -> Self {
+    Self {
+      name: String::from_utf16_lossy(until_nul(&entry.szModule)),
+      path: PathBuf::from(OsString::from_wide(until_nul(&entry.szExePath))),
+      address: entry.modBaseAddr as usize,
+      size: entry.modBaseSize as usize
+    }
+  }
+}
+
+/// Backend used to enumerate modules
+/// 
+/// All backends produce modules with the same fields. Toolhelp snapshots
+/// can't see 64-bit modules from a 32-bit caller, psapi can.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModuleEnumStrategy {
+  /// Toolhelp, falling back to psapi when the snapshot fails
+  #[default]
+  Auto,
+  /// `CreateToolhelp32Snapshot` only
+  Toolhelp,
+  /// `EnumProcessModulesEx(LIST_MODULES_ALL)` only
+  Psapi,
+  /// Loader list from the target's PEB, see [`Process::modules_via_peb`]
+  Peb
+}
+
+/// Enumerates modules with psapi
+pub(crate) fn psapi_modules(process: &Process) -> io::Result<Vec<Module>> {
+  let mut handles = Vec::<HMODULE>::new();
+
+  loop {
+    let mut needed = 0;
+    if unsafe {
+      EnumProcessModulesEx(
+        process.handle.get(),
+        handles.as_mut_ptr(),
+        (handles.len() * mem::size_of::<HMODULE>()) as u32,
+        &mut needed,
+        LIST_MODULES_ALL
+      )
+    } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    let count = needed as usize / mem::size_of::<HMODULE>();
+    if count <= handles.len() {
+      handles.truncate(count);
+      break;
+    }
+    // modules can load between the calls, so leave some headroom
+    handles.resize(count + 16, std::ptr::null_mut());
+  }
+
+  handles.into_iter()
+    .map(|module| {
+      let mut info = unsafe { mem::zeroed::<MODULEINFO>() };
+      if unsafe {
+        GetModuleInformation(process.handle.get(), module, &mut info, mem::size_of::<MODULEINFO>() as u32)
+      } == 0 {
+        return Err(io::Error::last_os_error());
+      }
+
+      let mut name = vec![0u16; MAX_PATH];
+      let name_len = unsafe {
+        GetModuleBaseNameW(process.handle.get(), module, name.as_mut_ptr(), name.len() as u32)
+      };
+      let mut path = vec![0u16; 32 * 1024];
+      let path_len = unsafe {
+        GetModuleFileNameExW(process.handle.get(), module, path.as_mut_ptr(), path.len() as u32)
+      };
+      if name_len == 0 || path_len == 0 {
+        return Err(io::Error::last_os_error());
+      }
+
+      Ok(Module {
+        name: String::from_utf16_lossy(&name[..name_len as usize]),
+        path: PathBuf::from(OsString::from_wide(&path[..path_len as usize])),
+        address: info.lpBaseOfDll as usize,
+        size: info.SizeOfImage as usize
+      })
+    })
+    .collect()
+}
+
+/// Which modules a toolhelp snapshot includes
+/// 
+/// For a 64-bit caller inspecting a 32-bit (WOW64) target, `TH32CS_SNAPMODULE`
+/// alone returns only the native 64-bit modules (the exe, 64-bit `ntdll.dll`
+/// and the wow64 layer), while `TH32CS_SNAPMODULE32` returns the 32-bit ones.
+/// [`ModuleFilter::All`] combines both, so some names (e.g. `ntdll.dll`) are
+/// reported twice with different bases and name lookups return the native
+/// one first. Use [`ModuleFilter::Bit32`] to get the bases the 32-bit code
+/// actually uses. For a native target both flags report the same modules.
+/// A 32-bit caller can't snapshot 64-bit modules at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModuleFilter {
+  /// `TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32`
+  #[default]
+  All,
+  /// `TH32CS_SNAPMODULE`, modules native to the caller
+  Native,
+  /// `TH32CS_SNAPMODULE32`, 32-bit modules of a WOW64 target
+  Bit32
+}
+
+impl ModuleFilter {
+  fn flags(&self) -> u32 {
+    match self {
+      Self::All => TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32,
+      Self::Native => TH32CS_SNAPMODULE,
+      Self::Bit32 => TH32CS_SNAPMODULE32
+    }
+  }
+}
+
+/// How module snapshots are taken
+/// 
+/// `CreateToolhelp32Snapshot` fails with `ERROR_BAD_LENGTH` or
+/// `ERROR_PARTIAL_COPY` while the target is still loading modules, those
+/// failures are retried with a linear backoff of `delay` per attempt.
+/// Other errors fail immediately.
+#[derive(Clone, Copy, Debug)]
+pub struct SnapshotOptions {
+  /// Number of retries after the first attempt
+  pub retries: u32,
+  /// Delay before the first retry, grows with every next one
+  pub delay: Duration,
+  /// Which modules to include
+  pub filter: ModuleFilter
+}
+
+impl Default for SnapshotOptions {
+  fn default() -> Self {
+    Self { retries: 4, delay: Duration::from_millis(10), filter: ModuleFilter::All }
+  }
+}
+
+/// Lazy iterator over module snapshot, see [`Process::modules`]
+/// 
+/// Iteration ends when `Module32NextW` fails, e.g. if a module unloads
+/// while the snapshot is walked
+pub struct Modules {
+  snapshot: HandleGuard,
+  entry: MODULEENTRY32W,
+  started: bool,
+  finished: bool
+}
+
+impl Modules {
+  pub(crate) fn new(process_id: u32, options: SnapshotOptions) -> io::Result<Self> {
+    let mut entry = unsafe { mem::zeroed::<MODULEENTRY32W>() };
+    entry.dwSize = mem::size_of::<MODULEENTRY32W>() as u32;
+
+    let mut attempt = 0;
+    let snapshot = loop {
+      let snapshot = unsafe {
+        CreateToolhelp32Snapshot(options.filter.flags(), process_id)
+      };
+
+      if snapshot != INVALID_HANDLE_VALUE {
+        break snapshot;
+      }
+
+      let error = io::Error::last_os_error();
+      let transient = matches!(
+        error.raw_os_error().map(|code| code as u32),
+        Some(ERROR_BAD_LENGTH) | Some(ERROR_PARTIAL_COPY)
+      );
+
+      if !transient || attempt >= options.retries {
+        return Err(CuralError::Snapshot(error).into());
+      }
+
+      attempt += 1;
+      thread::sleep(options.delay * attempt);
+    };
+
+    Ok(Self { snapshot: HandleGuard(snapshot), entry, started: false, finished: false })
+  }
+}
+
+impl Iterator for Modules {
+  type Item = io::Result<Module>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.finished {
+      return None;
+    }
+
+    if self.started {
+      if unsafe { Module32NextW(self.snapshot.get(), &mut self.entry) } == 0 {
+        self.finished = true;
+        return None;
+      }
+    } else {
+      self.started = true;
+      if unsafe { Module32FirstW(self.snapshot.get(), &mut self.entry) } == 0 {
+        self.finished = true;
+        let error = io::Error::last_os_error();
+
+        if error.raw_os_error() == Some(ERROR_NO_MORE_FILES as i32) {
+          return None;
+        }
+        return Some(Err(error));
+      }
+    }
+
+    Some(Ok(Module::from(&self.entry)))
+  }
+}
+
+/// Module together with the process which has it loaded
+pub struct BoundModule<'p> {
+  pub(crate) module: Module,
+  pub(crate) process: &'p Process
+}
+
+impl<'p> BoundModule<'p> {
+    /// Returns process which has the module loaded
+    pub fn get_process(&self) -> &'p Process {
+      self.process
+    }
+
+    /// Reads `T` at `offset` from the module base, zeroed on failure,
+    /// see [`BoundModule::try_read`]
+    /// 
+    /// # Examples
+    /// ```
+    /// use cural::Process;
+    /// let process = Process::find("process.exe").expect("no such process");
+    /// let main = process.main_module().expect("no main module");
+    /// let health = main.bind(&process).read::<i32>(0x1F2A0);
+    /// ```
+    pub fn read<T: Pod>(&self, offset: usize) -> T {
+      self.try_read(offset).unwrap_or_else(|_| unsafe { mem::zeroed::<T>() })
+    }
+
+    /// Reads `T` at `offset` from the module base
+    /// 
+    /// Fails with [`io::ErrorKind::InvalidInput`] when the value doesn't lie within the module
+    pub fn try_read<T: Pod>(&self, offset: usize) -> io::Result<T> {
+      self.process.try_read(self.offset_address(offset, mem::size_of::<T>())?)
+    }
+
+    /// Reads `len` bytes at `offset` from the module base
+    pub fn read_bytes(&self, offset: usize, len: usize) -> io::Result<Vec<u8>> {
+      self.process.read_bytes(self.offset_address(offset, len)?, len)
+    }
+
+    /// Writes `value` at `offset` from the module base, failures are ignored,
+    /// see [`BoundModule::try_write`]
+    /// 
+    /// # Examples
+    /// ```
+    /// use cural::Process;
+    /// let process = Process::find("process.exe").expect("no such process");
+    /// let main = process.main_module().expect("no main module");
+    /// main.bind(&process).write(100i32, 0x1F2A0);
+    /// ```
+    pub fn write<T: Pod>(&self, value: T, offset: usize) {
+      let _ = self.try_write(value, offset);
+    }
+
+    /// Writes `value` at `offset` from the module base
+    /// 
+    /// Fails with [`io::ErrorKind::InvalidInput`] when the value doesn't lie within the module
+    pub fn try_write<T: Pod>(&self, value: T, offset: usize) -> io::Result<()> {
+      self.process.try_write(value, self.offset_address(offset, mem::size_of::<T>())?)
+    }
+
+    /// Returns absolute address of `len` bytes at `offset`, which must lie within the module
+    fn offset_address(&self, offset: usize, len: usize) -> io::Result<usize> {
+      match offset.checked_add(len) {
+        Some(end) if end <= self.module.size => Ok(self.module.address + offset),
+        _ => Err(io::Error::new(
+          io::ErrorKind::InvalidInput,
+          format!("0x{:X} bytes at +0x{:X} lie outside of {:?}", len, offset, self.module)
+        ))
+      }
+    }
+}
+
+impl Deref for BoundModule<'_> {
+  type Target = Module;
+
+  fn deref(&self) -> &Self::Target {
+    &self.module
+  }
+}
+
+impl Debug for BoundModule<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.module, f)
+    }
 }
 
-impl ToString for Module {
-  fn to_string(&self) -> String {
-    self.name.clone()
+impl Display for Module {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.name)
   }
 }
 
+// `name @ 0xADDRESS (0xSIZE)`, `to_string` stays just the name
 impl Debug for Module {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.to_string())
+        write!(f, "{} @ 0x{:X} (0x{:X})", self.name, self.address, self.size)
     }
 }
\ No newline at end of file
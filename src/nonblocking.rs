@@ -0,0 +1,154 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::task::JoinHandle;
+use tokio::time::Sleep;
+
+use crate::Pod;
+use crate::Process;
+use crate::WaitResult;
+
+// the blocking pool moves processes between threads
+const _: fn() = || {
+  fn assert_send_sync<T: Send + Sync>() {}
+  assert_send_sync::<Process>();
+};
+
+enum WatchState<T> {
+  Idle,
+  Reading(JoinHandle<io::Result<T>>),
+  Waiting(Pin<Box<Sleep>>)
+}
+
+/// Stream of value changes from [`Process::watch_stream`]
+pub struct WatchStream<T> {
+  process: Process,
+  address: usize,
+  interval: Duration,
+  last: Option<T>,
+  state: WatchState<T>
+}
+
+impl<T> WatchStream<T> {
+  /// Returns watched address
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+}
+
+impl<T: Pod + PartialEq + Send + Unpin + 'static> Stream for WatchStream<T> {
+  type Item = T;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    loop {
+      let this = &mut *self;
+      match &mut this.state {
+        WatchState::Idle => this.state = WatchState::Reading(spawn_read(this.process.clone(), this.address)),
+        WatchState::Waiting(sleep) => {
+          if sleep.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+          }
+          this.state = WatchState::Reading(spawn_read(this.process.clone(), this.address));
+        },
+        WatchState::Reading(read) => {
+          let read = match Pin::new(read).poll(cx) {
+            Poll::Ready(read) => read,
+            Poll::Pending => return Poll::Pending
+          };
+          this.state = WatchState::Waiting(Box::pin(tokio::time::sleep(this.interval)));
+
+          // failed reads are skipped like the blocking watch does
+          if let Ok(Ok(value)) = read {
+            if this.last != Some(value) {
+              this.last = Some(value);
+              return Poll::Ready(Some(value));
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+fn spawn_read<T: Pod + Send + 'static>(process: Process, address: usize) -> JoinHandle<io::Result<T>> {
+  tokio::task::spawn_blocking(move || process.try_read::<T>(address))
+}
+
+impl Process {
+  /// Same as [`Process::try_read`], but runs `ReadProcessMemory` on tokio's
+  /// blocking pool so the executor isn't blocked
+  /// 
+  /// Needs the `async` feature and a tokio runtime
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// # async fn example() {
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let health = process.read_async::<i32>(0x1000).await.expect("Couldn't read memory");
+  /// # }
+  /// ```
+  pub async fn read_async<T: Pod + Send + 'static>(&self, address: usize) -> io::Result<T> {
+    spawn_read(self.clone(), address)
+      .await
+      .map_err(io::Error::other)?
+  }
+
+  /// Same as [`Process::wait`], but waits on tokio's blocking pool
+  /// 
+  /// The blocking thread stays taken until the process exits or the timeout
+  /// passes, even if the future is dropped. Needs the `async` feature and a
+  /// tokio runtime.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// # async fn example() {
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let result = process.wait_async(None).await.expect("Couldn't wait");
+  /// # }
+  /// ```
+  pub async fn wait_async(&self, timeout: Option<Duration>) -> io::Result<WaitResult> {
+    let process = self.clone();
+    tokio::task::spawn_blocking(move || process.wait(timeout))
+      .await
+      .map_err(io::Error::other)?
+  }
+
+  /// Async counterpart of [`Process::watch`], reads `T` at `address` every
+  /// `interval` and yields the first value, then every value which differs
+  /// from the previous one
+  /// 
+  /// Failed reads are skipped and the stream never ends, drop it to stop.
+  /// Needs the `async` feature and a tokio runtime.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::pin::Pin;
+  /// use std::time::Duration;
+  /// use futures_core::Stream;
+  /// use cural::Process;
+  /// # async fn example() {
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut health = process.watch_stream::<i32>(0x1000, Duration::from_millis(100));
+  /// while let Some(value) = std::future::poll_fn(|cx| Pin::new(&mut health).poll_next(cx)).await {
+  ///   println!("health {}", value);
+  /// }
+  /// # }
+  /// ```
+  pub fn watch_stream<T: Pod + PartialEq + Send + Unpin + 'static>(&self, address: usize, interval: Duration) -> WatchStream<T> {
+    WatchStream {
+      process: self.clone(),
+      address,
+      interval,
+      last: None,
+      // nothing is spawned before the first poll, which happens inside the runtime
+      state: WatchState::Idle
+    }
+  }
+}
@@ -0,0 +1,407 @@
+use std::io;
+use std::mem;
+use std::ptr;
+use std::time::Duration;
+
+use winapi::shared::ntdef::NTSTATUS;
+use winapi::shared::ntdef::UNICODE_STRING;
+use winapi::shared::ntstatus::STATUS_BUFFER_OVERFLOW;
+use winapi::shared::ntstatus::STATUS_BUFFER_TOO_SMALL;
+use winapi::shared::ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+use winapi::shared::winerror::WAIT_TIMEOUT;
+use winapi::um::processthreadsapi::CreateThread;
+use winapi::um::processthreadsapi::TerminateThread;
+use winapi::um::synchapi::WaitForSingleObject;
+use winapi::um::winbase::INFINITE;
+use winapi::um::winbase::WAIT_OBJECT_0;
+use winapi::um::winnt::HANDLE;
+
+use crate::handle::HandleGuard;
+use crate::pe::ImageSource;
+
+const SYSTEM_PROCESS_INFORMATION_CLASS: u32 = 5;
+const THREAD_QUERY_SET_WIN32_START_ADDRESS: u32 = 9;
+const PROCESS_BASIC_INFORMATION_CLASS: u32 = 0;
+const PROCESS_WOW64_INFORMATION_CLASS: u32 = 26;
+/// Windows 8.1+
+const PROCESS_COMMAND_LINE_INFORMATION_CLASS: u32 = 60;
+const SYSTEM_EXTENDED_HANDLE_INFORMATION_CLASS: u32 = 64;
+const OBJECT_NAME_INFORMATION_CLASS: u32 = 1;
+const OBJECT_TYPE_INFORMATION_CLASS: u32 = 2;
+/// Longest object name, `UNICODE_STRING` lengths are 16-bit
+const OBJECT_NAME_BUFFER: usize = 0x10000 + mem::size_of::<UNICODE_STRING>();
+
+/// `KTHREAD_STATE::Waiting`
+pub(crate) const THREAD_STATE_WAITING: u32 = 5;
+
+/// `KWAIT_REASON`s of `SleepEx`, `WaitFor*ObjectEx`, `MsgWaitForMultipleObjectsEx` and
+/// `NtWaitForAlertByThreadId`, the waits which can be alertable
+pub(crate) const ALERTABLE_WAIT_REASONS: [u32; 4] = [4, 6, 13, 37];
+
+#[link(name = "ntdll")]
+extern "system" {
+  fn NtQuerySystemInformation(class: u32, information: *mut u8, length: u32, returned: *mut u32) -> NTSTATUS;
+  fn NtQueryInformationProcess(process: HANDLE, class: u32, information: *mut u8, length: u32, returned: *mut u32) -> NTSTATUS;
+  fn NtQueryInformationThread(thread: HANDLE, class: u32, information: *mut u8, length: u32, returned: *mut u32) -> NTSTATUS;
+  fn NtQueryObject(handle: HANDLE, class: u32, information: *mut u8, length: u32, returned: *mut u32) -> NTSTATUS;
+  fn RtlNtStatusToDosError(status: NTSTATUS) -> u32;
+}
+
+/// `SYSTEM_PROCESS_INFORMATION`, followed in the buffer by its threads
+// mirrors the ntdll layout, not every field is read
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct SystemProcessInformation {
+  pub(crate) next_entry_offset: u32,
+  pub(crate) number_of_threads: u32,
+  pub(crate) working_set_private_size: i64,
+  pub(crate) hard_fault_count: u32,
+  pub(crate) number_of_threads_high_watermark: u32,
+  pub(crate) cycle_time: u64,
+  pub(crate) create_time: i64,
+  pub(crate) user_time: i64,
+  pub(crate) kernel_time: i64,
+  pub(crate) image_name: UNICODE_STRING,
+  pub(crate) base_priority: i32,
+  pub(crate) unique_process_id: HANDLE,
+  pub(crate) inherited_from_unique_process_id: HANDLE,
+  pub(crate) handle_count: u32,
+  pub(crate) session_id: u32,
+  pub(crate) unique_process_key: usize,
+  pub(crate) peak_virtual_size: usize,
+  pub(crate) virtual_size: usize,
+  pub(crate) page_fault_count: u32,
+  pub(crate) peak_working_set_size: usize,
+  pub(crate) working_set_size: usize,
+  pub(crate) quota_peak_paged_pool_usage: usize,
+  pub(crate) quota_paged_pool_usage: usize,
+  pub(crate) quota_peak_non_paged_pool_usage: usize,
+  pub(crate) quota_non_paged_pool_usage: usize,
+  pub(crate) pagefile_usage: usize,
+  pub(crate) peak_pagefile_usage: usize,
+  pub(crate) private_page_count: usize,
+  pub(crate) read_operation_count: i64,
+  pub(crate) write_operation_count: i64,
+  pub(crate) other_operation_count: i64,
+  pub(crate) read_transfer_count: i64,
+  pub(crate) write_transfer_count: i64,
+  pub(crate) other_transfer_count: i64
+}
+
+/// `SYSTEM_THREAD_INFORMATION`
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct SystemThreadInformation {
+  pub(crate) kernel_time: i64,
+  pub(crate) user_time: i64,
+  pub(crate) create_time: i64,
+  pub(crate) wait_time: u32,
+  pub(crate) start_address: usize,
+  pub(crate) unique_process: HANDLE,
+  pub(crate) unique_thread: HANDLE,
+  pub(crate) priority: i32,
+  pub(crate) base_priority: i32,
+  pub(crate) context_switches: u32,
+  pub(crate) thread_state: u32,
+  pub(crate) wait_reason: u32
+}
+
+/// `SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX`
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct SystemHandleEntry {
+  pub(crate) object: usize,
+  pub(crate) unique_process_id: usize,
+  pub(crate) handle_value: usize,
+  pub(crate) granted_access: u32,
+  pub(crate) creator_back_trace_index: u16,
+  pub(crate) object_type_index: u16,
+  pub(crate) handle_attributes: u32,
+  pub(crate) reserved: u32
+}
+
+/// Name query handed to [`query_name_worker`], which must not allocate since
+/// it may be terminated at any point
+struct NameQuery {
+  handle: HANDLE,
+  buffer: Vec<u8>,
+  status: NTSTATUS
+}
+
+/// `PROCESS_BASIC_INFORMATION`
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ProcessBasicInformation {
+  exit_status: NTSTATUS,
+  peb_base_address: usize,
+  affinity_mask: usize,
+  base_priority: i32,
+  unique_process_id: usize,
+  inherited_from_unique_process_id: usize
+}
+
+/// Converts failed `NTSTATUS` into an OS error
+pub(crate) fn status_error(status: NTSTATUS) -> io::Error {
+  io::Error::from_raw_os_error(unsafe { RtlNtStatusToDosError(status) } as i32)
+}
+
+/// Returns address the thread was started at, as passed to `CreateThread`
+pub(crate) fn thread_start_address(thread: HANDLE) -> io::Result<usize> {
+  let mut address = 0usize;
+  let status = unsafe {
+    NtQueryInformationThread(
+      thread,
+      THREAD_QUERY_SET_WIN32_START_ADDRESS,
+      &mut address as *mut usize as *mut u8,
+      mem::size_of::<usize>() as u32,
+      ptr::null_mut()
+    )
+  };
+
+  if status < 0 {
+    return Err(status_error(status));
+  }
+
+  Ok(address)
+}
+
+/// Returns `SystemProcessInformation` entry of a process with its threads
+pub(crate) fn process_information(id: u32) -> io::Result<(SystemProcessInformation, Vec<SystemThreadInformation>)> {
+  let mut buffer = vec![0u8; 0x40000];
+  loop {
+    let mut returned = 0;
+    let status = unsafe {
+      NtQuerySystemInformation(
+        SYSTEM_PROCESS_INFORMATION_CLASS,
+        buffer.as_mut_ptr(),
+        buffer.len() as u32,
+        &mut returned
+      )
+    };
+
+    match status {
+      STATUS_INFO_LENGTH_MISMATCH => buffer.resize((returned as usize).max(buffer.len() * 2), 0),
+      status if status < 0 => return Err(status_error(status)),
+      _ => break
+    }
+  }
+
+  let mut offset = 0;
+  loop {
+    let process = buffer.as_slice().read_image::<SystemProcessInformation>(offset)?;
+    if process.unique_process_id as usize == id as usize {
+      let threads = (0..process.number_of_threads as usize)
+        .map(|index| buffer.as_slice().read_image::<SystemThreadInformation>(
+          offset + mem::size_of::<SystemProcessInformation>() + index * mem::size_of::<SystemThreadInformation>()
+        ))
+        .collect::<io::Result<Vec<_>>>()?;
+      return Ok((process, threads));
+    }
+
+    if process.next_entry_offset == 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no process with id {} in system information", id)
+      ));
+    }
+    offset += process.next_entry_offset as usize;
+  }
+}
+
+fn query_process<T: Copy>(process: HANDLE, class: u32) -> io::Result<T> {
+  let mut information = unsafe { mem::zeroed::<T>() };
+  let status = unsafe {
+    NtQueryInformationProcess(
+      process,
+      class,
+      &mut information as *mut T as *mut u8,
+      mem::size_of::<T>() as u32,
+      ptr::null_mut()
+    )
+  };
+
+  if status < 0 {
+    return Err(status_error(status));
+  }
+
+  Ok(information)
+}
+
+/// Returns address of the process' PEB, the 32-bit one for WOW64 processes
+pub(crate) fn peb_address(process: HANDLE, x64: bool) -> io::Result<usize> {
+  let native = || query_process::<ProcessBasicInformation>(process, PROCESS_BASIC_INFORMATION_CLASS)
+    .map(|information| information.peb_base_address);
+
+  match x64 {
+    true => native(),
+    // x86 targets on x86 Windows aren't WOW64 and report no WOW64 PEB
+    false => match query_process::<usize>(process, PROCESS_WOW64_INFORMATION_CLASS)? {
+      0 => native(),
+      peb => Ok(peb)
+    }
+  }
+}
+
+/// Returns id of the process which created this one, the process may have exited since
+pub(crate) fn parent_process_id(process: HANDLE) -> io::Result<u32> {
+  query_process::<ProcessBasicInformation>(process, PROCESS_BASIC_INFORMATION_CLASS)
+    .map(|information| information.inherited_from_unique_process_id as u32)
+}
+
+/// Returns command line with `ProcessCommandLineInformation`, fails before Windows 8.1
+pub(crate) fn process_command_line(process: HANDLE) -> io::Result<String> {
+  let mut buffer = vec![0u8; 0x1000];
+  loop {
+    let mut returned = 0;
+    let status = unsafe {
+      NtQueryInformationProcess(
+        process,
+        PROCESS_COMMAND_LINE_INFORMATION_CLASS,
+        buffer.as_mut_ptr(),
+        buffer.len() as u32,
+        &mut returned
+      )
+    };
+
+    match status {
+      STATUS_INFO_LENGTH_MISMATCH | STATUS_BUFFER_TOO_SMALL => {
+        buffer.resize((returned as usize).max(buffer.len() * 2), 0)
+      },
+      status if status < 0 => return Err(status_error(status)),
+      _ => break
+    }
+  }
+
+  buffer_string(&buffer)
+}
+
+/// Reads `UNICODE_STRING` at the start of a returned buffer, pointing into it
+fn buffer_string(buffer: &[u8]) -> io::Result<String> {
+  let string = buffer.read_image::<UNICODE_STRING>(0)?;
+  if string.Length == 0 {
+    return Ok(String::new());
+  }
+
+  let offset = (string.Buffer as usize).wrapping_sub(buffer.as_ptr() as usize);
+  let text = offset.checked_add(string.Length as usize)
+    .and_then(|end| buffer.get(offset..end))
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "string outside of the returned buffer"))?;
+
+  let text = text.chunks_exact(2)
+    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+    .collect::<Vec<u16>>();
+  Ok(String::from_utf16_lossy(&text))
+}
+
+/// Returns every handle the process has open, from the system-wide handle table
+pub(crate) fn process_handles(id: u32) -> io::Result<Vec<SystemHandleEntry>> {
+  let mut buffer = vec![0u8; 0x100000];
+  loop {
+    let mut returned = 0;
+    let status = unsafe {
+      NtQuerySystemInformation(
+        SYSTEM_EXTENDED_HANDLE_INFORMATION_CLASS,
+        buffer.as_mut_ptr(),
+        buffer.len() as u32,
+        &mut returned
+      )
+    };
+
+    match status {
+      // handles keep being opened, so leave some room
+      STATUS_INFO_LENGTH_MISMATCH => buffer.resize((returned as usize + 0x10000).max(buffer.len() * 2), 0),
+      status if status < 0 => return Err(status_error(status)),
+      _ => break
+    }
+  }
+
+  // NumberOfHandles and a reserved pointer precede the entries
+  let count = buffer.as_slice().read_image::<usize>(0)?;
+  let first = 2 * mem::size_of::<usize>();
+  let mut handles = Vec::new();
+  for index in 0..count {
+    let entry = buffer.as_slice().read_image::<SystemHandleEntry>(first + index * mem::size_of::<SystemHandleEntry>())?;
+    if entry.unique_process_id == id as usize {
+      handles.push(entry);
+    }
+  }
+
+  Ok(handles)
+}
+
+/// Returns type name of a handle in the current process, like `File` or `Event`
+pub(crate) fn object_type_name(handle: HANDLE) -> io::Result<String> {
+  let mut buffer = vec![0u8; 0x1000];
+  loop {
+    let mut returned = 0;
+    let status = unsafe {
+      NtQueryObject(handle, OBJECT_TYPE_INFORMATION_CLASS, buffer.as_mut_ptr(), buffer.len() as u32, &mut returned)
+    };
+
+    match status {
+      STATUS_INFO_LENGTH_MISMATCH | STATUS_BUFFER_TOO_SMALL | STATUS_BUFFER_OVERFLOW => {
+        buffer.resize((returned as usize).max(buffer.len() * 2), 0)
+      },
+      status if status < 0 => return Err(status_error(status)),
+      // OBJECT_TYPE_INFORMATION starts with TypeName
+      _ => return buffer_string(&buffer)
+    }
+  }
+}
+
+unsafe extern "system" fn query_name_worker(query: *mut winapi::ctypes::c_void) -> u32 {
+  let query = &mut *(query as *mut NameQuery);
+  query.status = NtQueryObject(
+    query.handle,
+    OBJECT_NAME_INFORMATION_CLASS,
+    query.buffer.as_mut_ptr(),
+    query.buffer.len() as u32,
+    ptr::null_mut()
+  );
+
+  0
+}
+
+/// Returns name of a handle in the current process, `None` for unnamed objects
+/// 
+/// Names of synchronous files like pipes can't be queried while someone waits
+/// on them, the query blocks until that ends. With `timeout` it runs on a
+/// worker thread which is terminated once the timeout passes, reported as
+/// [`io::ErrorKind::TimedOut`].
+pub(crate) fn object_name(handle: HANDLE, timeout: Option<Duration>) -> io::Result<Option<String>> {
+  let mut query = Box::new(NameQuery { handle, buffer: vec![0u8; OBJECT_NAME_BUFFER], status: 0 });
+  let parameter = &mut *query as *mut NameQuery as *mut _;
+
+  if let Some(timeout) = timeout {
+    let thread = unsafe { CreateThread(ptr::null_mut(), 0, Some(query_name_worker), parameter, 0, ptr::null_mut()) };
+    if thread.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+    let thread = HandleGuard(thread);
+
+    let millis = timeout.as_millis().min(INFINITE as u128 - 1) as u32;
+    if unsafe { WaitForSingleObject(thread.get(), millis) } == WAIT_TIMEOUT {
+      // termination lands once the kernel wait is interrupted, until the
+      // worker is surely gone it may still write the query, so that leaks
+      let terminated = unsafe { TerminateThread(thread.get(), 1) } != 0
+        && unsafe { WaitForSingleObject(thread.get(), millis) } == WAIT_OBJECT_0;
+      if !terminated {
+        mem::forget(query);
+      }
+      return Err(io::Error::new(io::ErrorKind::TimedOut, "object name query timed out"));
+    }
+  } else {
+    unsafe { query_name_worker(parameter) };
+  }
+
+  if query.status < 0 {
+    return Err(status_error(query.status));
+  }
+
+  let name = buffer_string(&query.buffer)?;
+  Ok(Some(name).filter(|name| !name.is_empty()))
+}
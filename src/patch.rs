@@ -0,0 +1,83 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+
+use crate::Process;
+
+/// Bytes patched by [`Process::patch`], restored when dropped
+/// 
+/// The protection is changed only for the duration of each write, so the
+/// original one is back as soon as the patch is applied or restored
+pub struct Patch {
+  pub(crate) process: Process,
+  pub(crate) address: usize,
+  pub(crate) original: Vec<u8>
+}
+
+impl Patch {
+  /// Returns patched address
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns bytes which were there before the patch
+  pub fn get_original(&self) -> &[u8] {
+    &self.original
+  }
+
+  /// Restores original bytes now, reporting the error dropping would swallow
+  pub fn restore(mut self) -> io::Result<()> {
+    let original = mem::take(&mut self.original);
+    self.process.write_code(self.address, &original)
+  }
+}
+
+impl Drop for Patch {
+  fn drop(&mut self) {
+    if !self.original.is_empty() {
+      let _ = self.process.write_code(self.address, &self.original);
+    }
+  }
+}
+
+impl Display for Patch {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "patch of 0x{:X} bytes at {}", self.original.len(), self.process.format_address(self.address))
+  }
+}
+
+impl Debug for Patch {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Writes `bytes` over `address` until the returned [`Patch`] is dropped
+  /// 
+  /// The original bytes are saved first, the pages are made writable for the
+  /// write and the instruction cache is flushed, so code can be patched too.
+  /// Dropping the guard, also on panic or early return, writes the original
+  /// bytes back the same way.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// {
+  ///   // nop out a call
+  ///   let _patch = process.patch(0x7FF600001000, &[0x90; 5]).expect("Couldn't patch");
+  /// } // original bytes are back here
+  /// ```
+  pub fn patch(&self, address: usize, bytes: &[u8]) -> io::Result<Patch> {
+    let original = self.read_bytes(address, bytes.len())?;
+    self.write_code(address, bytes)?;
+
+    Ok(Patch {
+      process: self.clone(),
+      address,
+      original
+    })
+  }
+}
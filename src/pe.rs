@@ -0,0 +1,1010 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::ptr;
+
+use winapi::um::winnt::IMAGE_BASE_RELOCATION;
+use winapi::um::winnt::IMAGE_DATA_DIRECTORY;
+use winapi::um::winnt::IMAGE_DIRECTORY_ENTRY_BASERELOC;
+use winapi::um::winnt::IMAGE_DIRECTORY_ENTRY_EXPORT;
+use winapi::um::winnt::IMAGE_DOS_HEADER;
+use winapi::um::winnt::IMAGE_DOS_SIGNATURE;
+use winapi::um::winnt::IMAGE_FILE_MACHINE_AMD64;
+use winapi::um::winnt::IMAGE_FILE_MACHINE_ARM64;
+use winapi::um::winnt::IMAGE_FILE_MACHINE_I386;
+use winapi::um::winnt::IMAGE_DIRECTORY_ENTRY_IMPORT;
+use winapi::um::winnt::IMAGE_EXPORT_DIRECTORY;
+use winapi::um::winnt::IMAGE_FILE_DLL;
+use winapi::um::winnt::IMAGE_FILE_HEADER;
+use winapi::um::winnt::IMAGE_IMPORT_DESCRIPTOR;
+use winapi::um::winnt::IMAGE_NT_HEADERS32;
+use winapi::um::winnt::IMAGE_NT_HEADERS64;
+use winapi::um::winnt::IMAGE_NT_OPTIONAL_HDR32_MAGIC;
+use winapi::um::winnt::IMAGE_NT_OPTIONAL_HDR64_MAGIC;
+use winapi::um::winnt::IMAGE_NT_SIGNATURE;
+use winapi::um::winnt::IMAGE_ORDINAL_FLAG32;
+use winapi::um::winnt::IMAGE_ORDINAL_FLAG64;
+use winapi::um::winnt::IMAGE_REL_BASED_ABSOLUTE;
+use winapi::um::winnt::IMAGE_REL_BASED_DIR64;
+use winapi::um::winnt::IMAGE_REL_BASED_HIGHLOW;
+use winapi::um::winnt::IMAGE_SCN_MEM_EXECUTE;
+use winapi::um::winnt::IMAGE_SCN_MEM_READ;
+use winapi::um::winnt::IMAGE_SCN_MEM_WRITE;
+use winapi::um::winnt::IMAGE_SECTION_HEADER;
+
+use crate::BoundModule;
+use crate::Module;
+use crate::Process;
+
+/// Longest name we are willing to read out of a remote image
+pub(crate) const MAX_NAME_LEN: usize = 512;
+
+/// Something a PE image can be read out of: remote process memory
+/// (absolute addresses) or a local buffer such as a file on disk (offsets)
+pub(crate) trait ImageSource {
+  fn read_image<T: Copy>(&self, address: usize) -> io::Result<T>;
+}
+
+impl ImageSource for Process {
+  fn read_image<T: Copy>(&self, address: usize) -> io::Result<T> {
+    self.read_value(address)
+  }
+}
+
+impl ImageSource for [u8] {
+  fn read_image<T: Copy>(&self, address: usize) -> io::Result<T> {
+    let end = address.checked_add(mem::size_of::<T>());
+    match end {
+      Some(end) if end <= self.len() => {
+        Ok(unsafe { ptr::read_unaligned(self[address..].as_ptr() as *const T) })
+      },
+      _ => Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!("offset 0x{:X} is out of image bounds", address)
+      ))
+    }
+  }
+}
+
+#[derive(Clone, Copy)]
+enum Headers {
+  Pe32(IMAGE_NT_HEADERS32),
+  Pe64(IMAGE_NT_HEADERS64)
+}
+
+/// NT headers of an image, PE32 or PE32+
+#[derive(Clone, Copy)]
+pub(crate) struct NtHeaders {
+  /// Absolute address of the headers
+  pub(crate) address: usize,
+  headers: Headers
+}
+
+impl NtHeaders {
+  /// Reads and validates DOS and NT headers of the image at `base`
+  pub(crate) fn read<S: ImageSource + ?Sized>(source: &S, base: usize) -> io::Result<Self> {
+    let dos = source.read_image::<IMAGE_DOS_HEADER>(base)?;
+    if dos.e_magic != IMAGE_DOS_SIGNATURE {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad MZ signature 0x{:04X} at 0x{:X}", dos.e_magic, base)
+      ));
+    }
+
+    // corrupt or hollowed images can point anywhere, even before the image
+    let nt = usize::try_from(dos.e_lfanew).ok()
+      .and_then(|offset| base.checked_add(offset))
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad e_lfanew 0x{:X} at 0x{:X}", dos.e_lfanew, base)
+      ))?;
+    let signature = source.read_image::<u32>(nt)?;
+    if signature != IMAGE_NT_SIGNATURE {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad PE signature 0x{:08X} at 0x{:X}", signature, nt)
+      ));
+    }
+
+    let magic_offset = mem::size_of::<u32>() + mem::size_of::<IMAGE_FILE_HEADER>();
+    let magic = source.read_image::<u16>(nt + magic_offset)?;
+    let headers = match magic {
+      IMAGE_NT_OPTIONAL_HDR32_MAGIC => Headers::Pe32(source.read_image(nt)?),
+      IMAGE_NT_OPTIONAL_HDR64_MAGIC => Headers::Pe64(source.read_image(nt)?),
+      _ => return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad optional header magic 0x{:04X} at 0x{:X}", magic, nt + magic_offset)
+      ))
+    };
+
+    Ok(Self { address: nt, headers })
+  }
+
+  pub(crate) fn file_header(&self) -> &IMAGE_FILE_HEADER {
+    match &self.headers {
+      Headers::Pe32(headers) => &headers.FileHeader,
+      Headers::Pe64(headers) => &headers.FileHeader
+    }
+  }
+
+  /// Reads section headers which follow the optional header
+  pub(crate) fn section_headers<S: ImageSource + ?Sized>(&self, source: &S) -> io::Result<Vec<IMAGE_SECTION_HEADER>> {
+    let file_header = self.file_header();
+    let first = self.address
+      + mem::size_of::<u32>()
+      + mem::size_of::<IMAGE_FILE_HEADER>()
+      + file_header.SizeOfOptionalHeader as usize;
+
+    (0..file_header.NumberOfSections as usize)
+      .map(|index| source.read_image::<IMAGE_SECTION_HEADER>(
+        first + index * mem::size_of::<IMAGE_SECTION_HEADER>()
+      ))
+      .collect()
+  }
+
+  /// Returns is image PE32+ (64-bit)
+  pub(crate) fn is_pe64(&self) -> bool {
+    matches!(self.headers, Headers::Pe64(_))
+  }
+
+  /// Returns size of a pointer-sized entry (thunk, pointer) of the image
+  pub(crate) fn pointer_size(&self) -> usize {
+    if self.is_pe64() { 8 } else { 4 }
+  }
+
+  /// Reads pointer-sized value of the image
+  pub(crate) fn read_pointer<S: ImageSource + ?Sized>(&self, source: &S, address: usize) -> io::Result<u64> {
+    if self.is_pe64() {
+      source.read_image::<u64>(address)
+    } else {
+      Ok(source.read_image::<u32>(address)? as u64)
+    }
+  }
+
+  pub(crate) fn entry_point(&self) -> u32 {
+    match &self.headers {
+      Headers::Pe32(headers) => headers.OptionalHeader.AddressOfEntryPoint,
+      Headers::Pe64(headers) => headers.OptionalHeader.AddressOfEntryPoint
+    }
+  }
+
+  pub(crate) fn image_base(&self) -> u64 {
+    match &self.headers {
+      Headers::Pe32(headers) => headers.OptionalHeader.ImageBase as u64,
+      Headers::Pe64(headers) => headers.OptionalHeader.ImageBase
+    }
+  }
+
+  pub(crate) fn size_of_image(&self) -> u32 {
+    match &self.headers {
+      Headers::Pe32(headers) => headers.OptionalHeader.SizeOfImage,
+      Headers::Pe64(headers) => headers.OptionalHeader.SizeOfImage
+    }
+  }
+
+  pub(crate) fn size_of_headers(&self) -> u32 {
+    match &self.headers {
+      Headers::Pe32(headers) => headers.OptionalHeader.SizeOfHeaders,
+      Headers::Pe64(headers) => headers.OptionalHeader.SizeOfHeaders
+    }
+  }
+
+  pub(crate) fn subsystem(&self) -> u16 {
+    match &self.headers {
+      Headers::Pe32(headers) => headers.OptionalHeader.Subsystem,
+      Headers::Pe64(headers) => headers.OptionalHeader.Subsystem
+    }
+  }
+
+  /// Returns data directory by `IMAGE_DIRECTORY_ENTRY_*` index, `None` when it's empty
+  pub(crate) fn data_directory(&self, index: u16) -> Option<IMAGE_DATA_DIRECTORY> {
+    let (count, directories) = match &self.headers {
+      Headers::Pe32(headers) => (headers.OptionalHeader.NumberOfRvaAndSizes, &headers.OptionalHeader.DataDirectory),
+      Headers::Pe64(headers) => (headers.OptionalHeader.NumberOfRvaAndSizes, &headers.OptionalHeader.DataDirectory)
+    };
+
+    let index = index as usize;
+    if index >= count as usize || index >= directories.len() {
+      return None;
+    }
+
+    let directory = directories[index];
+    if directory.VirtualAddress == 0 || directory.Size == 0 {
+      return None;
+    }
+
+    Some(directory)
+  }
+}
+
+/// One relocated location of an image
+pub(crate) struct Relocation {
+  pub(crate) rva: usize,
+  pub(crate) kind: u16
+}
+
+impl Relocation {
+  /// Returns number of bytes the relocation covers
+  pub(crate) fn size(&self) -> usize {
+    match self.kind {
+      IMAGE_REL_BASED_DIR64 => 8,
+      _ => 4
+    }
+  }
+}
+
+/// Maps rva to offset in the file of an image with these section headers
+pub(crate) fn rva_to_offset(sections: &[IMAGE_SECTION_HEADER], rva: usize) -> Option<usize> {
+  sections.iter()
+    .find(|section| {
+      let start = section.VirtualAddress as usize;
+      start <= rva && rva < start + section.SizeOfRawData as usize
+    })
+    .map(|section| rva - section.VirtualAddress as usize + section.PointerToRawData as usize)
+}
+
+/// Parses base relocation blocks of a local image, `to_offset` maps rva to offset in `image`
+pub(crate) fn relocations(
+  image: &[u8],
+  headers: &NtHeaders,
+  to_offset: impl Fn(usize) -> Option<usize>
+) -> io::Result<Vec<Relocation>> {
+  let mut result = Vec::new();
+
+  let directory = match headers.data_directory(IMAGE_DIRECTORY_ENTRY_BASERELOC) {
+    Some(directory) => directory,
+    None => return Ok(result)
+  };
+  let start = to_offset(directory.VirtualAddress as usize).ok_or_else(|| io::Error::new(
+    io::ErrorKind::InvalidData,
+    "relocation directory is outside of the image"
+  ))?;
+  let end = start + directory.Size as usize;
+
+  let mut block_offset = start;
+  while block_offset + mem::size_of::<IMAGE_BASE_RELOCATION>() <= end {
+    let block = image.read_image::<IMAGE_BASE_RELOCATION>(block_offset)?;
+    let block_size = block.SizeOfBlock as usize;
+    if block_size < mem::size_of::<IMAGE_BASE_RELOCATION>() {
+      break;
+    }
+
+    let entries = (block_size - mem::size_of::<IMAGE_BASE_RELOCATION>()) / 2;
+    for index in 0..entries {
+      let entry = image.read_image::<u16>(block_offset + mem::size_of::<IMAGE_BASE_RELOCATION>() + index * 2)?;
+      let kind = entry >> 12;
+      if kind == IMAGE_REL_BASED_ABSOLUTE {
+        continue;
+      }
+
+      result.push(Relocation {
+        rva: block.VirtualAddress as usize + (entry & 0xFFF) as usize,
+        kind
+      });
+    }
+
+    block_offset += block_size;
+  }
+
+  Ok(result)
+}
+
+/// Applies relocation at `offset` of `bytes`, returns false for kinds we don't apply
+/// (only `HIGHLOW` and `DIR64` are) or when it doesn't fit
+pub(crate) fn apply_relocation(bytes: &mut [u8], offset: usize, relocation: &Relocation, delta: u64) -> bool {
+  match relocation.kind {
+    IMAGE_REL_BASED_HIGHLOW if offset + 4 <= bytes.len() => {
+      let value = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+      bytes[offset..offset + 4].copy_from_slice(&value.wrapping_add(delta as u32).to_le_bytes());
+      true
+    },
+    IMAGE_REL_BASED_DIR64 if offset + 8 <= bytes.len() => {
+      let value = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+      bytes[offset..offset + 8].copy_from_slice(&value.wrapping_add(delta).to_le_bytes());
+      true
+    },
+    _ => false
+  }
+}
+
+/// Function exported by a module
+#[derive(Clone)]
+pub struct Export {
+  pub(crate) name: Option<String>,
+  pub(crate) ordinal: u16,
+  pub(crate) address: usize,
+  pub(crate) forwarder: Option<String>
+}
+
+impl Export {
+  /// Returns name, `None` for exports by ordinal only
+  pub fn get_name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  /// Returns ordinal
+  pub fn get_ordinal(&self) -> &u16 {
+    &self.ordinal
+  }
+
+  /// Returns absolute address in the target process
+  /// 
+  /// For forwarded exports this points at the forwarder string
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns forwarder (e.g. `NTDLL.RtlAllocateHeap`) if export is forwarded
+  pub fn get_forwarder(&self) -> Option<&str> {
+    self.forwarder.as_deref()
+  }
+}
+
+impl Display for Export {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.name {
+      Some(name) => f.write_str(name),
+      None => write!(f, "#{}", self.ordinal)
+    }
+  }
+}
+
+impl Debug for Export {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// How an imported function is referenced
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportName {
+  /// Imported by name
+  Name(String),
+  /// Imported by ordinal only
+  Ordinal(u16),
+  /// Image has no lookup table, so the reference can't be recovered
+  /// from the (already resolved) IAT
+  Unknown
+}
+
+/// Function imported by a module
+#[derive(Clone)]
+pub struct ImportedFunction {
+  pub(crate) name_or_ordinal: ImportName,
+  pub(crate) iat_address: usize,
+  pub(crate) current_value: usize
+}
+
+impl ImportedFunction {
+  /// Returns name or ordinal of the function
+  pub fn get_name_or_ordinal(&self) -> &ImportName {
+    &self.name_or_ordinal
+  }
+
+  /// Returns absolute address of the IAT slot in the target process
+  pub fn get_iat_address(&self) -> &usize {
+    &self.iat_address
+  }
+
+  /// Returns value currently stored in the IAT slot
+  pub fn get_current_value(&self) -> &usize {
+    &self.current_value
+  }
+}
+
+impl Display for ImportedFunction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.name_or_ordinal {
+      ImportName::Name(name) => f.write_str(name),
+      ImportName::Ordinal(ordinal) => write!(f, "#{}", ordinal),
+      ImportName::Unknown => write!(f, "?@0x{:X}", self.iat_address)
+    }
+  }
+}
+
+impl Debug for ImportedFunction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Dll imported by a module together with its imported functions
+#[derive(Clone)]
+pub struct ImportedModule {
+  pub(crate) name: String,
+  pub(crate) functions: Vec<ImportedFunction>
+}
+
+impl ImportedModule {
+  /// Returns name of the dll
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  /// Returns functions imported from the dll
+  pub fn get_functions(&self) -> &[ImportedFunction] {
+    &self.functions
+  }
+}
+
+impl Display for ImportedModule {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.name)
+  }
+}
+
+impl Debug for ImportedModule {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ImportedModule")
+      .field("name", &self.name)
+      .field("functions", &self.functions)
+      .finish()
+  }
+}
+
+/// Section of a module image
+#[derive(Clone)]
+pub struct Section {
+  pub(crate) name: String,
+  pub(crate) address: usize,
+  pub(crate) virtual_size: usize,
+  pub(crate) raw_size: usize,
+  pub(crate) characteristics: u32
+}
+
+impl Section {
+  /// Returns name, names longer than 8 bytes are reported as the raw `/offset` reference
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  /// Returns absolute address in the target process
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns size of the section in memory
+  pub fn get_virtual_size(&self) -> &usize {
+    &self.virtual_size
+  }
+
+  /// Returns size of the section in the file
+  pub fn get_raw_size(&self) -> &usize {
+    &self.raw_size
+  }
+
+  /// Returns raw `IMAGE_SCN_*` characteristics flags
+  pub fn get_characteristics(&self) -> &u32 {
+    &self.characteristics
+  }
+
+  /// Returns is section executable
+  pub fn is_executable(&self) -> bool {
+    self.characteristics & IMAGE_SCN_MEM_EXECUTE != 0
+  }
+
+  /// Returns is section readable
+  pub fn is_readable(&self) -> bool {
+    self.characteristics & IMAGE_SCN_MEM_READ != 0
+  }
+
+  /// Returns is section writable
+  pub fn is_writable(&self) -> bool {
+    self.characteristics & IMAGE_SCN_MEM_WRITE != 0
+  }
+
+  /// Returns is address inside of the section
+  pub fn contains(&self, address: usize) -> bool {
+    self.address <= address && address < self.address + self.virtual_size
+  }
+}
+
+impl Display for Section {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.name)
+  }
+}
+
+impl Debug for Section {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Target machine of an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Machine {
+  X86,
+  X64,
+  Arm64,
+  /// Any other `IMAGE_FILE_MACHINE_*` value
+  Other(u16)
+}
+
+impl From<u16> for Machine {
+  fn from(machine: u16) -> Self {
+    match machine {
+      IMAGE_FILE_MACHINE_I386 => Self::X86,
+      IMAGE_FILE_MACHINE_AMD64 => Self::X64,
+      IMAGE_FILE_MACHINE_ARM64 => Self::Arm64,
+      other => Self::Other(other)
+    }
+  }
+}
+
+/// Summary of the PE headers of a module image
+#[derive(Clone, Debug)]
+pub struct PeInfo {
+  pub(crate) entry_point: usize,
+  pub(crate) machine: Machine,
+  pub(crate) timestamp: u32,
+  pub(crate) size_of_image: usize,
+  pub(crate) subsystem: u16,
+  pub(crate) is_dll: bool,
+  pub(crate) is_pe64: bool
+}
+
+impl PeInfo {
+  /// Returns absolute address of the entry point, `None` when image has none
+  pub fn get_entry_point(&self) -> Option<usize> {
+    Some(self.entry_point).filter(|address| address != &0)
+  }
+
+  /// Returns target machine
+  pub fn get_machine(&self) -> &Machine {
+    &self.machine
+  }
+
+  /// Returns link timestamp in seconds since unix epoch
+  pub fn get_timestamp(&self) -> &u32 {
+    &self.timestamp
+  }
+
+  /// Returns size of the image in memory
+  pub fn get_size_of_image(&self) -> &usize {
+    &self.size_of_image
+  }
+
+  /// Returns raw `IMAGE_SUBSYSTEM_*` value
+  pub fn get_subsystem(&self) -> &u16 {
+    &self.subsystem
+  }
+
+  /// Returns is image a dll
+  pub fn is_dll(&self) -> bool {
+    self.is_dll
+  }
+
+  /// Returns is image PE32+
+  pub fn is_pe64(&self) -> bool {
+    self.is_pe64
+  }
+}
+
+fn read_u32_array(process: &Process, address: usize, count: usize) -> io::Result<Vec<u32>> {
+  Ok(process.read_bytes(address, count * 4)?
+    .chunks_exact(4)
+    .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+    .collect())
+}
+
+fn read_u16_array(process: &Process, address: usize, count: usize) -> io::Result<Vec<u16>> {
+  Ok(process.read_bytes(address, count * 2)?
+    .chunks_exact(2)
+    .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+    .collect())
+}
+
+impl BoundModule<'_> {
+  /// Parses export table out of the module image in target's memory
+  /// 
+  /// Forwarded exports are not resolved, they are flagged with [`Export::get_forwarder`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let kernel = process.get_module("KERNEL32.DLL").expect("no such dll");
+  /// let exports = kernel.bind(&process).exports().expect("Couldn't read exports");
+  /// ```
+  pub fn exports(&self) -> io::Result<Vec<Export>> {
+    let base = self.address;
+    let headers = NtHeaders::read(self.process, base)?;
+
+    let directory = match headers.data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT) {
+      Some(directory) => directory,
+      None => return Ok(Vec::new())
+    };
+    let directory_start = directory.VirtualAddress as usize;
+    let directory_end = directory_start + directory.Size as usize;
+
+    let exports = self.process.read_value::<IMAGE_EXPORT_DIRECTORY>(base + directory_start)?;
+    let functions = read_u32_array(
+      self.process,
+      base + exports.AddressOfFunctions as usize,
+      exports.NumberOfFunctions as usize
+    )?;
+    let names = read_u32_array(
+      self.process,
+      base + exports.AddressOfNames as usize,
+      exports.NumberOfNames as usize
+    )?;
+    let name_ordinals = read_u16_array(
+      self.process,
+      base + exports.AddressOfNameOrdinals as usize,
+      exports.NumberOfNames as usize
+    )?;
+
+    let mut function_names = vec![None; functions.len()];
+    for (name, index) in names.iter().zip(name_ordinals) {
+      if let Some(slot) = function_names.get_mut(index as usize) {
+        *slot = Some(self.process.read_c_string(base + *name as usize, MAX_NAME_LEN)?);
+      }
+    }
+
+    let mut result = Vec::new();
+    for (index, (rva, name)) in functions.into_iter().zip(function_names).enumerate() {
+      if rva == 0 {
+        continue;
+      }
+
+      let rva = rva as usize;
+      let address = base + rva;
+      let forwarder = if (directory_start..directory_end).contains(&rva) {
+        Some(self.process.read_c_string(address, MAX_NAME_LEN)?)
+      } else {
+        None
+      };
+
+      result.push(Export {
+        name,
+        ordinal: (exports.Base as usize + index) as u16,
+        address,
+        forwarder
+      });
+    }
+
+    Ok(result)
+  }
+
+  /// Finds export by name
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let kernel = process.get_module("KERNEL32.DLL").expect("no such dll");
+  /// let load_library = kernel.bind(&process).get_export("LoadLibraryW").expect("no such export");
+  /// ```
+  pub fn get_export(&self, name: &str) -> io::Result<Export> {
+    self.exports()?
+      .into_iter()
+      .find(|export| export.name.as_deref() == Some(name))
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no export with name {} in {}", name, self.name)
+      ))
+  }
+
+  /// Parses import table out of the module image in target's memory
+  /// 
+  /// Names are taken from the import lookup table, so bound imports are
+  /// reported the same way as regular ones
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let imports = main.bind(&process).imports().expect("Couldn't read imports");
+  /// ```
+  pub fn imports(&self) -> io::Result<Vec<ImportedModule>> {
+    let base = self.address;
+    let headers = NtHeaders::read(self.process, base)?;
+
+    let directory = match headers.data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT) {
+      Some(directory) => directory,
+      None => return Ok(Vec::new())
+    };
+
+    let pointer_size = headers.pointer_size();
+    let ordinal_flag = if headers.is_pe64() {
+      IMAGE_ORDINAL_FLAG64
+    } else {
+      IMAGE_ORDINAL_FLAG32 as u64
+    };
+
+    let mut result = Vec::new();
+    let mut descriptor_address = base + directory.VirtualAddress as usize;
+    loop {
+      let descriptor = self.process.read_value::<IMAGE_IMPORT_DESCRIPTOR>(descriptor_address)?;
+      descriptor_address += mem::size_of::<IMAGE_IMPORT_DESCRIPTOR>();
+
+      let lookup = unsafe { *descriptor.u.OriginalFirstThunk() } as usize;
+      if descriptor.Name == 0 && descriptor.FirstThunk == 0 {
+        break;
+      }
+
+      let name = self.process.read_c_string(base + descriptor.Name as usize, MAX_NAME_LEN)?;
+      let iat = base + descriptor.FirstThunk as usize;
+
+      let mut functions = Vec::new();
+      for index in 0.. {
+        let iat_address = iat + index * pointer_size;
+        let current_value = headers.read_pointer(self.process, iat_address)?;
+
+        let name_or_ordinal = if lookup == 0 {
+          if current_value == 0 {
+            break;
+          }
+          ImportName::Unknown
+        } else {
+          let thunk = headers.read_pointer(self.process, base + lookup + index * pointer_size)?;
+          if thunk == 0 {
+            break;
+          }
+
+          if thunk & ordinal_flag != 0 {
+            ImportName::Ordinal(thunk as u16)
+          } else {
+            // IMAGE_IMPORT_BY_NAME: u16 hint followed by the name
+            let by_name = base + (thunk as u32) as usize;
+            ImportName::Name(self.process.read_c_string(by_name + 2, MAX_NAME_LEN)?)
+          }
+        };
+
+        functions.push(ImportedFunction {
+          name_or_ordinal,
+          iat_address,
+          current_value: current_value as usize
+        });
+      }
+
+      result.push(ImportedModule { name, functions });
+    }
+
+    Ok(result)
+  }
+
+  /// Parses section headers out of the module image in target's memory
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let sections = main.bind(&process).sections().expect("Couldn't read sections");
+  /// ```
+  pub fn sections(&self) -> io::Result<Vec<Section>> {
+    let headers = NtHeaders::read(self.process, self.address)?;
+
+    Ok(headers.section_headers(self.process)?
+      .into_iter()
+      .map(|header| {
+        let name = header.Name.iter()
+          .take_while(|byte| byte != &&0)
+          .map(|byte| *byte as char)
+          .collect::<String>();
+        let raw_size = header.SizeOfRawData as usize;
+        let virtual_size = match unsafe { *header.Misc.VirtualSize() } as usize {
+          0 => raw_size,
+          size => size
+        };
+
+        Section {
+          name,
+          address: self.address + header.VirtualAddress as usize,
+          virtual_size,
+          raw_size,
+          characteristics: header.Characteristics
+        }
+      })
+      .collect())
+  }
+
+  /// Finds section by name
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let text = main.bind(&process).section(".text").expect("no such section");
+  /// ```
+  pub fn section(&self, name: &str) -> io::Result<Section> {
+    self.sections()?
+      .into_iter()
+      .find(|section| section.name == name)
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no section with name {} in {}", name, self.name)
+      ))
+  }
+
+  /// Reads summary of the PE headers out of the module image in target's memory
+  /// 
+  /// Images with bad MZ/PE signatures (corrupt or hollowed) produce an
+  /// [`io::ErrorKind::InvalidData`] error describing what's wrong
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let info = main.bind(&process).pe_info().expect("Couldn't read headers");
+  /// ```
+  pub fn pe_info(&self) -> io::Result<PeInfo> {
+    let headers = NtHeaders::read(self.process, self.address)?;
+    let file_header = headers.file_header();
+    let entry_point = match headers.entry_point() {
+      0 => 0,
+      rva => self.address + rva as usize
+    };
+
+    Ok(PeInfo {
+      entry_point,
+      machine: Machine::from(file_header.Machine),
+      timestamp: file_header.TimeDateStamp,
+      size_of_image: headers.size_of_image() as usize,
+      subsystem: headers.subsystem(),
+      is_dll: file_header.Characteristics & IMAGE_FILE_DLL != 0,
+      is_pe64: headers.is_pe64()
+    })
+  }
+}
+
+/// Forwarder chains longer than this are treated as broken
+const MAX_FORWARDER_DEPTH: usize = 8;
+
+/// Reference to an export, by name or by ordinal
+enum ExportRef<'a> {
+  Name(&'a str),
+  Ordinal(u16)
+}
+
+impl Process {
+  /// Parses section headers of a module out of the target's memory, same as
+  /// [`BoundModule::sections`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let text = process.sections(&main).expect("Couldn't read sections")
+  ///   .into_iter()
+  ///   .find(|section| section.get_name() == ".text");
+  /// ```
+  pub fn sections(&self, module: &Module) -> io::Result<Vec<Section>> {
+    module.bind(self).sections()
+  }
+
+  /// Resolves address of an exported function inside the target
+  /// 
+  /// The export table is walked remotely, so the dll doesn't have to be
+  /// loaded at the same base in our process. Forwarders are resolved, ones to
+  /// an api set through the target's api set map, their host must be loaded.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let load_library = process.get_proc_address("kernel32.dll", "LoadLibraryW").expect("no such export");
+  /// ```
+  pub fn get_proc_address(&self, module: &str, function: &str) -> io::Result<usize> {
+    self.resolve_export(module, ExportRef::Name(function), 0)
+  }
+
+  /// Resolves address of a function exported by ordinal inside the target
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let function = process.get_proc_address_ordinal("ws2_32.dll", 23).expect("no such export");
+  /// ```
+  pub fn get_proc_address_ordinal(&self, module: &str, ordinal: u16) -> io::Result<usize> {
+    self.resolve_export(module, ExportRef::Ordinal(ordinal), 0)
+  }
+
+  fn resolve_export(&self, module: &str, export: ExportRef, depth: usize) -> io::Result<usize> {
+    if depth > MAX_FORWARDER_DEPTH {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("forwarder chain through {} is too long", module)
+      ));
+    }
+
+    let module = self.get_module(module)?;
+    let exports = module.bind(self).exports()?;
+    let found = exports.into_iter()
+      .find(|found| match export {
+        ExportRef::Name(name) => found.name.as_deref() == Some(name),
+        ExportRef::Ordinal(ordinal) => found.ordinal == ordinal
+      })
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        match export {
+          ExportRef::Name(name) => format!("no export with name {} in {}", name, module.name),
+          ExportRef::Ordinal(ordinal) => format!("no export with ordinal {} in {}", ordinal, module.name)
+        }
+      ))?;
+
+    let forwarder = match &found.forwarder {
+      Some(forwarder) => forwarder,
+      None => return Ok(found.address)
+    };
+
+    // "NTDLL.RtlAllocateHeap" or "NTDLL.#123", the dll part may itself contain dots
+    let (dll, function) = forwarder.rsplit_once('.').ok_or_else(|| io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("bad forwarder {}", forwarder)
+    ))?;
+    let dll = format!("{}.dll", dll);
+    // api sets like api-ms-win-core-*, common in kernel32 and kernelbase, are
+    // never in the module list under their own name
+    let dll = match is_api_set(&dll) {
+      true => self.api_set_host(&dll, &module.name)?.ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("api set {} has no host", dll)
+      ))?,
+      false => dll
+    };
+
+    match function.strip_prefix('#') {
+      Some(ordinal) => {
+        let ordinal = ordinal.parse::<u16>().map_err(|_| io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("bad forwarder {}", forwarder)
+        ))?;
+        self.resolve_export(&dll, ExportRef::Ordinal(ordinal), depth + 1)
+      },
+      None => self.resolve_export(&dll, ExportRef::Name(function), depth + 1)
+    }
+  }
+}
+
+/// Returns is a dll name an api set contract rather than a file
+pub(crate) fn is_api_set(name: &str) -> bool {
+  let name = name.to_ascii_lowercase();
+  name.starts_with("api-") || name.starts_with("ext-")
+}
+
+#[cfg(test)]
+mod tests {
+  use winapi::um::libloaderapi::GetModuleHandleA;
+  use winapi::um::libloaderapi::GetProcAddress;
+
+  use crate::Process;
+
+  use super::is_api_set;
+
+  #[test]
+  fn api_set_names_are_told_apart_from_files() {
+    assert!(is_api_set("api-ms-win-core-synch-l1-2-0.dll"));
+    assert!(is_api_set("EXT-MS-WIN-NTUSER-WINDOW-L1-1-0.dll"));
+    assert!(!is_api_set("kernelbase.dll"));
+  }
+
+  #[test]
+  fn api_set_resolves_to_a_loaded_host() {
+    let process = Process::open(std::process::id()).expect("Couldn't open current process");
+    let host = process.api_set_host("api-ms-win-core-synch-l1-2-0.dll", "")
+      .expect("Couldn't read api set map")
+      .expect("no host");
+    assert!(process.get_module(&host).is_ok(), "{} isn't loaded", host);
+  }
+
+  #[test]
+  fn remote_export_matches_get_proc_address() {
+    let process = Process::open(std::process::id()).expect("Couldn't open current process");
+    let kernel = process.get_module("KERNEL32.DLL").expect("no kernel32");
+    let export = kernel.bind(&process).get_export("GetProcAddress").expect("no such export");
+
+    let local = unsafe {
+      GetProcAddress(GetModuleHandleA(c"kernel32.dll".as_ptr()), c"GetProcAddress".as_ptr())
+    };
+    assert_eq!(export.get_address(), &(local as usize));
+  }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::pe::ImageSource;
+use crate::Module;
+use crate::Process;
+
+/// Most loader list entries walked before the list is considered garbage
+const PEB_MODULE_LIMIT: usize = 0x10000;
+/// `API_SET_NAMESPACE.Version` of Windows 10 and later, older layouts aren't parsed
+const API_SET_SCHEMA_VERSION: u32 = 6;
+
+/// `API_SET_NAMESPACE`, every offset in the map is relative to its start
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct ApiSetNamespace {
+  version: u32,
+  size: u32,
+  flags: u32,
+  count: u32,
+  entry_offset: u32,
+  hash_offset: u32,
+  hash_factor: u32
+}
+
+/// `API_SET_NAMESPACE_ENTRY`, one per contract
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct ApiSetEntry {
+  flags: u32,
+  name_offset: u32,
+  name_length: u32,
+  hashed_length: u32,
+  value_offset: u32,
+  value_count: u32
+}
+
+/// `API_SET_VALUE_ENTRY`, a host of a contract, for one importer or by default
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct ApiSetValue {
+  flags: u32,
+  name_offset: u32,
+  name_length: u32,
+  value_offset: u32,
+  value_length: u32
+}
+
+/// Reads a UTF-16 string of `len` bytes out of the api set map
+fn map_string(map: &[u8], offset: u32, len: u32) -> io::Result<String> {
+  let bytes = (offset as usize).checked_add(len as usize)
+    .and_then(|end| map.get(offset as usize..end))
+    .ok_or_else(|| io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("api set string at 0x{:X} is out of map bounds", offset)
+    ))?;
+
+  let wide = bytes.chunks_exact(2)
+    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+    .collect::<Vec<u16>>();
+  Ok(String::from_utf16_lossy(&wide))
+}
+
+/// Process environment block of a target, read once by [`Process::peb`]
+/// 
+/// WOW64 targets report their 32-bit PEB, whose values are what the x86 code
+/// of the process sees
+pub struct Peb {
+  pub(crate) process: Process,
+  pub(crate) address: usize,
+  pub(crate) being_debugged: bool,
+  pub(crate) image_base: usize,
+  pub(crate) ldr: usize,
+  pub(crate) process_parameters: usize
+}
+
+/// Entry of the loader's `InLoadOrderModuleList`, see [`Peb::ldr_modules`]
+#[derive(Clone)]
+pub struct LdrEntry {
+  pub(crate) address: usize,
+  pub(crate) name: String,
+  pub(crate) path: PathBuf,
+  pub(crate) base: usize,
+  pub(crate) size: usize,
+  pub(crate) entry_point: usize
+}
+
+/// Lazy walk over the remote loader list, see [`Peb::ldr_modules`]
+/// 
+/// Stops after the first error, an entry seen twice or too many entries mean
+/// the list is garbage and are reported as [`io::ErrorKind::InvalidData`]
+pub struct LdrModules<'p> {
+  process: &'p Process,
+  head: usize,
+  next: usize,
+  visited: HashSet<usize>,
+  finished: bool
+}
+
+impl Peb {
+  /// Returns address of the PEB in the target
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns `BeingDebugged`, set while a debugger is attached
+  pub fn is_being_debugged(&self) -> bool {
+    self.being_debugged
+  }
+
+  /// Returns `ImageBaseAddress`, where the main image is loaded
+  pub fn get_image_base(&self) -> &usize {
+    &self.image_base
+  }
+
+  /// Returns address of `PEB_LDR_DATA`, 0 before the loader is initialized
+  pub fn get_ldr(&self) -> &usize {
+    &self.ldr
+  }
+
+  /// Returns address of `RTL_USER_PROCESS_PARAMETERS`
+  pub fn get_process_parameters(&self) -> &usize {
+    &self.process_parameters
+  }
+
+  /// Walks `InLoadOrderModuleList` in the target's memory, main image first
+  /// 
+  /// Entries are read as the iterator advances, no snapshot is taken. Fails
+  /// with [`io::ErrorKind::InvalidData`] before the loader is initialized.
+  /// Needs `PROCESS_VM_READ`.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let peb = process.peb().expect("Couldn't read PEB");
+  /// for entry in peb.ldr_modules().expect("loader isn't initialized") {
+  ///   let entry = entry.expect("garbage loader list");
+  ///   println!("{} starts at 0x{:X}", entry.get_name(), entry.get_entry_point());
+  /// }
+  /// ```
+  pub fn ldr_modules(&self) -> io::Result<LdrModules<'_>> {
+    if self.ldr == 0 {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "loader isn't initialized yet"));
+    }
+
+    // offset of PEB_LDR_DATA.InLoadOrderModuleList
+    let head = self.ldr + match self.process.ptr_size() {
+      8 => 0x10,
+      _ => 0x0C
+    };
+
+    Ok(LdrModules {
+      process: &self.process,
+      head,
+      next: self.process.read_ptr(head)?,
+      visited: HashSet::new(),
+      finished: false
+    })
+  }
+}
+
+impl Display for Peb {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "PEB of {} at {}", self.process, self.process.format_address(self.address))
+  }
+}
+
+impl Debug for Peb {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl LdrEntry {
+  /// Returns address of the `LDR_DATA_TABLE_ENTRY` itself
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns `BaseDllName`
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  /// Returns `FullDllName`
+  pub fn get_path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Returns `DllBase`
+  pub fn get_base(&self) -> &usize {
+    &self.base
+  }
+
+  /// Returns `SizeOfImage`
+  pub fn get_size(&self) -> &usize {
+    &self.size
+  }
+
+  /// Returns `EntryPoint`, 0 for images without one
+  pub fn get_entry_point(&self) -> &usize {
+    &self.entry_point
+  }
+}
+
+impl From<LdrEntry> for Module {
+  fn from(entry: LdrEntry) -> Self {
+    Module { name: entry.name, path: entry.path, address: entry.base, size: entry.size }
+  }
+}
+
+impl Display for LdrEntry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}(0x{:X})", self.name, self.base)
+  }
+}
+
+impl Debug for LdrEntry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl LdrModules<'_> {
+  fn read_entry(&self, address: usize) -> io::Result<LdrEntry> {
+    // offsets of DllBase, EntryPoint, SizeOfImage, FullDllName and BaseDllName
+    let (base, entry_point, size, path, name) = match self.process.ptr_size() {
+      8 => (0x30, 0x38, 0x40, 0x48, 0x58),
+      _ => (0x18, 0x1C, 0x20, 0x24, 0x2C)
+    };
+
+    Ok(LdrEntry {
+      address,
+      name: self.process.read_unicode_string(address + name)?,
+      path: PathBuf::from(self.process.read_unicode_string(address + path)?),
+      base: self.process.read_ptr(address + base)?,
+      size: self.process.read_value::<u32>(address + size)? as usize,
+      entry_point: self.process.read_ptr(address + entry_point)?
+    })
+  }
+}
+
+impl Iterator for LdrModules<'_> {
+  type Item = io::Result<LdrEntry>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    // the links are the start of each entry
+    let address = self.next;
+    if self.finished || address == self.head {
+      return None;
+    }
+
+    if address == 0 || self.visited.len() >= PEB_MODULE_LIMIT || !self.visited.insert(address) {
+      self.finished = true;
+      return Some(Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("loader list at 0x{:X} doesn't lead back to its head", self.head)
+      )));
+    }
+
+    let entry = self.read_entry(address)
+      .and_then(|entry| Ok((entry, self.process.read_ptr(address)?)));
+    match entry {
+      Ok((entry, next)) => {
+        self.next = next;
+        Some(Ok(entry))
+      },
+      Err(error) => {
+        self.finished = true;
+        Some(Err(error))
+      }
+    }
+  }
+}
+
+impl Process {
+  /// Reads the PEB of the process, the 32-bit one for WOW64 targets
+  /// 
+  /// Located with `NtQueryInformationProcess`, `ProcessBasicInformation` for
+  /// native targets and `ProcessWow64Information` for WOW64 ones. x86 callers
+  /// can't read the PEB of x64 targets. Needs `PROCESS_VM_READ`.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let peb = process.peb().expect("Couldn't read PEB");
+  /// if peb.is_being_debugged() {
+  ///   println!("{} is being debugged", process.get_name());
+  /// }
+  /// ```
+  pub fn peb(&self) -> io::Result<Peb> {
+    let address = self.peb_address()?;
+    let ptr_size = self.ptr_size();
+
+    Ok(Peb {
+      process: self.clone(),
+      address,
+      being_debugged: self.read_value::<u8>(address + 2)? != 0,
+      // BeingDebugged and flags take the first pointer sized slot, Mutant the second
+      image_base: self.read_ptr(address + 2 * ptr_size)?,
+      ldr: self.read_ptr(address + 3 * ptr_size)?,
+      process_parameters: self.read_ptr(address + 4 * ptr_size)?
+    })
+  }
+
+  /// Maps an api set contract like `api-ms-win-core-synch-l1-2-0.dll` to the
+  /// dll implementing it, `None` when the contract has no host
+  /// 
+  /// Read from the `ApiSetMap` of the target's PEB, nothing is loaded. Hosts
+  /// can depend on the importing module, kernel32 itself is sent to kernelbase
+  /// for contracts others get from kernel32. Only the Windows 10 schema is
+  /// understood, older ones fail with [`io::ErrorKind::Unsupported`].
+  pub(crate) fn api_set_host(&self, contract: &str, importer: &str) -> io::Result<Option<String>> {
+    // offset of PEB.ApiSetMap
+    let address = self.peb_address()? + match self.ptr_size() {
+      8 => 0x68,
+      _ => 0x38
+    };
+    let map = self.read_ptr(address)?;
+    if map == 0 {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "PEB has no api set map"));
+    }
+
+    let namespace = self.read_value::<ApiSetNamespace>(map)?;
+    if namespace.version != API_SET_SCHEMA_VERSION {
+      return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("api set schema version {} isn't supported", namespace.version)
+      ));
+    }
+    let map = self.read_bytes(map, namespace.size as usize)?;
+
+    // the hashed part of a name leaves out the trailing revision, and the map
+    // stores names without ".dll"
+    let contract = contract.to_ascii_lowercase();
+    let contract = contract.strip_suffix(".dll").unwrap_or(&contract);
+    let hashed = contract.rsplit_once('-').map_or(contract, |(hashed, _)| hashed);
+
+    for index in 0..namespace.count as usize {
+      let entry = map.read_image::<ApiSetEntry>(namespace.entry_offset as usize + index * mem::size_of::<ApiSetEntry>())?;
+      if !map_string(&map, entry.name_offset, entry.hashed_length)?.eq_ignore_ascii_case(hashed) {
+        continue;
+      }
+
+      let mut default = None;
+      for value in 0..entry.value_count as usize {
+        let value = map.read_image::<ApiSetValue>(entry.value_offset as usize + value * mem::size_of::<ApiSetValue>())?;
+        let host = map_string(&map, value.value_offset, value.value_length)?;
+        if host.is_empty() {
+          continue;
+        }
+
+        let host_importer = map_string(&map, value.name_offset, value.name_length)?;
+        if host_importer.eq_ignore_ascii_case(importer) {
+          return Ok(Some(host));
+        }
+        if host_importer.is_empty() && default.is_none() {
+          default = Some(host);
+        }
+      }
+
+      return Ok(default);
+    }
+
+    Ok(None)
+  }
+}
@@ -0,0 +1,32 @@
+/// Plain-old-data type which can be read from and written to target memory as raw bytes
+/// 
+/// # Safety
+/// Implementors must be valid for every bit pattern and must not contain
+/// padding, references, pointers to local memory or types with invariants
+/// (`bool`, `char`, enums, `String`, ...). A `#[repr(C)]` struct of `Pod`
+/// fields without padding satisfies this.
+/// 
+/// # Examples
+/// ```
+/// use cural::Pod;
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Vector3 {
+///   x: f32,
+///   y: f32,
+///   z: f32
+/// }
+/// 
+/// unsafe impl Pod for Vector3 {}
+/// ```
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+  ($($ty:ty),*) => {
+    $(unsafe impl Pod for $ty {})*
+  };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
@@ -1,27 +1,72 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Write;
 use std::io;
 use std::mem;
+use std::os::windows::io::AsHandle;
+use std::os::windows::io::BorrowedHandle;
+use std::os::windows::io::RawHandle;
+use std::path::Path;
+use std::path::PathBuf;
 use std::ptr;
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::SystemTime;
 
+use winapi::shared::minwindef::FILETIME;
 use winapi::shared::ntdef::HANDLE;
-use winapi::um::handleapi::CloseHandle;
+use winapi::shared::winerror::WAIT_TIMEOUT;
 use winapi::um::handleapi::INVALID_HANDLE_VALUE;
 use winapi::um::memoryapi::ReadProcessMemory;
+use winapi::um::memoryapi::VirtualAllocEx;
+use winapi::um::memoryapi::VirtualFreeEx;
+use winapi::um::memoryapi::VirtualProtectEx;
 use winapi::um::memoryapi::WriteProcessMemory;
+use winapi::um::processthreadsapi::FlushInstructionCache;
+use winapi::um::processthreadsapi::GetCurrentProcessId;
+use winapi::um::processthreadsapi::GetExitCodeProcess;
+use winapi::um::processthreadsapi::GetProcessHandleCount;
+use winapi::um::processthreadsapi::GetProcessId;
+use winapi::um::processthreadsapi::GetProcessTimes;
 use winapi::um::processthreadsapi::OpenProcess;
-use winapi::um::tlhelp32::CreateToolhelp32Snapshot;
-use winapi::um::tlhelp32::MODULEENTRY32;
-use winapi::um::tlhelp32::Module32First;
-use winapi::um::tlhelp32::Module32Next;
-use winapi::um::tlhelp32::PROCESSENTRY32;
-use winapi::um::tlhelp32::Process32Next;
-use winapi::um::tlhelp32::TH32CS_SNAPMODULE;
-use winapi::um::tlhelp32::TH32CS_SNAPMODULE32;
-use winapi::um::tlhelp32::TH32CS_SNAPPROCESS;
+use winapi::um::processthreadsapi::TerminateProcess;
+use winapi::um::synchapi::WaitForSingleObject;
+use winapi::um::sysinfoapi::GetNativeSystemInfo;
+use winapi::um::sysinfoapi::SYSTEM_INFO;
+use winapi::um::winbase::QueryFullProcessImageNameW;
+use winapi::um::winbase::INFINITE;
+use winapi::um::winbase::WAIT_FAILED;
+use winapi::um::winbase::WTSGetActiveConsoleSessionId;
+use winapi::um::winnt::MEM_COMMIT;
+use winapi::um::winnt::MEM_RELEASE;
+use winapi::um::winnt::MEM_RESERVE;
+use winapi::um::winnt::PAGE_EXECUTE_READWRITE;
 use winapi::um::winnt::PROCESS_ALL_ACCESS;
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+use winapi::um::winnt::PROCESSOR_ARCHITECTURE_AMD64;
+use winapi::um::winnt::PROCESSOR_ARCHITECTURE_ARM64;
+use winapi::um::winnt::PROCESSOR_ARCHITECTURE_IA64;
+use winapi::um::winnt::PROCESS_VM_READ;
 use winapi::um::wow64apiset::IsWow64Process;
 
+use crate::entry::session_of;
+use crate::handle::HandleGuard;
+use crate::logging;
+use crate::ntdll;
+use crate::thread::filetime_to_system_time;
+use crate::BoundModule;
+use crate::CuralError;
+use crate::module::psapi_modules;
 use crate::Module;
+use crate::ModuleEnumStrategy;
+use crate::ModuleFilter;
+use crate::Modules;
+use crate::Pod;
+use crate::SnapshotOptions;
+use crate::ProcessEntry;
+use crate::ProcessIter;
 
 /// Struct which represents windows process
 /// 
@@ -33,9 +78,95 @@ use crate::Module;
 /// ```
 #[derive(Clone)]
 pub struct Process {
-  id: u32,
-  name: String,
-  handle: HANDLE
+  pub(crate) id: u32,
+  pub(crate) name: String,
+  pub(crate) handle: Arc<HandleGuard>,
+  main_module: OnceLock<Module>,
+  modules: OnceLock<Vec<Module>>,
+  path: OnceLock<PathBuf>,
+  x64: bool
+}
+
+/// Returns is Windows itself 64-bit, whatever the bitness of the current process
+fn native_x64() -> bool {
+  let mut info = unsafe { mem::zeroed::<SYSTEM_INFO>() };
+  unsafe { GetNativeSystemInfo(&mut info) };
+
+  matches!(
+    unsafe { info.u.s().wProcessorArchitecture },
+    PROCESSOR_ARCHITECTURE_AMD64 | PROCESSOR_ARCHITECTURE_ARM64 | PROCESSOR_ARCHITECTURE_IA64
+  )
+}
+
+/// Asks `IsWow64Process` whether the process is x64, WOW64 ones are x86
+/// 
+/// `IsWow64Process` reports false for every process on 32-bit Windows, so
+/// only processes that aren't WOW64 on 64-bit Windows count as x64
+pub(crate) fn query_x64(handle: HANDLE) -> io::Result<bool> {
+  let mut is_wow64 = 0;
+
+  if unsafe { IsWow64Process(handle, &mut is_wow64) } != 1 {
+    return Err(io::Error::last_os_error());
+  }
+
+  Ok(is_wow64 == 0 && native_x64())
+}
+
+/// `GetExitCodeProcess` code of running processes
+const STILL_ACTIVE: u32 = 259;
+
+/// `QueryFullProcessImageNameW` flag for the native path form
+const PROCESS_NAME_NATIVE: u32 = 1;
+
+/// Returns full image path with `QueryFullProcessImageNameW`, `native` selects
+/// the `\Device\HarddiskVolume1\...` form over the Win32 one
+fn query_image_path(handle: HANDLE, native: bool) -> io::Result<PathBuf> {
+  let mut buffer = vec![0u16; 32 * 1024];
+  let mut len = buffer.len() as u32;
+  let flags = match native {
+    true => PROCESS_NAME_NATIVE,
+    false => 0
+  };
+  if unsafe { QueryFullProcessImageNameW(handle, flags, buffer.as_mut_ptr(), &mut len) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  Ok(PathBuf::from(String::from_utf16_lossy(&buffer[..len as usize])))
+}
+
+/// Returns process name the way every constructor reports it, the file name of the image
+fn image_name(path: &Path) -> Option<String> {
+  path.file_name().map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Normalizes a Win32 path for comparison, dropping `\\?\` and ignoring case and slash direction
+fn normalize_path(path: &str) -> String {
+  let path = path.replace('/', "\\");
+  let path = path.strip_prefix(r"\\?\").unwrap_or(&path);
+
+  path.to_ascii_lowercase()
+}
+
+/// Largest environment block read before it's considered garbage
+const ENVIRONMENT_LIMIT: usize = 0x100000;
+
+/// Error for a read or write which transferred only `done` of `len` bytes
+fn short_transfer(operation: &str, address: usize, done: usize, len: usize) -> io::Error {
+  let error = match operation {
+    "write" => CuralError::ShortWrite { address, wanted: len, got: done },
+    _ => CuralError::ShortRead { address, wanted: len, got: done }
+  };
+
+  logging::failed(operation, address, len, error.into())
+}
+
+/// Outcome of [`Process::wait`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitResult {
+  /// Process exited with this code
+  Exited(u32),
+  /// Timeout passed while the process kept running
+  TimedOut
 }
 
 impl Process {
@@ -44,203 +175,1399 @@ impl Process {
   /// # Examples
   /// ```
   /// use cural::Process;
-  /// let processes = Process::all().expect("Couldn't get any process");
-  /// println!("found {:?}", processes);
+  /// let processes = Process::all().expect("Couldn't get any process");
+  /// println!("found {:?}", processes);
+  /// ```
+  pub fn all() -> io::Result<Vec<Self>> {
+    Ok(
+      Process::iter()?
+        .filter_map(|entry| Self::from_entry(&entry).ok())
+        .collect()
+    )
+  }
+
+  /// Opens process by id
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::open(1234).expect("no such process");
+  /// println!("found {}", process);
+  /// ```
+  pub fn open(id: u32) -> io::Result<Self> {
+    Process::iter()?
+      .find(|entry| entry.id == id)
+      .ok_or_else(|| CuralError::ProcessNotFound(format!("id {}", id)))?
+      .open()
+  }
+
+  /// Opens process by id with a handle child processes can inherit
+  /// 
+  /// Like [`Process::open`] but `bInheritHandle` is set, so a child created
+  /// with `bInheritHandles` gets the same handle value and doesn't have to
+  /// open the target itself. Use [`Process::builder`] for other access rights.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::process::Command;
+  /// use cural::Process;
+  /// let process = Process::open_inheritable(1234).expect("no such process");
+  /// Command::new("helper.exe")
+  ///   .arg(format!("{}", process.get_handle() as usize))
+  ///   .spawn()
+  ///   .expect("Couldn't spawn helper");
+  /// ```
+  pub fn open_inheritable(id: u32) -> io::Result<Self> {
+    Process::builder()
+      .id(id)
+      .inherit_handle(true)
+      .open()
+  }
+
+  /// Lazily iterates over process snapshot without opening any handles
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::iter().expect("Couldn't create snapshot")
+  ///   .find(|entry| entry.get_name() == "process.exe")
+  ///   .expect("no such process")
+  ///   .open()
+  ///   .expect("Couldn't open process");
+  /// ```
+  pub fn iter() -> io::Result<ProcessIter> {
+    ProcessIter::new()
+  }
+
+  /// Lists every process from the snapshot without opening them
+  /// 
+  /// Unlike [`Process::all`] the list is complete, processes we are not
+  /// allowed to open are included too
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let entries = Process::list().expect("Couldn't create snapshot");
+  /// println!("found {:?}", entries);
+  /// ```
+  pub fn list() -> io::Result<Vec<ProcessEntry>> {
+    Ok(Process::iter()?.collect())
+  }
+
+  /// Gets all processes of the terminal services session, 0 holds the services
+  /// 
+  /// Sessions are looked up by id, so only the matching processes are opened
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let services = Process::all_in_session(0).expect("Couldn't create snapshot");
+  /// println!("found {:?}", services);
+  /// ```
+  pub fn all_in_session(session: u32) -> io::Result<Vec<Self>> {
+    Ok(
+      Process::iter()?
+        .filter(|entry| entry.session == Some(session))
+        .filter_map(|entry| Self::from_entry(&entry).ok())
+        .collect()
+    )
+  }
+
+  /// Gets all processes of the session the current process runs in
+  /// 
+  /// That's the caller's own session, which on a terminal server differs from
+  /// the console one `WTSGetActiveConsoleSessionId` returns
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let processes = Process::all_in_current_session().expect("Couldn't create snapshot");
+  /// println!("found {:?}", processes);
+  /// ```
+  pub fn all_in_current_session() -> io::Result<Vec<Self>> {
+    Process::all_in_session(session_of(unsafe { GetCurrentProcessId() })?)
+  }
+
+  /// Gets all processes of the session attached to the physical console
+  /// 
+  /// That's the interactive desktop even when the caller is a service. Fails
+  /// with [`io::ErrorKind::NotFound`] while no session is attached, e.g.
+  /// during a session switch.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let processes = Process::all_in_console_session().expect("Couldn't create snapshot");
+  /// println!("found {:?}", processes);
+  /// ```
+  pub fn all_in_console_session() -> io::Result<Vec<Self>> {
+    match unsafe { WTSGetActiveConsoleSessionId() } {
+      u32::MAX => Err(io::Error::new(io::ErrorKind::NotFound, "no session is attached to the console")),
+      session => Process::all_in_session(session)
+    }
+  }
+
+  /// Returns terminal services session of the process, 0 for services
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// if process.session_id().expect("Couldn't query session") == 0 {
+  ///   println!("{} is a service", process.get_name());
+  /// }
+  /// ```
+  pub fn session_id(&self) -> io::Result<u32> {
+    session_of(self.id)
+  }
+
+  /// Wraps an existing process handle, taking ownership of it
+  /// 
+  /// Id and name are queried from the handle, so it needs at least
+  /// `PROCESS_QUERY_LIMITED_INFORMATION` access. The handle is closed with
+  /// `CloseHandle` once the returned process and all of its clones are
+  /// dropped, the caller must not close it too.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// use winapi::um::processthreadsapi::OpenProcess;
+  /// use winapi::um::winnt::PROCESS_ALL_ACCESS;
+  /// let handle = unsafe { OpenProcess(PROCESS_ALL_ACCESS, 0, 1234) };
+  /// let process = Process::from_handle(handle).expect("bad handle");
+  /// ```
+  pub fn from_handle(handle: HANDLE) -> io::Result<Process> {
+    if handle.is_null() || handle == INVALID_HANDLE_VALUE {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid process handle"));
+    }
+
+    let handle = HandleGuard(handle);
+
+    let id = unsafe { GetProcessId(handle.get()) };
+    if id == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    let path = query_image_path(handle.get(), false)?;
+    let x64 = query_x64(handle.get())?;
+
+    let name = image_name(&path).unwrap_or_default();
+
+    Ok(Self {
+      id,
+      name,
+      handle: Arc::new(handle),
+      main_module: OnceLock::new(),
+      modules: OnceLock::new(),
+      path: OnceLock::from(path),
+      x64
+    })
+  }
+
+  pub(crate) fn from_entry(entry: &ProcessEntry) -> io::Result<Self> {
+    Self::open_entry(entry, PROCESS_ALL_ACCESS, false)
+  }
+
+  pub(crate) fn open_entry(entry: &ProcessEntry, access: u32, inherit: bool) -> io::Result<Self> {
+    let handle = unsafe {
+      OpenProcess(access, inherit as i32, entry.id)
+    };
+
+    if handle.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+    let handle = HandleGuard(handle);
+    let x64 = query_x64(handle.get())?;
+
+    // same source as from_handle, the snapshot name is only a fallback
+    let path = query_image_path(handle.get(), false).ok();
+    let name = path.as_deref()
+      .and_then(image_name)
+      .unwrap_or_else(|| entry.name.clone());
+
+    Ok(Self {
+      id: entry.id,
+      name,
+      handle: Arc::new(handle),
+      main_module: OnceLock::new(),
+      modules: OnceLock::new(),
+      path: path.map_or_else(OnceLock::new, OnceLock::from),
+      x64
+    })
+  }
+
+  /// Finds process by name
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// println!("found {}", process);
+  /// ```
+  pub fn find(name: &str) -> io::Result<Self> {
+    let all = Process::all()?;
+
+    for process in all {
+      if process.name == name {
+        return Ok(process);
+      }
+    }
+
+    Err(CuralError::ProcessNotFound(format!("name {}", name)).into())
+  }
+
+  /// Finds process by full image path, case-insensitively and ignoring `\\?\` prefixes
+  /// 
+  /// Paths are compared in their Win32 form, see [`Process::path`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find_by_path(Path::new(r"C:\Games\foo\game.exe")).expect("no such process");
+  /// ```
+  pub fn find_by_path(path: &Path) -> io::Result<Self> {
+    let wanted = normalize_path(&path.to_string_lossy());
+
+    for entry in Process::iter()? {
+      // querying the path needs only limited access, the match is opened fully after
+      let matches = Self::open_entry(&entry, PROCESS_QUERY_LIMITED_INFORMATION, false)
+        .and_then(|process| process.path())
+        .is_ok_and(|path| normalize_path(&path.to_string_lossy()) == wanted);
+
+      if matches {
+        return entry.open();
+      }
+    }
+
+    Err(CuralError::ProcessNotFound(format!("path {}", path.display())).into())
+  }
+
+  /// Finds first process whose [`Process::command_line`] contains `substr`
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let gpu = Process::find_by_cmdline("--type=gpu-process").expect("no such process");
+  /// ```
+  pub fn find_by_cmdline(substr: &str) -> io::Result<Self> {
+    for entry in Process::iter()? {
+      let matches = Self::open_entry(&entry, PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ, false)
+        .and_then(|process| process.command_line())
+        .is_ok_and(|command_line| command_line.contains(substr));
+
+      if matches {
+        return entry.open();
+      }
+    }
+
+    Err(CuralError::ProcessNotFound(format!("command line containing {}", substr)).into())
+  }
+
+  /// Opens process by id when `target` is a decimal number, otherwise finds it by name
+  /// 
+  /// The id takes precedence, so a process named like `1234` can only be reached
+  /// through [`Process::find`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let by_id = Process::resolve("1234").expect("no such process");
+  /// let by_name = Process::resolve("process.exe").expect("no such process");
+  /// ```
+  pub fn resolve(target: &str) -> io::Result<Self> {
+    match target.parse::<u32>() {
+      Ok(id) => Process::open(id),
+      Err(_) => Process::find(target)
+    }
+  }
+
+  /// Reads from process by address
+  /// 
+  /// Only [`Pod`] types can be read, the value is zeroed if the read fails,
+  /// use [`Process::try_read`] to get the error
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let some_data = process.read::<i32>(0x0);
+  /// ```
+  pub fn read<T: Pod>(&self, address: usize) -> T {
+    self.read_value(address).unwrap_or_else(|_| unsafe { mem::zeroed::<T>() })
+  }
+
+  /// Reads from process by address, fails unless the whole value was read
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let some_data = process.try_read::<i32>(0x0).expect("Couldn't read memory");
+  /// ```
+  pub fn try_read<T: Pod>(&self, address: usize) -> io::Result<T> {
+    self.read_value(address)
+  }
+
+  /// Reads exactly `N` consecutive values into a stack array, no heap allocation
+  /// 
+  /// Same as `try_read::<[T; N]>`, fails unless all `N` values were read
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let position = process.read_array_n::<f32, 3>(0x1000).expect("Couldn't read memory");
+  /// let guid = process.read_array_n::<u8, 16>(0x2000).expect("Couldn't read memory");
+  /// ```
+  pub fn read_array_n<T: Pod, const N: usize>(&self, address: usize) -> io::Result<[T; N]> {
+    self.read_value(address)
+  }
+
+  /// Reads bytes from process by address, fails unless all `len` bytes were read
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let bytes = process.read_bytes(0x0, 16).expect("Couldn't read memory");
+  /// ```
+  pub fn read_bytes(&self, address: usize, len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![0u8; len];
+
+    let read = self.read_into(address, &mut buffer)?;
+    if read != len {
+      return Err(short_transfer("read", address, read, len));
+    }
+
+    Ok(buffer)
+  }
+
+  /// Reads `len` bytes and formats them as a classic hex dump, one line per 16
+  /// bytes with the address, hex bytes and a printable ASCII gutter
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// println!("{}", process.hexdump(0x7FF600001000, 0x40).expect("Couldn't read memory"));
+  /// // 00007FF600001000  48 89 5C 24 08 57 48 83  EC 20 48 8B D9 E8 00 00  |H.\$.WH.. H.....|
+  /// ```
+  pub fn hexdump(&self, address: usize, len: usize) -> io::Result<String> {
+    let bytes = self.read_bytes(address, len)?;
+
+    let mut dump = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+      let _ = write!(dump, "{:016X} ", address + line * 16);
+      for index in 0..16 {
+        if index == 8 {
+          dump.push(' ');
+        }
+        match chunk.get(index) {
+          Some(byte) => { let _ = write!(dump, " {:02X}", byte); },
+          None => dump.push_str("   ")
+        }
+      }
+
+      dump.push_str("  |");
+      dump.extend(chunk.iter().map(|byte| match byte.is_ascii_graphic() || *byte == b' ' {
+        true => *byte as char,
+        false => '.'
+      }));
+      dump.push_str("|\n");
+    }
+
+    Ok(dump)
+  }
+
+  /// Reads bytes into the buffer, returns how many were read
+  /// 
+  /// A read which runs into an unmapped or protected page reports the bytes read
+  /// before it, only a read which gets no bytes at all fails
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut buffer = [0u8; 0x2000];
+  /// let read = process.read_into(0x7FF600001000, &mut buffer).expect("Couldn't read memory");
+  /// ```
+  pub fn read_into(&self, address: usize, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+
+    if unsafe {
+      ReadProcessMemory(
+        self.handle.get(),
+        address as *const _,
+        buffer.as_mut_ptr() as *mut _,
+        buffer.len(),
+        &mut read
+      )
+    } == 0 && read == 0 {
+      return Err(self.failure("read", address, buffer.len()));
+    }
+
+    Ok(read)
+  }
+
+  /// Reads pointer of the target's width, 8 bytes for x64 targets and 4 for x86 ones
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let base = process.base_address().expect("no main module");
+  /// let player = process.read_ptr(base + 0x1000).expect("Couldn't read pointer");
+  /// let health = process.read_ptr(player + 0x10).expect("Couldn't read pointer");
+  /// ```
+  pub fn read_ptr(&self, address: usize) -> io::Result<usize> {
+    match self.is_x64()? {
+      true => Ok(self.read_value::<u64>(address)? as usize),
+      false => Ok(self.read_value::<u32>(address)? as usize)
+    }
+  }
+
+  /// Follows a pointer chain, returning the address it ends at
+  /// 
+  /// The pointer at `base` is read, then each offset but the last is added and
+  /// the pointer there is read, the last offset is only added. Pointers are
+  /// read in the target's width like [`Process::read_ptr`]. A failed read is
+  /// reported as [`CuralError::BrokenChain`] with the hop index, 0 being `base`.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let base = process.base_address().expect("no main module");
+  /// let health = process.read_pointer_chain(base + 0x1234, &[0x10, 0x8]).expect("Couldn't follow chain");
+  /// println!("health: {}", process.try_read::<i32>(health).expect("Couldn't read"));
+  /// ```
+  pub fn read_pointer_chain(&self, base: usize, offsets: &[usize]) -> io::Result<usize> {
+    let hop = |hop: usize, address: usize| {
+      self.read_ptr(address).map_err(|error| io::Error::from(CuralError::BrokenChain { hop, address, error }))
+    };
+
+    let mut pointer = hop(0, base)?;
+    let Some((last, offsets)) = offsets.split_last() else {
+      return Ok(pointer);
+    };
+    for (index, offset) in offsets.iter().enumerate() {
+      pointer = hop(index + 1, pointer.wrapping_add(*offset))?;
+    }
+
+    Ok(pointer.wrapping_add(*last))
+  }
+
+  /// Follows a pointer chain from the module base, the first offset is
+  /// relative to it, see [`Process::read_pointer_chain`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let health = process.chain(&main, &[0x1234, 0x10, 0x8]).expect("Couldn't follow chain");
+  /// ```
+  pub fn chain(&self, module: &Module, offsets: &[usize]) -> io::Result<usize> {
+    match offsets.split_first() {
+      Some((first, offsets)) => self.read_pointer_chain(module.address.wrapping_add(*first), offsets),
+      None => Ok(module.address)
+    }
+  }
+
+  /// Reads values at increasing addresses until `stop` returns true or `max` values are read
+  /// 
+  /// Addresses step by `stride` bytes, `0` means `size_of::<T>()`. The value
+  /// `stop` returned true for is not included.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// // null-terminated pointer array
+  /// let pointers = process.read_until::<usize, _>(0x0, 0, |pointer| pointer == &0, 256)
+  ///   .expect("Couldn't read memory");
+  /// ```
+  pub fn read_until<T: Pod, F: Fn(&T) -> bool>(&self, address: usize, stride: usize, stop: F, max: usize) -> io::Result<Vec<T>> {
+    let stride = match stride {
+      0 => mem::size_of::<T>(),
+      stride => stride
+    };
+
+    let mut result = Vec::new();
+    while result.len() < max {
+      let value = self.read_value::<T>(address + result.len() * stride)?;
+      if stop(&value) {
+        break;
+      }
+      result.push(value);
+    }
+
+    Ok(result)
+  }
+
+  pub(crate) fn read_value<T: Copy>(&self, address: usize) -> io::Result<T> {
+    let mut buffer = unsafe {
+      mem::zeroed::<T>()
+    };
+    let mut read = 0;
+
+    if unsafe {
+      ReadProcessMemory(
+        self.handle.get(),
+        address as *const _,
+        &mut buffer as *mut T as *mut _,
+        mem::size_of::<T>(),
+        &mut read
+      )
+    } == 0 && read == 0 {
+      return Err(self.failure("read", address, mem::size_of::<T>()));
+    }
+
+    if read != mem::size_of::<T>() {
+      return Err(short_transfer("read", address, read, mem::size_of::<T>()));
+    }
+
+    Ok(buffer)
+  }
+
+  /// Reads nul-terminated string, chunks never cross a page so the
+  /// read doesn't fail when the string ends right before unmapped memory
+  pub(crate) fn read_c_string(&self, address: usize, max_len: usize) -> io::Result<String> {
+    const CHUNK: usize = 64;
+    const PAGE: usize = 0x1000;
+
+    let mut bytes = Vec::new();
+    while bytes.len() < max_len {
+      let current = address + bytes.len();
+      let len = CHUNK
+        .min(PAGE - current % PAGE)
+        .min(max_len - bytes.len());
+      let chunk = self.read_bytes(current, len)?;
+
+      if let Some(end) = chunk.iter().position(|byte| byte == &0) {
+        bytes.extend_from_slice(&chunk[..end]);
+        break;
+      }
+      bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+  }
+
+  /// Writes to process by address
+  /// 
+  /// Only [`Pod`] types can be written, failures are ignored, use
+  /// [`Process::try_write`] to get the error
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// process.write(123, 0x0);
+  /// ```
+  pub fn write<T: Pod>(&self, value: T, address: usize) {
+    let _ = self.try_write(value, address);
+  }
+
+  /// Writes to process by address, fails unless the whole value was written
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// process.try_write(123, 0x0).expect("Couldn't write memory");
+  /// ```
+  pub fn try_write<T: Pod>(&self, value: T, address: usize) -> io::Result<()> {
+    let bytes = unsafe {
+      std::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>())
+    };
+
+    self.write_all(address, bytes)
+  }
+
+  /// Writes bytes to process by address, returns number of bytes written
+  /// 
+  /// A write which runs into an unmapped or read-only page reports the bytes
+  /// written before it, only a write which puts no bytes at all fails
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let written = process.write_bytes(0x0, &[0x90, 0x90]).expect("Couldn't write memory");
+  /// ```
+  pub fn write_bytes(&self, address: usize, bytes: &[u8]) -> io::Result<usize> {
+    let mut written = 0;
+
+    if unsafe {
+      WriteProcessMemory(
+        self.handle.get(),
+        address as *mut _,
+        bytes.as_ptr() as *const _,
+        bytes.len(),
+        &mut written
+      )
+    } == 0 && written == 0 {
+      return Err(self.failure("write", address, bytes.len()));
+    }
+
+    Ok(written)
+  }
+
+  /// Writes bytes, fails unless all of them were written
+  pub(crate) fn write_all(&self, address: usize, bytes: &[u8]) -> io::Result<()> {
+    let written = self.write_bytes(address, bytes)?;
+    if written != bytes.len() {
+      return Err(short_transfer("write", address, written, bytes.len()));
+    }
+
+    Ok(())
+  }
+
+  /// Writes UTF-8 string to process by address, returns number of bytes written
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let written = process.write_string(0x0, "hello", true).expect("Couldn't write memory");
+  /// ```
+  pub fn write_string(&self, address: usize, s: &str, nul_terminated: bool) -> io::Result<usize> {
+    let mut bytes = s.as_bytes().to_vec();
+    if nul_terminated {
+      bytes.push(0);
+    }
+
+    self.write_bytes(address, &bytes)
+  }
+
+  /// Writes UTF-16 string to process by address, returns number of bytes written
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let written = process.write_wstring(0x0, "C:\\payload.dll", true).expect("Couldn't write memory");
+  /// ```
+  pub fn write_wstring(&self, address: usize, s: &str, nul_terminated: bool) -> io::Result<usize> {
+    let mut units = s.encode_utf16().collect::<Vec<u16>>();
+    if nul_terminated {
+      units.push(0);
+    }
+
+    let bytes = units.into_iter()
+      .flat_map(|unit| unit.to_le_bytes())
+      .collect::<Vec<u8>>();
+
+    self.write_bytes(address, &bytes)
+  }
+
+  /// Allocates committed memory in the process with `PAGE_*` protection
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// use winapi::um::winnt::PAGE_READWRITE;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let address = process.alloc(0x1000, PAGE_READWRITE).expect("Couldn't allocate");
+  /// process.free(address).expect("Couldn't free");
+  /// ```
+  pub fn alloc(&self, size: usize, protect: u32) -> io::Result<usize> {
+    let address = unsafe {
+      VirtualAllocEx(self.handle.get(), ptr::null_mut(), size, MEM_COMMIT | MEM_RESERVE, protect)
+    };
+
+    if address.is_null() {
+      return Err(self.failure("alloc", 0, size));
+    }
+
+    Ok(address as usize)
+  }
+
+  /// Releases memory allocated with [`Process::alloc`]
+  pub fn free(&self, address: usize) -> io::Result<()> {
+    if unsafe { VirtualFreeEx(self.handle.get(), address as *mut _, 0, MEM_RELEASE) } == 0 {
+      return Err(self.failure("free", address, 0));
+    }
+
+    Ok(())
+  }
+
+  /// Changes `PAGE_*` protection of committed memory, returns the previous protection
+  /// of the first page
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// use winapi::um::winnt::PAGE_EXECUTE_READWRITE;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let old = process.protect(0x7FF600001000, 0x1000, PAGE_EXECUTE_READWRITE).expect("Couldn't protect");
+  /// ```
+  pub fn protect(&self, address: usize, size: usize, protect: u32) -> io::Result<u32> {
+    let mut old = 0;
+    if unsafe { VirtualProtectEx(self.handle.get(), address as *mut _, size, protect, &mut old) } == 0 {
+      return Err(self.failure("protect", address, size));
+    }
+
+    Ok(old)
+  }
+
+  /// Writes over code, making it writable meanwhile and flushing the instruction cache after
+  pub(crate) fn write_code(&self, address: usize, bytes: &[u8]) -> io::Result<()> {
+    let old = self.protect(address, bytes.len(), PAGE_EXECUTE_READWRITE)?;
+    let written = self.write_all(address, bytes);
+    self.protect(address, bytes.len(), old)?;
+    written?;
+
+    if unsafe { FlushInstructionCache(self.handle.get(), address as *const _, bytes.len()) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+  }
+
+  /// Gets module address
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let kernel = process.get_module("KERNEL32.DLL").expect("no such dll");
+  /// ```
+  pub fn get_module(&self, module: &str) -> io::Result<Module> {
+    let matches = |found: &Module| found.name.eq_ignore_ascii_case(module);
+
+    let found = match self.modules() {
+      Ok(mut modules) => modules
+        .find(|found| found.as_ref().map_or(true, matches))
+        .transpose()?,
+      Err(_) => psapi_modules(self)?.into_iter().find(matches)
+    };
+
+    found.ok_or_else(|| CuralError::ModuleNotFound(format!("name {}", module)).into())
+  }
+
+  /// Same as [`Process::get_module`], but binds the module to the process for
+  /// base-relative reads and writes
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let game = process.get_bound_module("game.dll").expect("no such dll");
+  /// let health = game.read::<i32>(0x1F2A0);
+  /// ```
+  pub fn get_bound_module(&self, module: &str) -> io::Result<BoundModule<'_>> {
+    Ok(self.get_module(module)?.bind(self))
+  }
+
+  /// Finds module by its full path, case-insensitively
+  /// 
+  /// Tells apart modules with the same name loaded from different directories
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let kernel = process.get_module_by_path(Path::new(r"C:\Windows\System32\kernel32.dll")).expect("no such dll");
+  /// ```
+  pub fn get_module_by_path(&self, path: &Path) -> io::Result<Module> {
+    let path = path.to_string_lossy();
+
+    self.get_all_modules()?
+      .into_iter()
+      .find(|module| module.path.to_string_lossy().eq_ignore_ascii_case(&path))
+      .ok_or_else(|| CuralError::ModuleNotFound(format!("path {}", path)).into())
+  }
+
+  /// Returns all modules
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let modules = process.get_all_modules().expect("error getting modules");
+  /// ```
+  pub fn get_all_modules(&self) -> io::Result<Vec<Module>> {
+    self.get_all_modules_using(ModuleEnumStrategy::Auto)
+  }
+
+  /// Returns modules included by the filter, see [`ModuleFilter`] for the WOW64 interaction
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{ModuleFilter, Process};
+  /// let process = Process::find("game32.exe").expect("no such process");
+  /// let modules = process.get_all_modules_filtered(ModuleFilter::Bit32).expect("error getting modules");
+  /// ```
+  pub fn get_all_modules_filtered(&self, filter: ModuleFilter) -> io::Result<Vec<Module>> {
+    self.get_all_modules_with(SnapshotOptions { filter, ..Default::default() })
+  }
+
+  /// Returns all modules enumerated with the given backend
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{ModuleEnumStrategy, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let modules = process.get_all_modules_using(ModuleEnumStrategy::Psapi).expect("error getting modules");
+  /// ```
+  pub fn get_all_modules_using(&self, strategy: ModuleEnumStrategy) -> io::Result<Vec<Module>> {
+    match strategy {
+      ModuleEnumStrategy::Toolhelp => self.modules()?.collect(),
+      ModuleEnumStrategy::Psapi => psapi_modules(self),
+      ModuleEnumStrategy::Peb => self.modules_via_peb(),
+      ModuleEnumStrategy::Auto => self.modules()
+        .and_then(|modules| modules.collect())
+        .or_else(|_| psapi_modules(self))
+    }
+  }
+
+  /// Returns modules in load order by walking `PEB.Ldr->InLoadOrderModuleList`
+  /// in the target's memory, no snapshot is taken
+  /// 
+  /// WOW64 targets report their 32-bit modules. Fails with
+  /// [`io::ErrorKind::InvalidData`] before the loader is initialized, e.g. in
+  /// a process created suspended, or while the list is garbage. Needs
+  /// `PROCESS_VM_READ`.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let modules = process.modules_via_peb().expect("Couldn't walk loader list");
+  /// println!("loaded first {:?}", modules.first());
   /// ```
-  pub fn all() -> io::Result<Vec<Self>> {
-    let mut result = Vec::new();
+  pub fn modules_via_peb(&self) -> io::Result<Vec<Module>> {
+    self.peb()?
+      .ldr_modules()?
+      .map(|entry| entry.map(Module::from))
+      .collect()
+  }
 
-    let mut entry = unsafe { mem::zeroed::<PROCESSENTRY32>() };
-    entry.dwSize = mem::size_of::<PROCESSENTRY32>() as u32;
+  /// Lazily iterates over module snapshot
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let kernel = process.modules().expect("error getting modules")
+  ///   .filter_map(Result::ok)
+  ///   .find(|module| module.get_name().eq_ignore_ascii_case("kernel32.dll"));
+  /// ```
+  pub fn modules(&self) -> io::Result<Modules> {
+    self.modules_with(SnapshotOptions::default())
+  }
 
-    let snapshot = unsafe {
-      CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
-    };
+  /// Lazily iterates over module snapshot, retrying snapshot creation as configured
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::{Process, SnapshotOptions};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let options = SnapshotOptions { retries: 20, delay: Duration::from_millis(50), ..Default::default() };
+  /// let modules = process.modules_with(options).expect("error getting modules");
+  /// ```
+  pub fn modules_with(&self, options: SnapshotOptions) -> io::Result<Modules> {
+    Modules::new(self.id, options)
+  }
 
-    if snapshot == INVALID_HANDLE_VALUE {
-      return Err(io::Error::new(
-        io::ErrorKind::Interrupted,
-        "Couldn't create snapshot tool"
-      ));
+  /// Returns all modules, retrying snapshot creation as configured
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{Process, SnapshotOptions};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let modules = process.get_all_modules_with(SnapshotOptions::default()).expect("error getting modules");
+  /// ```
+  pub fn get_all_modules_with(&self, options: SnapshotOptions) -> io::Result<Vec<Module>> {
+    self.modules_with(options)?.collect()
+  }
+
+  /// Returns main module of the process (its executable image)
+  /// 
+  /// The main module is taken as the first entry of the module snapshot,
+  /// toolhelp always reports the process image first. The result is cached,
+  /// so repeated calls don't re-snapshot. ASLR only picks the image base at
+  /// launch, so the cached base stays valid for the lifetime of the process.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// ```
+  pub fn main_module(&self) -> io::Result<Module> {
+    if let Some(module) = self.main_module.get() {
+      return Ok(module.clone());
     }
 
-    while unsafe { Process32Next(snapshot, &mut entry) } != 0 {
-      let id = entry.th32ProcessID;
-      let handle = unsafe {
-        OpenProcess(PROCESS_ALL_ACCESS, 0, id)
-      };
+    let module = self.modules()?
+      .next()
+      .transpose()?
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no main module in process {}", self.id)
+      ))?;
 
-      if handle == INVALID_HANDLE_VALUE {
-        continue;
-      }
+    Ok(self.main_module.get_or_init(|| module).clone())
+  }
 
-      let c_name = entry.szExeFile.into_iter()
-        .take_while(|byte| byte != &0)
-        .map(|byte| byte as u8 as char)
-        .collect::<String>();
+  /// Returns base address of the main module
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let base = process.base_address().expect("no main module");
+  /// ```
+  pub fn base_address(&self) -> io::Result<usize> {
+    Ok(self.main_module()?.address)
+  }
 
-      result.push(Self { id, name: c_name, handle })
-    }
+  /// Finds module which contains the address
+  /// 
+  /// Uses the cached module list, see [`Process::refresh_modules`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let module = process.module_at(0x7FF600001000).expect("error getting modules");
+  /// ```
+  pub fn module_at(&self, address: usize) -> io::Result<Option<Module>> {
+    Ok(self.cached_modules()?
+      .iter()
+      .find(|module| module.contains(address))
+      .cloned())
+  }
 
-    unsafe { CloseHandle(snapshot) };
+  /// Finds modules which contain each of the addresses
+  /// 
+  /// All addresses are resolved against one cached module list
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let modules = process.modules_at(&[0x7FF600001000, 0x0]).expect("error getting modules");
+  /// ```
+  pub fn modules_at(&self, addresses: &[usize]) -> io::Result<Vec<Option<Module>>> {
+    let modules = self.cached_modules()?;
 
-    Ok(result)
+    Ok(addresses.iter()
+      .map(|address| modules.iter().find(|module| module.contains(*address)).cloned())
+      .collect())
   }
 
-  /// Finds process by name
+  /// Formats address as `module+0xOFFSET` or plain `0xADDRESS` when no module contains it
   /// 
   /// # Examples
   /// ```
   /// use cural::Process;
   /// let process = Process::find("process.exe").expect("no such process");
-  /// println!("found {}", process);
+  /// println!("{}", process.format_address(0x7FF600001000));
   /// ```
-  pub fn find(name: &str) -> io::Result<Self> {
-    let all = Process::all()?;
+  pub fn format_address(&self, address: usize) -> String {
+    match self.module_at(address) {
+      Ok(Some(module)) => format!("{}+0x{:X}", module.name, address - module.address),
+      _ => format!("0x{:X}", address)
+    }
+  }
 
-    for process in all {
-      if &process.name == name {
-        return Ok(process);
+  /// Re-snapshots module list used by [`Process::module_at`] and [`Process::modules_at`]
+  pub fn refresh_modules(&mut self) -> io::Result<()> {
+    let modules = self.get_all_modules()?;
+    self.modules = OnceLock::from(modules);
+
+    Ok(())
+  }
+
+  fn cached_modules(&self) -> io::Result<&[Module]> {
+    if let Some(modules) = self.modules.get() {
+      return Ok(modules);
+    }
+
+    let modules = self.get_all_modules()?;
+    Ok(self.modules.get_or_init(|| modules))
+  }
+
+  /// Terminates the process with `exit_code`, requires `PROCESS_TERMINATE`
+  /// 
+  /// Killing a process which already exited succeeds, `TerminateProcess`
+  /// reports `ERROR_ACCESS_DENIED` for those so the exit code is checked,
+  /// which takes `PROCESS_QUERY_LIMITED_INFORMATION`. Termination is
+  /// asynchronous, poll [`Process::exit_code`] to wait for it.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// use winapi::um::winnt::PROCESS_TERMINATE;
+  /// let process = Process::builder()
+  ///   .access(PROCESS_TERMINATE)
+  ///   .name("process.exe")
+  ///   .open()
+  ///   .expect("no such process");
+  /// process.kill(1).expect("Couldn't kill");
+  /// while process.exit_code().expect("Couldn't get exit code").is_none() {}
+  /// ```
+  pub fn kill(&self, exit_code: u32) -> io::Result<()> {
+    if unsafe { TerminateProcess(self.handle.get(), exit_code) } == 0 {
+      let error = io::Error::last_os_error();
+      if let Ok(Some(_)) = self.exit_code() {
+        return Ok(());
       }
+      return Err(error);
     }
 
-    Err(io::Error::new(
-      io::ErrorKind::NotFound,
-      format!("no process found with name {}", name)
-    ))
+    Ok(())
   }
 
-  /// Reads from process by address
+  /// Returns exit code of the process, `None` while it's running
+  /// 
+  /// Needs `PROCESS_QUERY_LIMITED_INFORMATION`. A process which exited with
+  /// `STILL_ACTIVE` (259) is reported as running.
+  pub fn exit_code(&self) -> io::Result<Option<u32>> {
+    let mut code = 0;
+    if unsafe { GetExitCodeProcess(self.handle.get(), &mut code) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    match code {
+      STILL_ACTIVE => Ok(None),
+      code => Ok(Some(code))
+    }
+  }
+
+  /// Returns how many kernel handles the process has open, needs
+  /// `PROCESS_QUERY_LIMITED_INFORMATION`
+  /// 
+  /// Works on the current process too, so comparing counts before and after
+  /// repeating an operation shows whether it leaks handles.
   /// 
   /// # Examples
   /// ```
   /// use cural::Process;
   /// let process = Process::find("process.exe").expect("no such process");
-  /// let some_data = process.read::<i32>(0x0);
+  /// let before = process.handle_count().expect("Couldn't count handles");
+  /// for _ in 0..100 {
+  ///   let _ = process.modules();
+  /// }
+  /// println!("{} handles more", process.handle_count().expect("Couldn't count handles") as i64 - before as i64);
   /// ```
-  pub fn read<T>(&self, address: usize) -> T {
-    let mut buffer = unsafe {
-        mem::zeroed::<T>()
-    };
-
-    unsafe {
-      ReadProcessMemory(
-        self.handle,
-        address as *const _,
-        &mut buffer as *mut T as *mut _,
-        mem::size_of::<T>(),
-        ptr::null_mut()
-      );
+  pub fn handle_count(&self) -> io::Result<u32> {
+    let mut count = 0;
+    if unsafe { GetProcessHandleCount(self.handle.get(), &mut count) } == 0 {
+      return Err(io::Error::last_os_error());
     }
 
-    buffer
+    Ok(count)
   }
 
-  /// Writes to process by address
+  /// Returns is the process still running
+  /// 
+  /// Waits on the handle with `SYNCHRONIZE`, falling back to the exit code
+  /// with `PROCESS_QUERY_LIMITED_INFORMATION`. The handle keeps referring to
+  /// the same process, so a reused id can't make it look alive. A process
+  /// which can't be checked either way is assumed to run.
   /// 
   /// # Examples
   /// ```
   /// use cural::Process;
   /// let process = Process::find("process.exe").expect("no such process");
-  /// process.write(123, 0x0);
+  /// while process.is_running() {
+  ///   let health = process.try_read::<i32>(0x1000);
+  /// }
   /// ```
-  pub fn write<T>(&self, value: T, address: usize) {
-    unsafe {
-      WriteProcessMemory(
-        self.handle,
-        address as *mut _,
-        &value as *const T as *const _,
-        mem::size_of::<T>(),
-        ptr::null_mut()
-      )
-    };
+  pub fn is_running(&self) -> bool {
+    match unsafe { WaitForSingleObject(self.handle.get(), 0) } {
+      WAIT_FAILED => self.exit_code().map_or(true, |code| code.is_none()),
+      WAIT_TIMEOUT => true,
+      _ => false
+    }
   }
 
-  /// Gets module address
+  /// Revalidates the process and re-reads its name, dropping cached modules
+  /// 
+  /// Fails with [`CuralError::ProcessExited`] once the process has exited
   /// 
   /// # Examples
   /// ```
   /// use cural::Process;
+  /// let mut process = Process::find("process.exe").expect("no such process");
+  /// process.refresh().expect("process has exited");
+  /// ```
+  pub fn refresh(&mut self) -> io::Result<()> {
+    if let Some(exit_code) = self.exit_code()? {
+      return Err(CuralError::ProcessExited { id: self.id, exit_code }.into());
+    }
+
+    let path = query_image_path(self.handle.get(), false)?;
+    if let Some(name) = image_name(&path) {
+      self.name = name;
+    }
+    self.path = OnceLock::from(path);
+    self.main_module = OnceLock::new();
+    self.modules = OnceLock::new();
+
+    Ok(())
+  }
+
+  /// Wraps the last OS error of a failed memory operation, reporting
+  /// [`CuralError::ProcessExited`] instead when the process is gone
+  fn failure(&self, operation: &str, address: usize, size: usize) -> io::Error {
+    let error = io::Error::last_os_error();
+    let error = match self.exit_code() {
+      Ok(Some(exit_code)) => CuralError::ProcessExited { id: self.id, exit_code }.into(),
+      _ => error
+    };
+
+    logging::failed(operation, address, size, error)
+  }
+
+  /// Waits for the process to exit, `None` waits forever
+  /// 
+  /// Requires `SYNCHRONIZE`, which the default `PROCESS_ALL_ACCESS` includes,
+  /// and `PROCESS_QUERY_LIMITED_INFORMATION` for the exit code
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::{Process, WaitResult};
   /// let process = Process::find("process.exe").expect("no such process");
-  /// let kernel = process.get_module("KERNEL32.DLL").expect("no such dll");
+  /// match process.wait(Some(Duration::from_secs(5))).expect("Couldn't wait") {
+  ///   WaitResult::Exited(code) => println!("exited with {}", code),
+  ///   WaitResult::TimedOut => println!("still running")
+  /// }
   /// ```
-  pub fn get_module(&self, module: &str) -> io::Result<Module> {
-    let all = self.get_all_modules()?;
+  pub fn wait(&self, timeout: Option<Duration>) -> io::Result<WaitResult> {
+    let millis = timeout.map_or(INFINITE, |timeout| timeout.as_millis().min(INFINITE as u128 - 1) as u32);
+    match unsafe { WaitForSingleObject(self.handle.get(), millis) } {
+      WAIT_FAILED => return Err(io::Error::last_os_error()),
+      WAIT_TIMEOUT => return Ok(WaitResult::TimedOut),
+      _ => {}
+    }
+
+    // a process which exited with STILL_ACTIVE itself is reported as such
+    Ok(WaitResult::Exited(self.exit_code()?.unwrap_or(STILL_ACTIVE)))
+  }
+
+  /// Returns is process x64 or no
+  /// 
+  /// Bitness can't change during the lifetime of a process, so it's queried
+  /// once when the process is opened and this never fails
+  pub fn is_x64(&self) -> io::Result<bool> {
+    Ok(self.x64)
+  }
 
-    for module in all {
-      return Ok(module);
+  /// Returns full path of the executable, retrieved once and cached
+  /// 
+  /// Needs only `PROCESS_QUERY_LIMITED_INFORMATION`. Processes with the same
+  /// [`Process::get_name`] can be told apart by it.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("game.exe").expect("no such process");
+  /// println!("runs from {}", process.path().expect("Couldn't get path").display());
+  /// ```
+  pub fn path(&self) -> io::Result<PathBuf> {
+    if let Some(path) = self.path.get() {
+      return Ok(path.clone());
     }
 
-    Err(io::Error::new(
-      io::ErrorKind::NotFound,
-      format!("no module with name {}", module)
-    ))
+    let path = query_image_path(self.handle.get(), false)?;
+    Ok(self.path.get_or_init(|| path).clone())
   }
 
-  /// Returns all modules
+  /// Returns full path of the executable in its native `\Device\...` form, not cached
+  pub fn native_path(&self) -> io::Result<PathBuf> {
+    query_image_path(self.handle.get(), true)
+  }
+
+  /// Returns command line the process was started with
+  /// 
+  /// Asks `NtQueryInformationProcess` on Windows 8.1+, otherwise reads it from
+  /// the remote PEB, which also needs `PROCESS_VM_READ`. Unreadable or
+  /// inconsistent PEB strings fail with [`io::ErrorKind::InvalidData`].
   /// 
   /// # Examples
   /// ```
   /// use cural::Process;
-  /// let process = Process::find("process.exe").expect("no such process");
-  /// let modules = process.get_all_modules().expect("error getting modules");
+  /// let process = Process::find("chrome.exe").expect("no such process");
+  /// println!("started as {}", process.command_line().expect("Couldn't get command line"));
   /// ```
-  pub fn get_all_modules(&self) -> io::Result<Vec<Module>> {
-    let mut modules = Vec::new();
+  pub fn command_line(&self) -> io::Result<String> {
+    ntdll::process_command_line(self.handle.get())
+      .or_else(|_| self.peb_command_line())
+  }
+
+  /// Returns address of the PEB in the target's own layout
+  pub(crate) fn peb_address(&self) -> io::Result<usize> {
+    if self.x64 && cfg!(target_pointer_width = "32") {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, "x86 process can't read PEB of an x64 one"));
+    }
 
-    let mut entry = unsafe { mem::zeroed::<MODULEENTRY32>() };
-    entry.dwSize = mem::size_of::<MODULEENTRY32>() as u32;
+    ntdll::peb_address(self.handle.get(), self.x64)
+  }
 
-    let snapshot = unsafe {
-      CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, self.id)
-    };
+  /// Returns size of the target's pointers
+  pub(crate) fn ptr_size(&self) -> usize {
+    match self.x64 {
+      true => 8,
+      false => 4
+    }
+  }
 
-    if snapshot == INVALID_HANDLE_VALUE {
+  /// Reads remote `UNICODE_STRING` in the target's layout, failing on inconsistent ones
+  pub(crate) fn read_unicode_string(&self, address: usize) -> io::Result<String> {
+    let len = self.read_value::<u16>(address)? as usize;
+    let max = self.read_value::<u16>(address + 2)? as usize;
+    let buffer = self.read_ptr(address + self.ptr_size())?;
+    if buffer == 0 || !len.is_multiple_of(2) || len > max {
       return Err(io::Error::new(
-        io::ErrorKind::Interrupted,
-        "Couldn't create snapshot tool"
+        io::ErrorKind::InvalidData,
+        format!("garbage UNICODE_STRING at 0x{:X}", address)
       ));
     }
 
-    if unsafe { Module32First(snapshot, &mut entry) } == 0 {
-      return Ok(modules);
+    let text = self.read_bytes(buffer, len)?
+      .chunks_exact(2)
+      .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+      .collect::<Vec<u16>>();
+    Ok(String::from_utf16_lossy(&text))
+  }
+
+  /// Returns the PEB and `RTL_USER_PROCESS_PARAMETERS` addresses
+  fn process_parameters(&self) -> io::Result<(usize, usize)> {
+    let peb = self.peb_address()?;
+    let parameters = self.read_ptr(peb + 4 * self.ptr_size())?;
+    if parameters == 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("no process parameters in PEB at 0x{:X}", peb)
+      ));
     }
 
-    loop {
-      let c_module = entry.szModule.into_iter()
-        .take_while(|byte| byte != &0)
-        .map(|byte| byte as u8 as char)
-        .collect::<String>();
+    Ok((peb, parameters))
+  }
+
+  /// Returns environment variables of the process, read from its PEB
+  /// 
+  /// Entries starting with `=`, like the per-drive `=C:=C:\dir` ones, are
+  /// skipped, [`Process::raw_environment`] keeps them. Needs `PROCESS_VM_READ`.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("worker.exe").expect("no such process");
+  /// let environment = process.environment().expect("Couldn't read environment");
+  /// println!("role {:?}", environment.get("WORKER_ROLE"));
+  /// ```
+  pub fn environment(&self) -> io::Result<HashMap<String, String>> {
+    Ok(
+      self.raw_environment()?
+        .into_iter()
+        .filter(|entry| !entry.starts_with('='))
+        .filter_map(|entry| entry.split_once('=').map(|(name, value)| (name.to_string(), value.to_string())))
+        .collect()
+    )
+  }
+
+  /// Returns every `NAME=value` entry of the environment block as is, in order
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// for entry in process.raw_environment().expect("Couldn't read environment") {
+  ///   println!("{}", entry);
+  /// }
+  /// ```
+  pub fn raw_environment(&self) -> io::Result<Vec<String>> {
+    let (peb, parameters) = self.process_parameters()?;
+    // RTL_USER_PROCESS_PARAMETERS.Environment
+    let block = self.read_ptr(parameters + match self.x64 {
+      true => 0x80,
+      false => 0x48
+    })?;
+    if block == 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("no environment block in PEB at 0x{:X}", peb)
+      ));
+    }
 
-      modules.push(Module { name: c_module, address: entry.modBaseAddr as usize });
+    // the block ends with an empty entry, read page-sized chunks until one shows up
+    let mut units: Vec<u16> = Vec::new();
+    let mut chunk = [0u8; 0x1000];
+    loop {
+      if units.len() * 2 >= ENVIRONMENT_LIMIT {
+        return Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("environment block at 0x{:X} isn't terminated", block)
+        ));
+      }
 
-      if unsafe { Module32Next(snapshot, &mut entry) } == 0 {
+      let read = self.read_into(block + units.len() * 2, &mut chunk)?;
+      units.extend(chunk[..read & !1].chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])));
+      if units.windows(2).any(|pair| pair == [0, 0]) || units.first() == Some(&0) {
         break;
       }
     }
 
-    unsafe { CloseHandle(snapshot) };
-
-    Ok(modules)
+    Ok(
+      units.split(|unit| *unit == 0)
+        .take_while(|entry| !entry.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect()
+    )
   }
 
-  /// Returns is process x64 or no
-  pub fn is_x64(&self) -> io::Result<bool> {
-    let mut is_x64 = 0;
-    
-    if unsafe { IsWow64Process(self.handle, &mut is_x64) } != 1 {
-      return Err(io::Error::last_os_error());
-    }
+  /// Reads `PEB.ProcessParameters->CommandLine` in the target's layout
+  fn peb_command_line(&self) -> io::Result<String> {
+    let (_, parameters) = self.process_parameters()?;
 
-    return Ok(is_x64 != 1);
+    // RTL_USER_PROCESS_PARAMETERS.CommandLine
+    self.read_unicode_string(parameters + match self.x64 {
+      true => 0x70,
+      false => 0x40
+    })
   }
 
   /// Returns windows process handle
+  /// 
+  /// The handle is owned by the process and closed when it (and all of its
+  /// clones) are dropped
   pub fn get_handle(&self) -> HANDLE {
-    self.handle
+    self.handle.get()
+  }
+
+  /// Borrows windows process handle without transferring ownership
+  /// 
+  /// The borrow can't outlive the process, unlike the raw handle from
+  /// [`Process::get_handle`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::os::windows::io::AsRawHandle;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let handle = process.borrow_handle();
+  /// println!("handle {:?}", handle.as_raw_handle());
+  /// ```
+  pub fn borrow_handle(&self) -> BorrowedHandle<'_> {
+    unsafe { BorrowedHandle::borrow_raw(self.handle.get() as RawHandle) }
   }
 
   /// Returns name field of process
+  /// 
+  /// It's the file name of the image from `QueryFullProcessImageNameW`
+  /// however the process was opened, falling back to the snapshot name when
+  /// that can't be queried. Captured once, see [`Process::refresh`].
   pub fn get_name(&self) -> &str {
     &self.name
   }
@@ -249,16 +1576,115 @@ impl Process {
   pub fn get_id(&self) -> &u32 {
     &self.id
   }
+
+  /// Returns id of the process which created this one
+  /// 
+  /// The parent may have exited since and its id may belong to another
+  /// process by now, [`Process::parent`] checks for that
+  pub fn parent_id(&self) -> io::Result<u32> {
+    ntdll::parent_process_id(self.handle.get())
+  }
+
+  /// Opens parent process, `None` when it has exited
+  /// 
+  /// A process with the parent's id which was created after this one has just
+  /// reused the id, it's not reported as the parent
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// match process.parent().expect("Couldn't open parent") {
+  ///   Some(parent) => println!("started by {}", parent),
+  ///   None => println!("parent has exited")
+  /// }
+  /// ```
+  pub fn parent(&self) -> io::Result<Option<Process>> {
+    let parent = match Process::open(self.parent_id()?) {
+      Ok(parent) => parent,
+      Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(error) => return Err(error)
+    };
+
+    match parent.creation_time()? <= self.creation_time()? {
+      true => Ok(Some(parent)),
+      false => Ok(None)
+    }
+  }
+
+  /// Returns when the process was created
+  pub(crate) fn creation_time(&self) -> io::Result<SystemTime> {
+    let mut creation = unsafe { mem::zeroed::<FILETIME>() };
+    let mut exit = unsafe { mem::zeroed::<FILETIME>() };
+    let mut kernel = unsafe { mem::zeroed::<FILETIME>() };
+    let mut user = unsafe { mem::zeroed::<FILETIME>() };
+    if unsafe { GetProcessTimes(self.handle.get(), &mut creation, &mut exit, &mut kernel, &mut user) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(filetime_to_system_time(&creation))
+  }
 }
 
-impl ToString for Process {
-    fn to_string(&self) -> String {
-      format!("{}({})", self.name, self.id)
+impl Display for Process {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      write!(f, "{}({})", self.name, self.id)
     }
 }
 
+impl AsHandle for Process {
+  fn as_handle(&self) -> BorrowedHandle<'_> {
+    self.borrow_handle()
+  }
+}
+
 impl Debug for Process {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.to_string())
+        Display::fmt(self, f)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Process;
+
+  #[test]
+  fn find_and_open_agree_on_name() {
+    let exe = std::env::current_exe().expect("Couldn't get current exe");
+    let name = exe.file_name().expect("exe has no file name").to_string_lossy();
+
+    let found = Process::find(&name).expect("Couldn't find current process");
+    let opened = Process::open(std::process::id()).expect("Couldn't open current process");
+    assert_eq!(found.get_name(), opened.get_name());
+  }
+
+  #[test]
+  fn get_all_modules_keeps_handle_count_flat() {
+    let process = Process::open(std::process::id()).expect("Couldn't open current process");
+    // the first snapshot may load dlls which keep handles of their own
+    process.get_all_modules().expect("Couldn't get modules");
+
+    let before = process.handle_count().expect("Couldn't count handles");
+    for _ in 0..100 {
+      process.get_all_modules().expect("Couldn't get modules");
+    }
+    let after = process.handle_count().expect("Couldn't count handles");
+
+    // tests run in parallel, a leak would add one handle per call
+    assert!(after < before + 50, "{} handles before, {} after", before, after);
+  }
+
+  #[test]
+  fn open_keeps_handle_count_flat() {
+    let process = Process::open(std::process::id()).expect("Couldn't open current process");
+
+    let before = process.handle_count().expect("Couldn't count handles");
+    for _ in 0..100 {
+      Process::open(std::process::id()).expect("Couldn't open current process");
+    }
+    let after = process.handle_count().expect("Couldn't count handles");
+
+    // every open takes a snapshot and a process handle, both must be closed
+    assert!(after < before + 50, "{} handles before, {} after", before, after);
+  }
+}
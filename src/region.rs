@@ -0,0 +1,291 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+
+use winapi::um::memoryapi::VirtualQueryEx;
+use winapi::um::winnt::MEMORY_BASIC_INFORMATION;
+use winapi::um::winnt::MEM_COMMIT;
+use winapi::um::winnt::MEM_FREE;
+use winapi::um::winnt::MEM_RESERVE;
+use winapi::um::winnt::PAGE_EXECUTE;
+use winapi::um::winnt::PAGE_EXECUTE_READ;
+use winapi::um::winnt::PAGE_EXECUTE_READWRITE;
+use winapi::um::winnt::PAGE_EXECUTE_WRITECOPY;
+use winapi::um::winnt::PAGE_GUARD;
+use winapi::um::winnt::PAGE_NOACCESS;
+use winapi::um::winnt::PAGE_READONLY;
+use winapi::um::winnt::PAGE_READWRITE;
+use winapi::um::winnt::PAGE_WRITECOPY;
+
+use crate::Process;
+
+const READABLE: u32 = PAGE_READONLY | PAGE_READWRITE | PAGE_WRITECOPY
+  | PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY;
+const WRITABLE: u32 = PAGE_READWRITE | PAGE_WRITECOPY
+  | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY;
+const EXECUTABLE: u32 = PAGE_EXECUTE | PAGE_EXECUTE_READ
+  | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY;
+
+/// Region of pages with the same state and protection, as reported by `VirtualQueryEx`
+#[derive(Clone)]
+pub struct MemoryRegion {
+  pub(crate) address: usize,
+  pub(crate) size: usize,
+  pub(crate) allocation_base: usize,
+  pub(crate) state: u32,
+  pub(crate) protect: u32,
+  pub(crate) kind: u32
+}
+
+impl MemoryRegion {
+  /// Returns base address
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns size in bytes
+  pub fn get_size(&self) -> &usize {
+    &self.size
+  }
+
+  /// Returns base address of the allocation the region belongs to
+  pub fn get_allocation_base(&self) -> &usize {
+    &self.allocation_base
+  }
+
+  /// Returns raw `MEM_*` state
+  pub fn get_state(&self) -> &u32 {
+    &self.state
+  }
+
+  /// Returns raw `PAGE_*` protection
+  pub fn get_protect(&self) -> &u32 {
+    &self.protect
+  }
+
+  /// Returns raw `MEM_IMAGE`/`MEM_MAPPED`/`MEM_PRIVATE` type
+  pub fn get_type(&self) -> &u32 {
+    &self.kind
+  }
+
+  /// Returns is address inside of the region
+  pub fn contains(&self, address: usize) -> bool {
+    self.address <= address && address < self.address + self.size
+  }
+
+  /// Returns are pages readable, guard pages are not
+  pub fn is_readable(&self) -> bool {
+    self.protect & READABLE != 0 && !self.is_guard() && self.protect & PAGE_NOACCESS == 0
+  }
+
+  /// Returns are pages writable (including copy-on-write)
+  pub fn is_writable(&self) -> bool {
+    self.protect & WRITABLE != 0 && !self.is_guard()
+  }
+
+  /// Returns are pages executable
+  pub fn is_executable(&self) -> bool {
+    self.protect & EXECUTABLE != 0
+  }
+
+  /// Returns are pages committed
+  pub fn is_committed(&self) -> bool {
+    self.state == MEM_COMMIT
+  }
+
+  /// Returns are pages only reserved
+  pub fn is_reserved(&self) -> bool {
+    self.state == MEM_RESERVE
+  }
+
+  /// Returns are pages free
+  pub fn is_free(&self) -> bool {
+    self.state == MEM_FREE
+  }
+
+  /// Returns are pages guard pages
+  pub fn is_guard(&self) -> bool {
+    self.protect & PAGE_GUARD != 0
+  }
+}
+
+impl From<&MEMORY_BASIC_INFORMATION> for MemoryRegion {
+  fn from(info: &MEMORY_BASIC_INFORMATION) -> Self {
+    Self {
+      address: info.BaseAddress as usize,
+      size: info.RegionSize,
+      allocation_base: info.AllocationBase as usize,
+      state: info.State,
+      protect: info.Protect,
+      kind: info.Type
+    }
+  }
+}
+
+impl Display for MemoryRegion {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "0x{:X}-0x{:X}", self.address, self.address + self.size)
+  }
+}
+
+impl Debug for MemoryRegion {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Queries region which contains the address
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let region = process.query(0x7FF600001000).expect("Couldn't query memory");
+  /// ```
+  pub fn query(&self, address: usize) -> io::Result<MemoryRegion> {
+    let mut info = unsafe { mem::zeroed::<MEMORY_BASIC_INFORMATION>() };
+
+    if unsafe {
+      VirtualQueryEx(
+        self.handle.get(),
+        address as *const _,
+        &mut info,
+        mem::size_of::<MEMORY_BASIC_INFORMATION>()
+      )
+    } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(MemoryRegion::from(&info))
+  }
+
+  /// Returns every region of the process address space
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let readable = process.regions().expect("Couldn't query memory")
+  ///   .into_iter()
+  ///   .filter(|region| region.is_readable() && region.is_committed())
+  ///   .collect::<Vec<_>>();
+  /// ```
+  pub fn regions(&self) -> io::Result<Vec<MemoryRegion>> {
+    let mut regions = Vec::new();
+
+    let mut address = 0usize;
+    // VirtualQueryEx fails once address passes the highest user-mode address
+    while let Ok(region) = self.query(address) {
+      let next = region.address.checked_add(region.size);
+      regions.push(region);
+
+      match next {
+        Some(next) if next > address => address = next,
+        _ => break
+      }
+    }
+
+    Ok(regions)
+  }
+
+  /// Reads up to `len` bytes, stopping at the first page which isn't readable,
+  /// returns the bytes read and their count
+  /// 
+  /// The read is split per region with `VirtualQueryEx`, so a range running
+  /// into uncommitted, guard or no-access memory returns what comes before it.
+  /// Fails only when the start address can't be queried, an unreadable start
+  /// returns no bytes.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let (dump, read) = process.read_bytes_partial(0x7FF600000000, 0x100000).expect("Couldn't query memory");
+  /// println!("dumped 0x{:X} bytes", read);
+  /// ```
+  pub fn read_bytes_partial(&self, address: usize, len: usize) -> io::Result<(Vec<u8>, usize)> {
+    let mut buffer = vec![0u8; len];
+
+    let mut read = 0;
+    while read < len {
+      let current = address + read;
+      let region = match self.query(current) {
+        Ok(region) => region,
+        Err(error) if read == 0 => return Err(error),
+        Err(_) => break
+      };
+      if !region.is_committed() || !region.is_readable() || region.is_guard() {
+        break;
+      }
+
+      let chunk = (region.address + region.size - current).min(len - read);
+      let chunk_read = self.read_into(current, &mut buffer[read..read + chunk]).unwrap_or(0);
+      read += chunk_read;
+      // the region changed since it was queried
+      if chunk_read < chunk {
+        break;
+      }
+    }
+
+    buffer.truncate(read);
+    Ok((buffer, read))
+  }
+
+  /// Returns whether the whole range lies in committed readable pages
+  /// 
+  /// A cheap check for walking untrusted pointers, each region is queried
+  /// with `VirtualQueryEx` and nothing is read. The memory can still change
+  /// before the read, so the read itself may fail anyway.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let pointer = process.try_read::<usize>(0x7FF600001000).expect("Couldn't read");
+  /// if process.is_readable(pointer, 8) {
+  ///   let health = process.try_read::<i32>(pointer);
+  /// }
+  /// ```
+  pub fn is_readable(&self, address: usize, len: usize) -> bool {
+    self.range_is(address, len, MemoryRegion::is_readable)
+  }
+
+  /// Returns whether the whole range lies in committed writable pages, see
+  /// [`Process::is_readable`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// if process.is_writable(0x7FF600001000, 4) {
+  ///   process.write(100i32, 0x7FF600001000);
+  /// }
+  /// ```
+  pub fn is_writable(&self, address: usize, len: usize) -> bool {
+    self.range_is(address, len, MemoryRegion::is_writable)
+  }
+
+  fn range_is(&self, address: usize, len: usize, check: fn(&MemoryRegion) -> bool) -> bool {
+    let Some(end) = address.checked_add(len) else {
+      return false;
+    };
+
+    let mut current = address;
+    loop {
+      let region = match self.query(current) {
+        Ok(region) => region,
+        Err(_) => return false
+      };
+      if !region.is_committed() || !check(&region) {
+        return false;
+      }
+
+      current = region.address + region.size;
+      if current >= end {
+        return true;
+      }
+    }
+  }
+}
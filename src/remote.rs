@@ -0,0 +1,344 @@
+use std::cell::RefCell;
+use std::io;
+use std::time::Duration;
+
+use winapi::um::processthreadsapi::FlushInstructionCache;
+use winapi::um::winnt::PAGE_EXECUTE_READ;
+use winapi::um::winnt::PAGE_READWRITE;
+
+use crate::Process;
+
+/// Options of [`Process::execute_shellcode_with`]
+#[derive(Clone, Copy, Debug)]
+pub struct ShellcodeOptions {
+  /// How long to wait for the thread, `None` waits forever
+  pub timeout: Option<Duration>,
+  /// Keep the code allocated after the thread exits, for resident payloads
+  pub keep_alive: bool,
+  /// Pointer-sized argument the thread start receives
+  pub argument: usize
+}
+
+impl Default for ShellcodeOptions {
+  fn default() -> Self {
+    Self { timeout: Some(Duration::from_secs(5)), keep_alive: false, argument: 0 }
+  }
+}
+
+/// Argument of [`Process::call_remote`]
+#[derive(Clone, Copy, Debug)]
+pub enum RemoteArg<'a> {
+  /// Integer passed by value, must fit 32 bits for x86 targets
+  Int(u64),
+  /// Address inside the target passed as is
+  Ptr(usize),
+  /// Bytes copied into the target, the function receives their address
+  Buffer(&'a [u8]),
+  /// Same as [`RemoteArg::Buffer`], but the bytes are read back after the call
+  Out(&'a RefCell<Vec<u8>>)
+}
+
+/// Calling convention of the function called by [`Process::call_remote`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallingConvention {
+  /// Microsoft x64 convention, the only one of x64 targets
+  Win64,
+  /// x86, caller cleans the stack
+  Cdecl,
+  /// x86, callee cleans the stack
+  Stdcall,
+  /// x86, first argument (`this`) in `ecx`, callee cleans the stack
+  Thiscall
+}
+
+/// Alignment of buffers inside the argument block
+const BUFFER_ALIGNMENT: usize = 16;
+
+/// Calls `function` with the values loaded into registers and stack, then
+/// stores `rax` at `result`
+fn win64_stub(function: usize, values: &[u64], result: usize) -> Vec<u8> {
+  let stack_args = values.len().saturating_sub(4);
+  // shadow space plus stack arguments, keeps rsp 16-byte aligned after push rbx
+  let frame = ((32 + stack_args * 8 + 15) & !15) as u32;
+
+  let mut code = Vec::new();
+  // push rbx; sub rsp, frame
+  code.push(0x53);
+  code.extend([0x48, 0x81, 0xEC]);
+  code.extend(frame.to_le_bytes());
+
+  for (index, value) in values.iter().enumerate().skip(4) {
+    // mov rax, value; mov [rsp + offset], rax
+    code.extend([0x48, 0xB8]);
+    code.extend(value.to_le_bytes());
+    code.extend([0x48, 0x89, 0x84, 0x24]);
+    code.extend((32 + (index as u32 - 4) * 8).to_le_bytes());
+  }
+
+  // mov rcx / rdx / r8 / r9, value
+  let registers: [[u8; 2]; 4] = [[0x48, 0xB9], [0x48, 0xBA], [0x49, 0xB8], [0x49, 0xB9]];
+  for (register, value) in registers.iter().zip(values) {
+    code.extend(register);
+    code.extend(value.to_le_bytes());
+  }
+
+  // mov rax, function; call rax
+  code.extend([0x48, 0xB8]);
+  code.extend((function as u64).to_le_bytes());
+  code.extend([0xFF, 0xD0]);
+  // mov rbx, result; mov [rbx], rax
+  code.extend([0x48, 0xBB]);
+  code.extend((result as u64).to_le_bytes());
+  code.extend([0x48, 0x89, 0x03]);
+  // add rsp, frame; pop rbx; ret
+  code.extend([0x48, 0x81, 0xC4]);
+  code.extend(frame.to_le_bytes());
+  code.extend([0x5B, 0xC3]);
+
+  code
+}
+
+/// Calls `function` with the values pushed (and `this` in `ecx` for thiscall),
+/// then stores `edx:eax` at `result`
+fn x86_stub(function: u32, values: &[u32], convention: CallingConvention, result: u32) -> Vec<u8> {
+  let (this, pushed) = match convention {
+    CallingConvention::Thiscall => (values.first().copied(), &values[1..]),
+    _ => (None, values)
+  };
+
+  let mut code = Vec::new();
+  // push ebp; mov ebp, esp
+  code.extend([0x55, 0x89, 0xE5]);
+  for value in pushed.iter().rev() {
+    // push value
+    code.push(0x68);
+    code.extend(value.to_le_bytes());
+  }
+  if let Some(this) = this {
+    // mov ecx, this
+    code.push(0xB9);
+    code.extend(this.to_le_bytes());
+  }
+
+  // mov eax, function; call eax
+  code.push(0xB8);
+  code.extend(function.to_le_bytes());
+  code.extend([0xFF, 0xD0]);
+  // mov ecx, result; mov [ecx], eax; mov [ecx + 4], edx
+  code.push(0xB9);
+  code.extend(result.to_le_bytes());
+  code.extend([0x89, 0x01, 0x89, 0x51, 0x04]);
+  // mov esp, ebp restores the stack whoever cleaned it; pop ebp; ret 4
+  code.extend([0x89, 0xEC, 0x5D, 0xC2, 0x04, 0x00]);
+
+  code
+}
+
+fn buffer_len(arg: &RemoteArg) -> usize {
+  let len = match arg {
+    RemoteArg::Buffer(bytes) => bytes.len(),
+    RemoteArg::Out(bytes) => bytes.borrow().len(),
+    _ => return 0
+  };
+  (len.max(1) + BUFFER_ALIGNMENT - 1) & !(BUFFER_ALIGNMENT - 1)
+}
+
+impl Process {
+  /// Calls function in the target on a new remote thread, waits for it and
+  /// returns the thread exit code
+  /// 
+  /// Only the `LPTHREAD_START_ROUTINE` convention is supported: the function
+  /// receives exactly one pointer-sized argument, and only the low 32 bits of
+  /// its return value are observable, see [`Process::call_remote`] for others
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let code = process.call_routine(0x7FF600001000, 0).expect("Couldn't call");
+  /// ```
+  pub fn call_routine(&self, func: usize, arg: usize) -> io::Result<u32> {
+    self.call_routine_timeout(func, arg, None)
+  }
+
+  /// Same as [`Process::call_routine`], but gives up waiting after `timeout`
+  /// with an [`io::ErrorKind::TimedOut`] error, the thread keeps running then
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let code = process.call_routine_timeout(0x7FF600001000, 0, Some(Duration::from_secs(5))).expect("Couldn't call");
+  /// ```
+  pub fn call_routine_timeout(&self, func: usize, arg: usize, timeout: Option<Duration>) -> io::Result<u32> {
+    self.create_thread(func, arg)?
+      .join(timeout)
+      .map_err(|error| match error.kind() {
+        io::ErrorKind::TimedOut => io::Error::new(
+          io::ErrorKind::TimedOut,
+          format!("remote call at 0x{:X} didn't finish in time", func)
+        ),
+        _ => error
+      })
+  }
+
+  /// Calls function in the target with any number of arguments and returns
+  /// its full 64-bit result (`rax`, or `edx:eax` for x86 targets)
+  /// 
+  /// A small stub loading the arguments is executed on a remote thread, which
+  /// is waited for without a timeout. [`RemoteArg::Buffer`] and [`RemoteArg::Out`]
+  /// contents are copied into memory allocated for the call, so they are only
+  /// valid until it returns. x64 targets take [`CallingConvention::Win64`] only,
+  /// x86 ones every other convention, and x64 targets can't be called from an
+  /// x86 process.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::cell::RefCell;
+  /// use cural::{CallingConvention, Process, RemoteArg};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let name = RefCell::new(vec![0u8; 64]);
+  /// let args = [RemoteArg::Ptr(0x1F0000), RemoteArg::Int(42), RemoteArg::Out(&name)];
+  /// let result = process.call_remote(0x7FF600001000, &args, CallingConvention::Win64).expect("Couldn't call");
+  /// println!("returned {} and wrote {:?}", result, name.borrow());
+  /// ```
+  pub fn call_remote(&self, address: usize, args: &[RemoteArg], conv: CallingConvention) -> io::Result<u64> {
+    let x64 = self.is_x64()?;
+    if x64 && cfg!(target_pointer_width = "32") {
+      return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("can't call into x64 {}({}) from an x86 process", self.name, self.id)
+      ));
+    }
+    if x64 != (conv == CallingConvention::Win64) {
+      return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{:?} isn't available for {} targets", conv, if x64 { "x64" } else { "x86" })
+      ));
+    }
+    if conv == CallingConvention::Thiscall && args.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "thiscall needs this as the first argument"));
+    }
+
+    // result slot first, buffers after it
+    let size = BUFFER_ALIGNMENT + args.iter().map(buffer_len).sum::<usize>();
+    let data = self.alloc(size, PAGE_READWRITE)?;
+
+    let result = self.call_with_data(address, args, conv, x64, data);
+    // the call's own error matters more than a failed cleanup
+    let freed = self.free(data);
+    result.and_then(|value| freed.map(|_| value))
+  }
+
+  fn call_with_data(&self, address: usize, args: &[RemoteArg], conv: CallingConvention, x64: bool, data: usize) -> io::Result<u64> {
+    let mut values = Vec::with_capacity(args.len());
+    let mut offset = BUFFER_ALIGNMENT;
+    for arg in args {
+      let value = match arg {
+        RemoteArg::Int(value) => *value,
+        RemoteArg::Ptr(address) => *address as u64,
+        RemoteArg::Buffer(bytes) => {
+          self.write_all(data + offset, bytes)?;
+          (data + offset) as u64
+        },
+        RemoteArg::Out(bytes) => {
+          self.write_all(data + offset, &bytes.borrow())?;
+          (data + offset) as u64
+        }
+      };
+      offset += buffer_len(arg);
+      values.push(value);
+    }
+
+    let code = if x64 {
+      win64_stub(address, &values, data)
+    } else {
+      let values = values.iter()
+        .map(|value| u32::try_from(*value))
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "argument doesn't fit 32 bits of an x86 target"))?;
+      let address = u32::try_from(address)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "function address doesn't fit 32 bits of an x86 target"))?;
+      x86_stub(address, &values, conv, data as u32)
+    };
+
+    self.execute_shellcode_with(&code, ShellcodeOptions { timeout: None, ..Default::default() })?;
+
+    let mut offset = BUFFER_ALIGNMENT;
+    for arg in args {
+      if let RemoteArg::Out(bytes) = arg {
+        let mut bytes = bytes.borrow_mut();
+        let len = bytes.len();
+        *bytes = self.read_bytes(data + offset, len)?;
+      }
+      offset += buffer_len(arg);
+    }
+
+    self.try_read::<u64>(data)
+  }
+
+  /// Runs position-independent code on a remote thread and returns the thread exit code,
+  /// see [`Process::execute_shellcode_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// // mov eax, 1; ret
+  /// let code = process.execute_shellcode(&[0xB8, 0x01, 0x00, 0x00, 0x00, 0xC3], Duration::from_secs(1))
+  ///   .expect("Couldn't execute");
+  /// ```
+  pub fn execute_shellcode(&self, code: &[u8], timeout: Duration) -> io::Result<u32> {
+    self.execute_shellcode_with(code, ShellcodeOptions { timeout: Some(timeout), ..Default::default() })
+  }
+
+  /// Runs position-independent code on a remote thread and returns the thread exit code
+  /// 
+  /// The code is written into fresh `PAGE_EXECUTE_READ` memory, which is freed
+  /// after the thread exits unless `keep_alive` is set. When the wait times out
+  /// the thread may still be executing it, so the memory is leaked and an
+  /// [`io::ErrorKind::TimedOut`] error is returned.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{Process, ShellcodeOptions};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// // mov rax, rcx; ret
+  /// let options = ShellcodeOptions { argument: 7, ..Default::default() };
+  /// let code = process.execute_shellcode_with(&[0x48, 0x89, 0xC8, 0xC3], options).expect("Couldn't execute");
+  /// ```
+  pub fn execute_shellcode_with(&self, code: &[u8], options: ShellcodeOptions) -> io::Result<u32> {
+    let address = self.alloc(code.len(), PAGE_READWRITE)?;
+
+    let prepared = self.write_all(address, code)
+      .and_then(|_| self.protect(address, code.len(), PAGE_EXECUTE_READ))
+      .and_then(|_| {
+        if unsafe { FlushInstructionCache(self.handle.get(), address as *const _, code.len()) } == 0 {
+          return Err(io::Error::last_os_error());
+        }
+        Ok(())
+      });
+    if let Err(error) = prepared {
+      let _ = self.free(address);
+      return Err(error);
+    }
+
+    let result = self.call_routine_timeout(address, options.argument, options.timeout);
+    if let Err(error) = &result {
+      if error.kind() == io::ErrorKind::TimedOut {
+        return Err(io::Error::new(
+          io::ErrorKind::TimedOut,
+          format!("shellcode at 0x{:X} didn't finish in time, its memory is leaked", address)
+        ));
+      }
+    }
+
+    if !options.keep_alive {
+      self.free(address)?;
+    }
+
+    result
+  }
+}
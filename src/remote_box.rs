@@ -0,0 +1,270 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+
+use winapi::um::winnt::PAGE_READWRITE;
+
+use crate::Pod;
+use crate::Process;
+
+fn bytes_of<T: Pod>(values: &[T]) -> &[u8] {
+  unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values)) }
+}
+
+/// Single [`Pod`] value in memory allocated inside the target, freed on drop
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// let process = Process::find("process.exe").expect("no such process");
+/// let value = process.alloc_value(&100i32).expect("Couldn't allocate");
+/// value.write(&200).expect("Couldn't write memory");
+/// println!("0x{:X} holds {}", value.addr(), value.read().expect("Couldn't read memory"));
+/// ```
+pub struct RemoteBox<'p, T: Pod> {
+  process: &'p Process,
+  address: usize,
+  marker: PhantomData<T>
+}
+
+impl<'p, T: Pod> RemoteBox<'p, T> {
+  /// Returns address of the value inside the target
+  pub fn addr(&self) -> usize {
+    self.address
+  }
+
+  /// Reads the value back from the target
+  pub fn read(&self) -> io::Result<T> {
+    self.process.try_read(self.address)
+  }
+
+  /// Overwrites the value inside the target
+  pub fn write(&self, value: &T) -> io::Result<()> {
+    self.process.try_write(*value, self.address)
+  }
+
+  /// Keeps the allocation alive past the box and returns its address
+  pub fn leak(self) -> usize {
+    let address = self.address;
+    mem::forget(self);
+    address
+  }
+
+  /// Returns process the value is allocated in
+  pub fn get_process(&self) -> &'p Process {
+    self.process
+  }
+}
+
+impl<T: Pod> Drop for RemoteBox<'_, T> {
+  fn drop(&mut self) {
+    let _ = self.process.free(self.address);
+  }
+}
+
+impl<T: Pod> Display for RemoteBox<'_, T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} @ 0x{:X}", std::any::type_name::<T>(), self.address)
+  }
+}
+
+impl<T: Pod> Debug for RemoteBox<'_, T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Fixed-length array of [`Pod`] values allocated inside the target, freed on drop
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// let process = Process::find("process.exe").expect("no such process");
+/// let array = process.alloc_array(&[1u32, 2, 3]).expect("Couldn't allocate");
+/// array.write_at(1, &20).expect("Couldn't write memory");
+/// assert_eq!(array.read_all().expect("Couldn't read memory"), vec![1, 20, 3]);
+/// ```
+pub struct RemoteArray<'p, T: Pod> {
+  process: &'p Process,
+  address: usize,
+  len: usize,
+  marker: PhantomData<T>
+}
+
+impl<'p, T: Pod> RemoteArray<'p, T> {
+  /// Returns address of the first element inside the target
+  pub fn addr(&self) -> usize {
+    self.address
+  }
+
+  /// Returns number of elements
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns is the array empty
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Reads element at `index`, fails with [`io::ErrorKind::InvalidInput`] when out of bounds
+  pub fn read_at(&self, index: usize) -> io::Result<T> {
+    self.process.try_read(self.element(index)?)
+  }
+
+  /// Overwrites element at `index`, fails with [`io::ErrorKind::InvalidInput`] when out of bounds
+  pub fn write_at(&self, index: usize, value: &T) -> io::Result<()> {
+    self.process.try_write(*value, self.element(index)?)
+  }
+
+  /// Reads every element back from the target
+  pub fn read_all(&self) -> io::Result<Vec<T>> {
+    let bytes = self.process.read_bytes(self.address, self.len * mem::size_of::<T>())?;
+
+    Ok(
+      (0..self.len)
+        .map(|index| unsafe { ptr::read_unaligned(bytes.as_ptr().add(index * mem::size_of::<T>()) as *const T) })
+        .collect()
+    )
+  }
+
+  /// Overwrites every element, `values` must have the array's length
+  pub fn write_all(&self, values: &[T]) -> io::Result<()> {
+    if values.len() != self.len {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("got {} values for an array of {}", values.len(), self.len)
+      ));
+    }
+
+    self.process.write_all(self.address, bytes_of(values))
+  }
+
+  /// Keeps the allocation alive past the array and returns its address
+  pub fn leak(self) -> usize {
+    let address = self.address;
+    mem::forget(self);
+    address
+  }
+
+  /// Returns process the array is allocated in
+  pub fn get_process(&self) -> &'p Process {
+    self.process
+  }
+
+  fn element(&self, index: usize) -> io::Result<usize> {
+    if index >= self.len {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("index {} is out of bounds for an array of {}", index, self.len)
+      ));
+    }
+
+    Ok(self.address + index * mem::size_of::<T>())
+  }
+}
+
+impl<T: Pod> Drop for RemoteArray<'_, T> {
+  fn drop(&mut self) {
+    let _ = self.process.free(self.address);
+  }
+}
+
+impl<T: Pod> Display for RemoteArray<'_, T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "[{}; {}] @ 0x{:X}", std::any::type_name::<T>(), self.len, self.address)
+  }
+}
+
+impl<T: Pod> Debug for RemoteArray<'_, T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Allocates `PAGE_READWRITE` memory sized for `T` and writes `value` into it
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let value = process.alloc_value(&0u64).expect("Couldn't allocate");
+  /// let copy = value.read().expect("Couldn't read memory");
+  /// ```
+  pub fn alloc_value<T: Pod>(&self, value: &T) -> io::Result<RemoteBox<'_, T>> {
+    let address = self.alloc(mem::size_of::<T>().max(1), PAGE_READWRITE)?;
+    let boxed = RemoteBox { process: self, address, marker: PhantomData };
+    boxed.write(value)?;
+
+    Ok(boxed)
+  }
+
+  /// Allocates `PAGE_READWRITE` memory sized for `values` and writes them into it
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let array = process.alloc_array(&[0u8; 256]).expect("Couldn't allocate");
+  /// let first = array.read_at(0).expect("Couldn't read memory");
+  /// ```
+  pub fn alloc_array<T: Pod>(&self, values: &[T]) -> io::Result<RemoteArray<'_, T>> {
+    let address = self.alloc(mem::size_of_val(values).max(1), PAGE_READWRITE)?;
+    let array = RemoteArray { process: self, address, len: values.len(), marker: PhantomData };
+    array.process.write_all(address, bytes_of(values))?;
+
+    Ok(array)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io;
+
+  use crate::Process;
+
+  fn current() -> Process {
+    Process::open(std::process::id()).expect("Couldn't open current process")
+  }
+
+  #[test]
+  fn box_round_trip() {
+    let process = current();
+    let value = process.alloc_value(&0x1122334455667788u64).expect("Couldn't allocate");
+    assert_eq!(value.read().expect("Couldn't read memory"), 0x1122334455667788);
+    // the current process sees its own allocation directly
+    assert_eq!(unsafe { *(value.addr() as *const u64) }, 0x1122334455667788);
+
+    value.write(&42).expect("Couldn't write memory");
+    assert_eq!(value.read().expect("Couldn't read memory"), 42);
+  }
+
+  #[test]
+  fn array_round_trip() {
+    let process = current();
+    let array = process.alloc_array(&[1u32, 2, 3]).expect("Couldn't allocate");
+    assert_eq!(array.len(), 3);
+
+    array.write_at(1, &20).expect("Couldn't write memory");
+    assert_eq!(array.read_at(1).expect("Couldn't read memory"), 20);
+    assert_eq!(array.read_all().expect("Couldn't read memory"), vec![1, 20, 3]);
+
+    array.write_all(&[7, 8, 9]).expect("Couldn't write memory");
+    assert_eq!(array.read_all().expect("Couldn't read memory"), vec![7, 8, 9]);
+  }
+
+  #[test]
+  fn array_out_of_bounds() {
+    let process = current();
+    let array = process.alloc_array(&[0u16; 4]).expect("Couldn't allocate");
+
+    assert_eq!(array.write_at(4, &1).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(array.read_at(4).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(array.write_all(&[1, 2]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(array.read_all().expect("Couldn't read memory"), vec![0; 4]);
+  }
+}
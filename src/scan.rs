@@ -0,0 +1,538 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::ptr;
+
+use crate::BoundModule;
+use crate::MemoryRegion;
+use crate::Module;
+use crate::Pod;
+use crate::Process;
+
+/// Reach of a rel32 jump or call
+const REL32_REACH: usize = i32::MAX as usize;
+/// Largest span of neighbouring candidates a [`Scan`] re-reads with one call
+const RESCAN_SPAN: usize = 0x10000;
+
+/// Run of padding bytes inside an executable section
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeCave {
+  pub(crate) address: usize,
+  pub(crate) len: usize,
+  pub(crate) filler: u8
+}
+
+impl CodeCave {
+  /// Returns absolute address of the first padding byte
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns number of padding bytes
+  pub fn get_len(&self) -> &usize {
+    &self.len
+  }
+
+  /// Returns padding byte, `0x00` or `0xCC`
+  pub fn get_filler(&self) -> &u8 {
+    &self.filler
+  }
+}
+
+/// Options of typed value scans
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScanOptions {
+  /// Only check addresses which are multiples of this, `0` means `align_of::<T>()`
+  pub alignment: usize
+}
+
+/// What a [`Scan`] holds between passes
+enum ScanState<T> {
+  /// Snapshots of every scanned region, taken by the first scan
+  Regions(Vec<(usize, Vec<u8>)>),
+  /// Remaining addresses with their last read values, sorted by address
+  Candidates(Vec<(usize, T)>)
+}
+
+/// Scan session narrowing down addresses of a value over several passes,
+/// see [`Process::new_scan`]
+/// 
+/// The first scan snapshots readable committed memory, as much memory as the
+/// target has committed. Every `next_*` pass re-reads the remaining addresses
+/// and keeps those whose new value matches, comparing against the value read
+/// by the previous pass. Addresses which became unreadable are dropped.
+pub struct Scan<T: Pod> {
+  process: Process,
+  alignment: usize,
+  state: ScanState<T>
+}
+
+impl<T: Pod> Scan<T> {
+  /// Returns number of remaining candidates
+  pub fn count(&self) -> usize {
+    match &self.state {
+      ScanState::Regions(regions) => regions.iter()
+        .map(|(address, bytes)| aligned_values::<T>(*address, bytes, self.alignment).count())
+        .sum(),
+      ScanState::Candidates(candidates) => candidates.len()
+    }
+  }
+
+  /// Returns remaining candidates with the values read by the last pass
+  /// 
+  /// Before the first `next_*` pass that's every aligned address of the snapshot
+  pub fn get_candidates(&self) -> Vec<(usize, T)> {
+    match &self.state {
+      ScanState::Regions(regions) => regions.iter()
+        .flat_map(|(address, bytes)| aligned_values::<T>(*address, bytes, self.alignment))
+        .collect(),
+      ScanState::Candidates(candidates) => candidates.clone()
+    }
+  }
+
+  /// Re-reads candidates and keeps those for which `matches(previous, current)`
+  /// returns true, returns the narrowed count
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut scan = process.new_scan::<i32>().expect("Couldn't scan");
+  /// // health went down, but by no more than 10
+  /// let left = scan.next_matching(|previous, current| current < previous && previous - current <= 10);
+  /// ```
+  pub fn next_matching<F: Fn(&T, &T) -> bool>(&mut self, matches: F) -> usize {
+    let candidates = match &self.state {
+      ScanState::Regions(regions) => {
+        let mut candidates = Vec::new();
+        for (address, previous) in regions {
+          let Ok(current) = self.process.read_bytes(*address, previous.len()) else {
+            continue;
+          };
+
+          candidates.extend(aligned_values::<T>(*address, previous, self.alignment)
+            .zip(aligned_values::<T>(*address, &current, self.alignment))
+            .filter(|((_, previous), (_, current))| matches(previous, current))
+            .map(|(_, current)| current));
+        }
+        candidates
+      },
+      ScanState::Candidates(candidates) => self.reread(candidates)
+        .into_iter()
+        .filter(|(_, previous, current)| matches(previous, current))
+        .map(|(address, _, current)| (address, current))
+        .collect()
+    };
+
+    self.state = ScanState::Candidates(candidates);
+    self.count()
+  }
+
+  /// Keeps candidates which now hold `value`
+  pub fn next_exact(&mut self, value: T) -> usize where T: PartialEq {
+    self.next_matching(|_, current| current == &value)
+  }
+
+  /// Keeps candidates whose value didn't change since the last pass
+  pub fn next_unchanged(&mut self) -> usize where T: PartialEq {
+    self.next_matching(|previous, current| previous == current)
+  }
+
+  /// Keeps candidates whose value changed since the last pass
+  pub fn next_changed(&mut self) -> usize where T: PartialEq {
+    self.next_matching(|previous, current| previous != current)
+  }
+
+  /// Keeps candidates whose value increased since the last pass
+  pub fn next_increased(&mut self) -> usize where T: PartialOrd {
+    self.next_matching(|previous, current| current > previous)
+  }
+
+  /// Keeps candidates whose value decreased since the last pass
+  pub fn next_decreased(&mut self) -> usize where T: PartialOrd {
+    self.next_matching(|previous, current| current < previous)
+  }
+
+  /// Returns process the scan reads from
+  pub fn get_process(&self) -> &Process {
+    &self.process
+  }
+
+  /// Reads current values as (address, previous, current), neighbouring
+  /// candidates are read together
+  fn reread(&self, candidates: &[(usize, T)]) -> Vec<(usize, T, T)> {
+    let size = mem::size_of::<T>();
+
+    let mut result = Vec::with_capacity(candidates.len());
+    let mut start = 0;
+    while start < candidates.len() {
+      let base = candidates[start].0;
+      let end = start + candidates[start..].iter()
+        .take_while(|(address, _)| address + size - base <= RESCAN_SPAN)
+        .count()
+        .max(1);
+
+      let mut bytes = vec![0u8; candidates[end - 1].0 + size - base];
+      let read = self.process.read_into(base, &mut bytes).unwrap_or(0);
+      for (address, previous) in &candidates[start..end] {
+        let offset = address - base;
+        let current = match offset + size <= read {
+          true => unsafe { ptr::read_unaligned(bytes[offset..].as_ptr() as *const T) },
+          // the span ran into an unreadable page, it may end after this one
+          false => match self.process.try_read::<T>(*address) {
+            Ok(current) => current,
+            Err(_) => continue
+          }
+        };
+        result.push((*address, *previous, current));
+      }
+
+      start = end;
+    }
+
+    result
+  }
+}
+
+impl<T: Pod> Display for Scan<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "scan of {} with {} candidates", self.process, self.count())
+  }
+}
+
+impl<T: Pod> Debug for Scan<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Returns aligned values of a region read into `bytes` as (address, value)
+fn aligned_values<T: Pod>(address: usize, bytes: &[u8], alignment: usize) -> impl Iterator<Item = (usize, T)> + '_ {
+  // first aligned offset of the region
+  let first = (alignment - address % alignment) % alignment;
+
+  (first..(bytes.len() + 1).saturating_sub(mem::size_of::<T>()))
+    .step_by(alignment)
+    .map(move |offset| (address + offset, unsafe { ptr::read_unaligned(bytes[offset..].as_ptr() as *const T) }))
+}
+
+/// Returns alignment of typed scans, `0` meaning `align_of::<T>()`
+fn scan_alignment<T>(options: ScanOptions) -> usize {
+  match options.alignment {
+    0 => mem::align_of::<T>(),
+    alignment => alignment
+  }
+}
+
+/// Returns runs of the same padding byte at least `min_size` long as (offset, len, filler)
+fn padding_runs(bytes: &[u8], min_size: usize) -> Vec<(usize, usize, u8)> {
+  let mut runs = Vec::new();
+
+  let mut start = 0;
+  while start < bytes.len() {
+    let filler = bytes[start];
+    let len = bytes[start..].iter().take_while(|byte| byte == &&filler).count();
+
+    if (filler == 0x00 || filler == 0xCC) && len >= min_size.max(1) {
+      runs.push((start, len, filler));
+    }
+    start += len;
+  }
+
+  runs
+}
+
+/// Parses IDA-style pattern (`"48 8B ?? ?? 05"`), `?` and `??` are wildcards
+pub(crate) fn parse_pattern(pattern: &str) -> io::Result<Vec<Option<u8>>> {
+  let bytes = pattern.split_whitespace()
+    .map(|byte| match byte {
+      "?" | "??" => Ok(None),
+      _ => u8::from_str_radix(byte, 16).map(Some).map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid pattern byte {}", byte)
+      ))
+    })
+    .collect::<io::Result<Vec<_>>>()?;
+
+  if bytes.is_empty() {
+    return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty pattern"));
+  }
+
+  Ok(bytes)
+}
+
+/// Returns offsets of every match of `pattern` in `haystack`
+pub(crate) fn find_pattern(haystack: &[u8], pattern: &[Option<u8>]) -> Vec<usize> {
+  if pattern.len() > haystack.len() {
+    return Vec::new();
+  }
+
+  haystack.windows(pattern.len())
+    .enumerate()
+    .filter(|(_, window)| window.iter()
+      .zip(pattern)
+      .all(|(byte, expected)| expected.is_none_or(|expected| expected == *byte)))
+    .map(|(offset, _)| offset)
+    .collect()
+}
+
+impl Process {
+  /// Scans module for IDA-style pattern (`"48 8B ?? ?? 05"`)
+  /// 
+  /// Only the named section is scanned when `section` is given, otherwise
+  /// every section of the module is, skipping unreadable ones like discarded
+  /// sections. A named section which can't be read fails the call. Returns
+  /// absolute addresses of the matches.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let matches = process.scan_module(&main, "48 8B 05 ?? ?? ?? ??", Some(".text")).expect("Couldn't scan");
+  /// ```
+  pub fn scan_module(&self, module: &Module, pattern: &str, section: Option<&str>) -> io::Result<Vec<usize>> {
+    let pattern = parse_pattern(pattern)?;
+    let bound = module.bind(self);
+
+    let (sections, skip_unreadable) = match section {
+      Some(name) => (vec![bound.section(name)?], false),
+      None => (bound.sections()?, true)
+    };
+
+    let mut result = Vec::new();
+    for section in sections {
+      let bytes = match self.read_bytes(section.address, section.virtual_size) {
+        Ok(bytes) => bytes,
+        Err(_) if skip_unreadable => continue,
+        Err(error) => return Err(error)
+      };
+      result.extend(find_pattern(&bytes, &pattern)
+        .into_iter()
+        .map(|offset| section.address + offset));
+    }
+
+    Ok(result)
+  }
+
+  /// Resolves target of a rip-relative instruction at `address`, usually a scan match
+  /// 
+  /// Reads the signed 32-bit displacement at `address + disp_offset` and adds
+  /// it to the address of the next instruction, `address + instr_len`
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// // mov rax, [rip + disp32]
+  /// let found = process.scan_module(&main, "48 8B 05 ?? ?? ?? ??", Some(".text")).expect("Couldn't scan");
+  /// let global = process.resolve_rip(found[0], 3, 7).expect("Couldn't read memory");
+  /// ```
+  pub fn resolve_rip(&self, address: usize, disp_offset: usize, instr_len: usize) -> io::Result<usize> {
+    let displacement = self.try_read::<i32>(address + disp_offset)?;
+
+    Ok((address + instr_len).wrapping_add_signed(displacement as isize))
+  }
+
+  /// Scans readable committed memory for a value, see [`Process::scan_value_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let addresses = process.scan_value(100i32).expect("Couldn't scan");
+  /// ```
+  pub fn scan_value<T: Pod + PartialEq>(&self, value: T) -> io::Result<Vec<usize>> {
+    self.scan_value_with(value, ScanOptions::default())
+  }
+
+  /// Scans readable committed memory for a value and returns addresses where it's found
+  /// 
+  /// Regions which become unreadable during the scan are skipped
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{Process, ScanOptions};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let addresses = process.scan_value_with(100i32, ScanOptions { alignment: 4 }).expect("Couldn't scan");
+  /// ```
+  pub fn scan_value_with<T: Pod + PartialEq>(&self, value: T, options: ScanOptions) -> io::Result<Vec<usize>> {
+    self.scan_values(options, |found: &T| found == &value)
+  }
+
+  /// Scans readable committed memory for values within `[min, max]`, see [`Process::scan_range_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let addresses = process.scan_range(0.0f32, 100.0f32).expect("Couldn't scan");
+  /// ```
+  pub fn scan_range<T: Pod + PartialOrd>(&self, min: T, max: T) -> io::Result<Vec<usize>> {
+    self.scan_range_with(min, max, ScanOptions::default())
+  }
+
+  /// Scans readable committed memory for values within `[min, max]` and returns their addresses
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{Process, ScanOptions};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let addresses = process.scan_range_with(1u32, 100u32, ScanOptions { alignment: 4 }).expect("Couldn't scan");
+  /// ```
+  pub fn scan_range_with<T: Pod + PartialOrd>(&self, min: T, max: T, options: ScanOptions) -> io::Result<Vec<usize>> {
+    self.scan_values(options, |found: &T| &min <= found && found <= &max)
+  }
+
+  /// Scans readable committed memory for a string, UTF-16LE when `wide`, UTF-8 otherwise
+  /// 
+  /// The string is matched exactly, without a terminating nul. Matches never
+  /// span two regions and regions which become unreadable are skipped.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let addresses = process.scan_string("PlayerName", true).expect("Couldn't scan");
+  /// ```
+  pub fn scan_string(&self, needle: &str, wide: bool) -> io::Result<Vec<usize>> {
+    if needle.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty string"));
+    }
+
+    let pattern = match wide {
+      true => needle.encode_utf16().flat_map(u16::to_le_bytes).map(Some).collect::<Vec<_>>(),
+      false => needle.bytes().map(Some).collect::<Vec<_>>()
+    };
+
+    let mut result = Vec::new();
+    for region in self.scannable_regions()? {
+      let bytes = match self.read_bytes(region.address, region.size) {
+        Ok(bytes) => bytes,
+        Err(_) => continue
+      };
+
+      result.extend(find_pattern(&bytes, &pattern)
+        .into_iter()
+        .map(|offset| region.address + offset));
+    }
+
+    Ok(result)
+  }
+
+  /// Returns committed readable regions worth scanning
+  pub(crate) fn scannable_regions(&self) -> io::Result<Vec<MemoryRegion>> {
+    Ok(self.regions()?
+      .into_iter()
+      .filter(|region| region.is_committed() && region.is_readable())
+      .collect())
+  }
+
+  /// Starts a [`Scan`] over readable committed memory, see [`Process::new_scan_with`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut scan = process.new_scan::<i32>().expect("Couldn't scan");
+  /// scan.next_exact(100);
+  /// // take some damage
+  /// scan.next_decreased();
+  /// println!("{:?}", scan.get_candidates());
+  /// ```
+  pub fn new_scan<T: Pod>(&self) -> io::Result<Scan<T>> {
+    self.new_scan_with(ScanOptions::default())
+  }
+
+  /// Starts a [`Scan`], snapshotting readable committed memory for the first pass
+  /// 
+  /// Regions which become unreadable while snapshotting are skipped
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{Process, ScanOptions};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let mut scan = process.new_scan_with::<f32>(ScanOptions { alignment: 4 }).expect("Couldn't scan");
+  /// scan.next_increased();
+  /// ```
+  pub fn new_scan_with<T: Pod>(&self, options: ScanOptions) -> io::Result<Scan<T>> {
+    let regions = self.scannable_regions()?
+      .into_iter()
+      .filter_map(|region| Some((region.address, self.read_bytes(region.address, region.size).ok()?)))
+      .collect();
+
+    Ok(Scan {
+      process: self.clone(),
+      alignment: scan_alignment::<T>(options),
+      state: ScanState::Regions(regions)
+    })
+  }
+
+  fn scan_values<T: Pod, F: Fn(&T) -> bool>(&self, options: ScanOptions, matches: F) -> io::Result<Vec<usize>> {
+    let alignment = scan_alignment::<T>(options);
+
+    let mut result = Vec::new();
+    for region in self.scannable_regions()? {
+      let bytes = match self.read_bytes(region.address, region.size) {
+        Ok(bytes) => bytes,
+        Err(_) => continue
+      };
+
+      result.extend(aligned_values::<T>(region.address, &bytes, alignment)
+        .filter(|(_, value)| matches(value))
+        .map(|(address, _)| address));
+    }
+
+    Ok(result)
+  }
+}
+
+impl BoundModule<'_> {
+  /// Finds runs of `0x00`/`0xCC` padding at least `min_size` long inside
+  /// executable sections, sorted by size descending
+  /// 
+  /// Caves never span section boundaries
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let caves = main.bind(&process).find_code_caves(32).expect("Couldn't scan");
+  /// ```
+  pub fn find_code_caves(&self, min_size: usize) -> io::Result<Vec<CodeCave>> {
+    let mut caves = Vec::new();
+
+    for section in self.sections()?.into_iter().filter(|section| section.is_executable()) {
+      let bytes = self.process.read_bytes(section.address, section.virtual_size)?;
+      caves.extend(padding_runs(&bytes, min_size)
+        .into_iter()
+        .map(|(offset, len, filler)| CodeCave { address: section.address + offset, len, filler }));
+    }
+
+    caves.sort_by_key(|cave| std::cmp::Reverse(cave.len));
+    Ok(caves)
+  }
+
+  /// Same as [`BoundModule::find_code_caves`], but keeps only caves which
+  /// lie entirely within reach of a rel32 jump from `address` (±2 GB)
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let main = process.main_module().expect("no main module");
+  /// let caves = main.bind(&process).find_code_caves_near(32, 0x7FF600001000).expect("Couldn't scan");
+  /// ```
+  pub fn find_code_caves_near(&self, min_size: usize, address: usize) -> io::Result<Vec<CodeCave>> {
+    Ok(self.find_code_caves(min_size)?
+      .into_iter()
+      .filter(|cave| {
+        cave.address.abs_diff(address) <= REL32_REACH
+          && (cave.address + cave.len).abs_diff(address) <= REL32_REACH
+      })
+      .collect())
+  }
+}
@@ -0,0 +1,185 @@
+use std::ffi::OsStr;
+use std::io;
+use std::mem;
+use std::ops::Deref;
+use std::os::windows::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::ptr;
+
+use winapi::um::processthreadsapi::CreateProcessW;
+use winapi::um::processthreadsapi::PROCESS_INFORMATION;
+use winapi::um::processthreadsapi::STARTUPINFOW;
+use winapi::um::winbase::CREATE_SUSPENDED;
+use winapi::um::winbase::CREATE_UNICODE_ENVIRONMENT;
+
+use crate::handle::HandleGuard;
+use crate::Process;
+use crate::RemoteThread;
+
+/// What to start with [`Process::spawn`]
+#[derive(Clone, Debug, Default)]
+pub struct SpawnOptions {
+  /// Path of the executable
+  pub exe: PathBuf,
+  /// Arguments after the executable name, quoted as needed
+  pub args: Vec<String>,
+  /// Working directory, `None` inherits the current one
+  pub current_dir: Option<PathBuf>,
+  /// Complete environment as (name, value) pairs, `None` inherits the current one
+  pub env: Option<Vec<(String, String)>>
+}
+
+/// Process started suspended by [`Process::spawn`], derefs to [`Process`]
+pub struct SpawnedProcess {
+  pub(crate) process: Process,
+  pub(crate) main_thread: RemoteThread
+}
+
+impl SpawnedProcess {
+  /// Returns main thread, suspended until [`SpawnedProcess::resume`]
+  pub fn get_main_thread(&self) -> &RemoteThread {
+    &self.main_thread
+  }
+
+  /// Lets the main thread run, returns its previous suspend count
+  pub fn resume(&self) -> io::Result<u32> {
+    self.main_thread.resume()
+  }
+
+  /// Returns the spawned process, the main thread handle is closed
+  pub fn into_process(self) -> Process {
+    self.process
+  }
+}
+
+impl Deref for SpawnedProcess {
+  type Target = Process;
+
+  fn deref(&self) -> &Self::Target {
+    &self.process
+  }
+}
+
+impl std::fmt::Debug for SpawnedProcess {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.process.to_string())
+  }
+}
+
+fn wide(value: &OsStr) -> Vec<u16> {
+  value.encode_wide().chain(Some(0)).collect()
+}
+
+/// Quotes argument the way `CommandLineToArgvW` splits it back
+fn quote_arg(arg: &str, command_line: &mut String) {
+  if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+    command_line.push_str(arg);
+    return;
+  }
+
+  command_line.push('"');
+  let mut backslashes = 0;
+  for char in arg.chars() {
+    match char {
+      '\\' => backslashes += 1,
+      '"' => {
+        // backslashes before a quote are doubled and the quote is escaped
+        command_line.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+        command_line.push(char);
+        backslashes = 0;
+      },
+      _ => {
+        command_line.extend(std::iter::repeat_n('\\', backslashes));
+        command_line.push(char);
+        backslashes = 0;
+      }
+    }
+  }
+  // same for the ones before the closing quote
+  command_line.extend(std::iter::repeat_n('\\', backslashes * 2));
+  command_line.push('"');
+}
+
+fn environment_block(env: &[(String, String)]) -> Vec<u16> {
+  let mut block = Vec::new();
+  for (name, value) in env {
+    block.extend(format!("{}={}", name, value).encode_utf16());
+    block.push(0);
+  }
+  if env.is_empty() {
+    block.push(0);
+  }
+  block.push(0);
+  block
+}
+
+impl Process {
+  /// Starts process with `CREATE_SUSPENDED`, so nothing of it runs until
+  /// [`SpawnedProcess::resume`]
+  /// 
+  /// Only ntdll and the executable are mapped at that point. Remote threads
+  /// still work: the first one to run initializes the process before its start
+  /// address is called, so the usual flow is spawn, [`Process::inject_dll`]
+  /// and resume.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::path::Path;
+  /// use cural::{Process, SpawnOptions};
+  /// let options = SpawnOptions {
+  ///   exe: "C:\\Windows\\System32\\notepad.exe".into(),
+  ///   args: vec!["file.txt".to_string()],
+  ///   ..Default::default()
+  /// };
+  /// let spawned = Process::spawn(&options).expect("Couldn't spawn");
+  /// spawned.inject_dll(Path::new("payload.dll")).expect("Couldn't inject");
+  /// spawned.resume().expect("Couldn't resume");
+  /// ```
+  pub fn spawn(command: &SpawnOptions) -> io::Result<SpawnedProcess> {
+    let application = wide(command.exe.as_os_str());
+
+    let mut command_line = String::new();
+    quote_arg(&command.exe.to_string_lossy(), &mut command_line);
+    for arg in &command.args {
+      command_line.push(' ');
+      quote_arg(arg, &mut command_line);
+    }
+    let mut command_line = wide(OsStr::new(&command_line));
+
+    let current_dir = command.current_dir.as_ref().map(|dir| wide(dir.as_os_str()));
+    let mut environment = command.env.as_deref().map(environment_block);
+
+    let mut startup = unsafe { mem::zeroed::<STARTUPINFOW>() };
+    startup.cb = mem::size_of::<STARTUPINFOW>() as u32;
+    let mut information = unsafe { mem::zeroed::<PROCESS_INFORMATION>() };
+
+    if unsafe {
+      CreateProcessW(
+        application.as_ptr(),
+        command_line.as_mut_ptr(),
+        ptr::null_mut(),
+        ptr::null_mut(),
+        0,
+        CREATE_SUSPENDED | CREATE_UNICODE_ENVIRONMENT,
+        environment.as_mut().map_or(ptr::null_mut(), |block| block.as_mut_ptr() as *mut _),
+        current_dir.as_ref().map_or(ptr::null(), |dir| dir.as_ptr()),
+        &mut startup,
+        &mut information
+      )
+    } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    let main_thread = RemoteThread { handle: HandleGuard(information.hThread), id: information.dwThreadId };
+    let process = match Process::from_handle(information.hProcess) {
+      Ok(process) => process,
+      Err(error) => {
+        // from_handle closed the process handle, ending the only thread doesn't leave it suspended forever
+        let _ = main_thread.terminate(1);
+        return Err(error);
+      }
+    };
+
+    Ok(SpawnedProcess { process, main_thread })
+  }
+}
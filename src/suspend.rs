@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io;
+use std::mem;
+use std::sync::Mutex;
+
+use winapi::shared::minwindef::FARPROC;
+use winapi::shared::ntdef::NTSTATUS;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::libloaderapi::GetProcAddress;
+use winapi::um::winnt::HANDLE;
+
+use crate::ntdll;
+use crate::Process;
+use crate::Thread;
+
+type NtProcessCall = unsafe extern "system" fn(HANDLE) -> NTSTATUS;
+
+/// How many times the thread list is re-read by the fallback to catch threads
+/// created while suspending
+const FALLBACK_PASSES: usize = 8;
+
+/// How a process was suspended, so it's resumed the same way
+enum Suspension {
+  Nt,
+  Threads(Vec<Thread>)
+}
+
+/// Suspend counts of processes suspended through guards, keyed by process id
+static SUSPENDED: Mutex<Option<HashMap<u32, (usize, Suspension)>>> = Mutex::new(None);
+
+fn nt_process_call(name: &std::ffi::CStr) -> Option<NtProcessCall> {
+  let ntdll = "ntdll.dll".encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+  let module = unsafe { GetModuleHandleW(ntdll.as_ptr()) };
+  if module.is_null() {
+    return None;
+  }
+
+  let function = unsafe { GetProcAddress(module, name.as_ptr()) };
+  if function.is_null() {
+    return None;
+  }
+
+  Some(unsafe { mem::transmute::<FARPROC, NtProcessCall>(function) })
+}
+
+fn nt_call(process: &Process, function: NtProcessCall) -> io::Result<()> {
+  match unsafe { function(process.handle.get()) } {
+    0 => Ok(()),
+    status => Err(ntdll::status_error(status))
+  }
+}
+
+/// Suspends every thread, re-reading the list until no new thread shows up
+fn suspend_threads(process: &Process) -> io::Result<Vec<Thread>> {
+  let mut suspended: Vec<Thread> = Vec::new();
+  for _ in 0..FALLBACK_PASSES {
+    let mut found = false;
+    for thread in process.threads()? {
+      if suspended.iter().any(|known| known.id == thread.id) {
+        continue;
+      }
+      found = true;
+      // exited threads can't be suspended and don't need to be
+      if thread.suspend().is_ok() {
+        suspended.push(thread);
+      }
+    }
+
+    if !found {
+      break;
+    }
+  }
+
+  Ok(suspended)
+}
+
+fn suspend(process: &Process) -> io::Result<Suspension> {
+  match nt_process_call(c"NtSuspendProcess") {
+    Some(function) => nt_call(process, function).map(|_| Suspension::Nt),
+    None => suspend_threads(process).map(Suspension::Threads)
+  }
+}
+
+fn resume(process: &Process, suspension: &Suspension) -> io::Result<()> {
+  match suspension {
+    Suspension::Nt => match nt_process_call(c"NtResumeProcess") {
+      Some(function) => nt_call(process, function),
+      None => Err(io::Error::new(io::ErrorKind::Unsupported, "NtResumeProcess is missing from ntdll"))
+    },
+    Suspension::Threads(threads) => {
+      for thread in threads {
+        let _ = thread.resume();
+      }
+      Ok(())
+    }
+  }
+}
+
+/// Keeps a process suspended until dropped, see [`Process::suspend`]
+pub struct ProcessSuspendGuard {
+  process: Process
+}
+
+impl ProcessSuspendGuard {
+  /// Returns the suspended process
+  pub fn get_process(&self) -> &Process {
+    &self.process
+  }
+}
+
+impl Drop for ProcessSuspendGuard {
+  fn drop(&mut self) {
+    let mut suspended = SUSPENDED.lock().unwrap_or_else(|error| error.into_inner());
+    let Some(map) = suspended.as_mut() else {
+      return;
+    };
+    let Some((count, _)) = map.get_mut(&self.process.id) else {
+      return;
+    };
+
+    *count -= 1;
+    if *count == 0 {
+      if let Some((_, suspension)) = map.remove(&self.process.id) {
+        let _ = resume(&self.process, &suspension);
+      }
+    }
+  }
+}
+
+impl Debug for ProcessSuspendGuard {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "suspended {:?}", self.process)
+  }
+}
+
+impl Process {
+  /// Suspends every thread of the process until the returned guard is dropped
+  /// 
+  /// Uses `NtSuspendProcess` from ntdll, which also stops threads created
+  /// meanwhile. If it's unavailable the threads are suspended one by one and the
+  /// list is re-read a few times to catch new ones, which can still race thread
+  /// creation. Guards are counted per process id within the current process,
+  /// only the last one dropped resumes.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// {
+  ///   let _guard = process.suspend().expect("Couldn't suspend");
+  ///   let snapshot = process.read_bytes(0x1000, 0x100).expect("Couldn't read memory");
+  /// } // resumed here
+  /// ```
+  pub fn suspend(&self) -> io::Result<ProcessSuspendGuard> {
+    let mut suspended = SUSPENDED.lock().unwrap_or_else(|error| error.into_inner());
+    let map = suspended.get_or_insert_with(HashMap::new);
+
+    match map.get_mut(&self.id) {
+      Some((count, _)) => *count += 1,
+      None => {
+        let suspension = suspend(self)?;
+        map.insert(self.id, (1, suspension));
+      }
+    }
+
+    Ok(ProcessSuspendGuard { process: self.clone() })
+  }
+
+  /// Resumes a process suspended outside of guards, e.g. by a debugger or
+  /// [`Process::spawn`], with `NtResumeProcess`
+  /// 
+  /// Fails with [`io::ErrorKind::ResourceBusy`] while a [`ProcessSuspendGuard`]
+  /// holds the process. Each call undoes one suspension, threads suspended
+  /// individually keep their own counts.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// process.resume().expect("Couldn't resume");
+  /// ```
+  pub fn resume(&self) -> io::Result<()> {
+    let suspended = SUSPENDED.lock().unwrap_or_else(|error| error.into_inner());
+    if suspended.as_ref().is_some_and(|map| map.contains_key(&self.id)) {
+      return Err(io::Error::new(
+        io::ErrorKind::ResourceBusy,
+        format!("{}({}) is held suspended by a guard", self.name, self.id)
+      ));
+    }
+
+    resume(self, &Suspension::Nt)
+  }
+
+  /// Reads several `(address, len)` ranges, suspending the process meanwhile
+  /// when `suspend` is set so values updated together can't tear
+  /// 
+  /// Without `suspend` it's just a series of [`Process::read_bytes`] calls.
+  /// Any failed read fails the whole call, the process is resumed either way.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let position = process.read_coherent(&[(0x1000, 4), (0x1004, 4), (0x1008, 4)], true).expect("Couldn't read memory");
+  /// ```
+  pub fn read_coherent(&self, requests: &[(usize, usize)], suspend: bool) -> io::Result<Vec<Vec<u8>>> {
+    let _guard = match suspend {
+      true => Some(self.suspend()?),
+      false => None
+    };
+
+    requests.iter()
+      .map(|(address, len)| self.read_bytes(*address, *len))
+      .collect()
+  }
+}
@@ -0,0 +1,1083 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use winapi::shared::winerror::ERROR_ACCESS_DENIED;
+use winapi::shared::winerror::ERROR_INVALID_PARAMETER;
+use winapi::shared::winerror::WAIT_TIMEOUT;
+use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::um::minwinbase::LPTHREAD_START_ROUTINE;
+use winapi::um::processthreadsapi::CreateRemoteThread;
+use winapi::shared::minwindef::FILETIME;
+use winapi::um::processthreadsapi::GetExitCodeThread;
+use winapi::um::processthreadsapi::GetThreadTimes;
+use winapi::um::processthreadsapi::OpenThread;
+use winapi::um::processthreadsapi::ResumeThread;
+use winapi::um::processthreadsapi::SuspendThread;
+use winapi::um::processthreadsapi::TerminateThread;
+use winapi::um::synchapi::WaitForSingleObject;
+use winapi::um::tlhelp32::CreateToolhelp32Snapshot;
+use winapi::um::tlhelp32::TH32CS_SNAPTHREAD;
+use winapi::um::tlhelp32::THREADENTRY32;
+use winapi::um::tlhelp32::Thread32First;
+use winapi::um::tlhelp32::Thread32Next;
+use winapi::um::winbase::CREATE_SUSPENDED;
+use winapi::um::winbase::INFINITE;
+use winapi::um::winbase::WAIT_FAILED;
+use winapi::um::winbase::WAIT_OBJECT_0;
+use winapi::um::winnt::HANDLE;
+use winapi::um::winnt::SYNCHRONIZE;
+use winapi::um::winnt::THREAD_QUERY_INFORMATION;
+use winapi::um::winnt::THREAD_QUERY_LIMITED_INFORMATION;
+use winapi::um::winnt::THREAD_SUSPEND_RESUME;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use winapi::um::winnt::THREAD_GET_CONTEXT;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use winapi::um::winnt::THREAD_SET_CONTEXT;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use winapi::um::winnt::CONTEXT;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use winapi::um::winnt::CONTEXT_DEBUG_REGISTERS;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use winapi::um::winnt::CONTEXT_FULL;
+#[cfg(target_arch = "x86_64")]
+use winapi::um::winnt::WOW64_CONTEXT;
+#[cfg(target_arch = "x86_64")]
+use winapi::um::winnt::WOW64_CONTEXT_DEBUG_REGISTERS;
+#[cfg(target_arch = "x86_64")]
+use winapi::um::winnt::WOW64_CONTEXT_FULL;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use winapi::um::processthreadsapi::GetThreadContext;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use winapi::um::processthreadsapi::SetThreadContext;
+#[cfg(target_arch = "x86_64")]
+use winapi::um::winbase::Wow64GetThreadContext;
+#[cfg(target_arch = "x86_64")]
+use winapi::um::winbase::Wow64SetThreadContext;
+#[cfg(target_arch = "x86_64")]
+use winapi::um::winbase::Wow64SuspendThread;
+#[cfg(target_arch = "x86")]
+use winapi::um::processthreadsapi::OpenProcess;
+#[cfg(target_arch = "x86")]
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+use crate::handle::HandleGuard;
+use crate::ntdll;
+#[cfg(target_arch = "x86")]
+use crate::process::query_x64;
+use crate::CuralError;
+use crate::window::is_main_window;
+use crate::window::top_level_windows;
+use crate::window::window_owner;
+use crate::Module;
+use crate::Process;
+
+/// 100ns intervals between 1601-01-01 (`FILETIME` epoch) and 1970-01-01
+const FILETIME_UNIX_OFFSET: u64 = 116_444_736_000_000_000;
+
+/// Converts absolute `FILETIME` into `SystemTime`
+pub(crate) fn filetime_to_system_time(time: &FILETIME) -> SystemTime {
+  let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+  match ticks.checked_sub(FILETIME_UNIX_OFFSET) {
+    Some(ticks) => UNIX_EPOCH + Duration::from_nanos(ticks * 100),
+    None => UNIX_EPOCH - Duration::from_nanos((FILETIME_UNIX_OFFSET - ticks) * 100)
+  }
+}
+
+/// Converts relative `FILETIME` (an amount of 100ns ticks) into `Duration`
+fn filetime_to_duration(time: &FILETIME) -> Duration {
+  let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+  Duration::from_nanos(ticks * 100)
+}
+
+/// `CONTEXT` with the 16-byte alignment `GetThreadContext` requires,
+/// a misaligned one fails with `ERROR_NOACCESS`
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[repr(C, align(16))]
+pub struct AlignedContext(pub CONTEXT);
+
+/// Register access shared by the `CONTEXT` layouts, so every view of a
+/// thread's registers behaves the same
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+trait Registers {
+  fn instruction_pointer(&self) -> usize;
+  fn set_instruction_pointer(&mut self, address: usize);
+  fn stack_pointer(&self) -> usize;
+  fn set_stack_pointer(&mut self, address: usize);
+  #[cfg(target_arch = "x86_64")]
+  fn frame_pointer(&self) -> usize;
+  fn flags(&self) -> u32;
+  fn set_flags(&mut self, flags: u32);
+  fn debug_register(&self, index: usize) -> Option<usize>;
+  fn set_debug_register(&mut self, index: usize, value: usize) -> bool;
+}
+
+/// Implements [`Registers`] over the instruction, stack and frame pointer
+/// fields of a context, `$width` being the type of its registers
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+macro_rules! impl_registers {
+  ($context:ty, $ip:ident, $sp:ident, $bp:ident, $width:ty) => {
+    impl Registers for $context {
+      fn instruction_pointer(&self) -> usize {
+        self.$ip as usize
+      }
+
+      fn set_instruction_pointer(&mut self, address: usize) {
+        self.$ip = address as $width;
+      }
+
+      fn stack_pointer(&self) -> usize {
+        self.$sp as usize
+      }
+
+      fn set_stack_pointer(&mut self, address: usize) {
+        self.$sp = address as $width;
+      }
+
+      #[cfg(target_arch = "x86_64")]
+      fn frame_pointer(&self) -> usize {
+        self.$bp as usize
+      }
+
+      fn flags(&self) -> u32 {
+        self.EFlags
+      }
+
+      fn set_flags(&mut self, flags: u32) {
+        self.EFlags = flags;
+      }
+
+      fn debug_register(&self, index: usize) -> Option<usize> {
+        let value = match index {
+          0 => self.Dr0,
+          1 => self.Dr1,
+          2 => self.Dr2,
+          3 => self.Dr3,
+          6 => self.Dr6,
+          7 => self.Dr7,
+          _ => return None
+        };
+
+        Some(value as usize)
+      }
+
+      fn set_debug_register(&mut self, index: usize, value: usize) -> bool {
+        let register = match index {
+          0 => &mut self.Dr0,
+          1 => &mut self.Dr1,
+          2 => &mut self.Dr2,
+          3 => &mut self.Dr3,
+          6 => &mut self.Dr6,
+          7 => &mut self.Dr7,
+          _ => return false
+        };
+        *register = value as $width;
+
+        true
+      }
+    }
+  };
+}
+
+#[cfg(target_arch = "x86_64")]
+impl_registers!(CONTEXT, Rip, Rsp, Rbp, u64);
+#[cfg(target_arch = "x86_64")]
+impl_registers!(WOW64_CONTEXT, Eip, Esp, Ebp, u32);
+#[cfg(target_arch = "x86")]
+impl_registers!(CONTEXT, Eip, Esp, Ebp, u32);
+
+/// Register state of a thread, x64 `CONTEXT` or `WOW64_CONTEXT` for x86 targets
+/// 
+/// Holds the integer, control, segment, floating point and debug registers,
+/// see [`Thread::context`]. x86 callers only reach x86 threads, so theirs is
+/// always the native x86 `CONTEXT`.
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+pub enum ThreadContext {
+  Native(Box<AlignedContext>),
+  #[cfg(target_arch = "x86_64")]
+  Wow64(Box<WOW64_CONTEXT>)
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+impl ThreadContext {
+  /// Captures context of a suspended thread, `wow64` selects the 32-bit view
+  pub(crate) fn get(thread: HANDLE, wow64: bool) -> io::Result<Self> {
+    #[cfg(target_arch = "x86_64")]
+    if wow64 {
+      let mut context = Box::new(unsafe { mem::zeroed::<WOW64_CONTEXT>() });
+      context.ContextFlags = WOW64_CONTEXT_FULL | WOW64_CONTEXT_DEBUG_REGISTERS;
+      if unsafe { Wow64GetThreadContext(thread, &mut *context) } == 0 {
+        return Err(io::Error::last_os_error());
+      }
+      return Ok(Self::Wow64(context));
+    }
+    // x86 callers can't tell WOW64 threads apart, they never ask for them
+    #[cfg(target_arch = "x86")]
+    let _ = wow64;
+
+    let mut context = Box::new(AlignedContext(unsafe { mem::zeroed::<CONTEXT>() }));
+    context.0.ContextFlags = CONTEXT_FULL | CONTEXT_DEBUG_REGISTERS;
+    if unsafe { GetThreadContext(thread, &mut context.0) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(Self::Native(context))
+  }
+
+  /// Applies context to a suspended thread
+  pub(crate) fn set(&self, thread: HANDLE) -> io::Result<()> {
+    let result = match self {
+      Self::Native(context) => unsafe { SetThreadContext(thread, &context.0) },
+      #[cfg(target_arch = "x86_64")]
+      Self::Wow64(context) => unsafe { Wow64SetThreadContext(thread, &**context) }
+    };
+
+    if result == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+  }
+
+  fn registers(&self) -> &dyn Registers {
+    match self {
+      Self::Native(context) => &context.0,
+      #[cfg(target_arch = "x86_64")]
+      Self::Wow64(context) => &**context
+    }
+  }
+
+  fn registers_mut(&mut self) -> &mut dyn Registers {
+    match self {
+      Self::Native(context) => &mut context.0,
+      #[cfg(target_arch = "x86_64")]
+      Self::Wow64(context) => &mut **context
+    }
+  }
+
+  /// Returns is this the 32-bit view of a WOW64 thread
+  pub fn is_wow64(&self) -> bool {
+    match self {
+      Self::Native(_) => false,
+      #[cfg(target_arch = "x86_64")]
+      Self::Wow64(_) => true
+    }
+  }
+
+  /// Returns `rip` / `eip`
+  pub fn instruction_pointer(&self) -> usize {
+    self.registers().instruction_pointer()
+  }
+
+  /// Sets `rip` / `eip`, an x86 value is truncated to 32 bits
+  pub fn set_instruction_pointer(&mut self, address: usize) {
+    self.registers_mut().set_instruction_pointer(address)
+  }
+
+  /// Returns `rsp` / `esp`
+  pub fn stack_pointer(&self) -> usize {
+    self.registers().stack_pointer()
+  }
+
+  /// Sets `rsp` / `esp`, an x86 value is truncated to 32 bits
+  pub fn set_stack_pointer(&mut self, address: usize) {
+    self.registers_mut().set_stack_pointer(address)
+  }
+
+  /// Returns `rflags` / `eflags`
+  pub fn flags(&self) -> u32 {
+    self.registers().flags()
+  }
+
+  /// Sets `rflags` / `eflags`
+  pub fn set_flags(&mut self, flags: u32) {
+    self.registers_mut().set_flags(flags)
+  }
+
+  /// Returns `rbp` / `ebp`
+  #[cfg(target_arch = "x86_64")]
+  pub(crate) fn frame_pointer(&self) -> usize {
+    self.registers().frame_pointer()
+  }
+
+  /// Returns pointer to the wrapped `CONTEXT` / `WOW64_CONTEXT` for APIs which take either
+  #[cfg(target_arch = "x86_64")]
+  pub(crate) fn as_mut_ptr(&mut self) -> *mut winapi::ctypes::c_void {
+    match self {
+      Self::Native(context) => &mut context.0 as *mut CONTEXT as *mut _,
+      Self::Wow64(context) => &mut **context as *mut WOW64_CONTEXT as *mut _
+    }
+  }
+
+  /// Returns debug register `Dr<index>`, `None` for indices other than 0-3, 6 and 7
+  pub fn debug_register(&self, index: usize) -> Option<usize> {
+    self.registers().debug_register(index)
+  }
+
+  /// Sets debug register `Dr<index>`, returns false for indices other than 0-3, 6 and 7
+  pub fn set_debug_register(&mut self, index: usize, value: usize) -> bool {
+    self.registers_mut().set_debug_register(index, value)
+  }
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+impl Clone for ThreadContext {
+  fn clone(&self) -> Self {
+    match self {
+      Self::Native(context) => Self::Native(Box::new(AlignedContext(context.0))),
+      #[cfg(target_arch = "x86_64")]
+      Self::Wow64(context) => Self::Wow64(context.clone())
+    }
+  }
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+impl Debug for ThreadContext {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} context(ip 0x{:X}, sp 0x{:X})",
+      match self.is_wow64() {
+        true => "wow64",
+        false if cfg!(target_arch = "x86_64") => "x64",
+        false => "x86"
+      },
+      self.instruction_pointer(),
+      self.stack_pointer()
+    )
+  }
+}
+
+/// Returns ids of threads owned by a process
+pub(crate) fn thread_ids(process_id: u32) -> io::Result<Vec<u32>> {
+  let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+  if snapshot == INVALID_HANDLE_VALUE {
+    return Err(CuralError::Snapshot(io::Error::last_os_error()).into());
+  }
+  let snapshot = HandleGuard(snapshot);
+
+  let mut entry = unsafe { mem::zeroed::<THREADENTRY32>() };
+  entry.dwSize = mem::size_of::<THREADENTRY32>() as u32;
+
+  let mut result = Vec::new();
+  let mut found = unsafe { Thread32First(snapshot.get(), &mut entry) };
+  while found != 0 {
+    if entry.th32OwnerProcessID == process_id {
+      result.push(entry.th32ThreadID);
+    }
+    found = unsafe { Thread32Next(snapshot.get(), &mut entry) };
+  }
+
+  Ok(result)
+}
+
+/// Thread of a process found by [`Process::threads`]
+/// 
+/// Only the id is known up front, handles are opened on demand with the
+/// access asked for and kept until the thread and all of its clones are
+/// dropped, clones share them
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// let process = Process::find("process.exe").expect("no such process");
+/// for thread in process.threads().expect("Couldn't get threads") {
+///   println!("{:?} alive: {:?}", thread, thread.is_alive());
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Thread {
+  pub(crate) id: u32,
+  pub(crate) process_id: u32,
+  pub(crate) wow64: bool,
+  handles: Arc<Mutex<Vec<(u32, HandleGuard)>>>
+}
+
+impl Thread {
+  /// `wow64` tells whether the owning process runs under WOW64 on an x64 caller
+  pub(crate) fn new(id: u32, process_id: u32, wow64: bool) -> Self {
+    Self { id, process_id, wow64, handles: Arc::new(Mutex::new(Vec::new())) }
+  }
+
+  /// Returns thread id
+  pub fn get_id(&self) -> &u32 {
+    &self.id
+  }
+
+  /// Returns id of the process owning the thread
+  pub fn get_process_id(&self) -> &u32 {
+    &self.process_id
+  }
+
+  /// Returns handle with at least `access` (`THREAD_*` rights), opening one if needed
+  /// 
+  /// A handle opened earlier with enough access is reused. The handle stays
+  /// valid as long as the thread or one of its clones is alive, don't close it.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// use winapi::um::winnt::THREAD_QUERY_LIMITED_INFORMATION;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.threads().expect("Couldn't get threads").remove(0);
+  /// let handle = thread.open(THREAD_QUERY_LIMITED_INFORMATION).expect("Couldn't open thread");
+  /// ```
+  pub fn open(&self, access: u32) -> io::Result<HANDLE> {
+    let mut handles = self.handles.lock().unwrap_or_else(|error| error.into_inner());
+    if let Some((_, handle)) = handles.iter().find(|(granted, _)| granted & access == access) {
+      return Ok(handle.get());
+    }
+
+    let handle = unsafe { OpenThread(access, 0, self.id) };
+    if handle.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+
+    handles.push((access, HandleGuard(handle)));
+    Ok(handle)
+  }
+
+  /// Returns is the thread still running (or suspended)
+  /// 
+  /// A thread which can't be opened anymore because its id is gone counts as exited
+  pub fn is_alive(&self) -> io::Result<bool> {
+    let handle = match self.open(SYNCHRONIZE) {
+      Ok(handle) => handle,
+      Err(error) if error.raw_os_error() == Some(ERROR_INVALID_PARAMETER as i32) => return Ok(false),
+      Err(error) => return Err(error)
+    };
+
+    match unsafe { WaitForSingleObject(handle, 0) } {
+      WAIT_FAILED => Err(io::Error::last_os_error()),
+      WAIT_TIMEOUT => Ok(true),
+      _ => Ok(false)
+    }
+  }
+
+  /// Suspends the thread, returns the previous suspend count
+  /// 
+  /// Threads of WOW64 targets are suspended with `Wow64SuspendThread`. Errors
+  /// keep the OS code, so access denied (`ERROR_ACCESS_DENIED`) can be told
+  /// apart from an exited thread (`ERROR_INVALID_PARAMETER` while opening).
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.threads().expect("Couldn't get threads").remove(0);
+  /// thread.suspend().expect("Couldn't suspend");
+  /// thread.resume().expect("Couldn't resume");
+  /// ```
+  pub fn suspend(&self) -> io::Result<u32> {
+    let handle = self.open(THREAD_SUSPEND_RESUME)?;
+    let count = match self.wow64 {
+      true => wow64_suspend(handle),
+      false => unsafe { SuspendThread(handle) }
+    };
+
+    match count {
+      u32::MAX => Err(io::Error::last_os_error()),
+      count => Ok(count)
+    }
+  }
+
+  /// Resumes the thread, returns the previous suspend count
+  /// 
+  /// The thread only runs again once the count drops to zero
+  pub fn resume(&self) -> io::Result<u32> {
+    let handle = self.open(THREAD_SUSPEND_RESUME)?;
+
+    match unsafe { ResumeThread(handle) } {
+      u32::MAX => Err(io::Error::last_os_error()),
+      count => Ok(count)
+    }
+  }
+
+  /// Suspends the thread until the returned guard is dropped, even by a panic
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.threads().expect("Couldn't get threads").remove(0);
+  /// {
+  ///   let _guard = thread.suspend_guard().expect("Couldn't suspend");
+  ///   process.write(0u32, 0x1000);
+  /// } // resumed here
+  /// ```
+  pub fn suspend_guard(&self) -> io::Result<SuspendGuard<'_>> {
+    self.suspend()?;
+    Ok(SuspendGuard { thread: self })
+  }
+
+  /// Returns address the thread was started at (`ThreadQuerySetWin32StartAddress`)
+  /// 
+  /// Needs `THREAD_QUERY_INFORMATION`, WOW64 threads report their 32-bit start
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.threads().expect("Couldn't get threads").remove(0);
+  /// println!("started at 0x{:X}", thread.start_address().expect("Couldn't query thread"));
+  /// ```
+  pub fn start_address(&self) -> io::Result<usize> {
+    ntdll::thread_start_address(self.open(THREAD_QUERY_INFORMATION)?)
+  }
+
+  /// Returns module containing the start address, `None` for threads started
+  /// outside of any module (e.g. in injected shellcode)
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// for thread in process.threads().expect("Couldn't get threads") {
+  ///   println!("{:?} starts in {:?}", thread, thread.start_module(&process));
+  /// }
+  /// ```
+  pub fn start_module(&self, process: &Process) -> io::Result<Option<Module>> {
+    process.module_at(self.start_address()?)
+  }
+
+  /// Returns creation and exit time of the thread with CPU time it has used
+  /// 
+  /// Needs only `THREAD_QUERY_LIMITED_INFORMATION`, so works without full access
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// for thread in process.threads().expect("Couldn't get threads") {
+  ///   let times = thread.times().expect("Couldn't get times");
+  ///   println!("{:?} used {:?}", thread, times.get_cpu_time());
+  /// }
+  /// ```
+  pub fn times(&self) -> io::Result<ThreadTimes> {
+    let handle = self.open(THREAD_QUERY_LIMITED_INFORMATION)?;
+
+    let mut creation = unsafe { mem::zeroed::<FILETIME>() };
+    let mut exit = unsafe { mem::zeroed::<FILETIME>() };
+    let mut kernel = unsafe { mem::zeroed::<FILETIME>() };
+    let mut user = unsafe { mem::zeroed::<FILETIME>() };
+    if unsafe { GetThreadTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    // exit time stays zeroed while the thread runs
+    let exited = exit.dwLowDateTime != 0 || exit.dwHighDateTime != 0;
+
+    Ok(ThreadTimes {
+      creation: filetime_to_system_time(&creation),
+      exit: exited.then(|| filetime_to_system_time(&exit)),
+      kernel: filetime_to_duration(&kernel),
+      user: filetime_to_duration(&user)
+    })
+  }
+
+  /// Returns when the thread was created
+  pub(crate) fn creation_time(&self) -> io::Result<SystemTime> {
+    Ok(self.times()?.creation)
+  }
+
+  /// Captures registers of the thread, suspending it meanwhile
+  /// 
+  /// WOW64 threads report their 32-bit view. x86 callers get an x86 context
+  /// and fail with [`io::ErrorKind::Unsupported`] for threads of x64 processes.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.threads().expect("Couldn't get threads").remove(0);
+  /// let context = thread.context().expect("Couldn't get context");
+  /// println!("at 0x{:X}", context.instruction_pointer());
+  /// ```
+  #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+  pub fn context(&self) -> io::Result<ThreadContext> {
+    #[cfg(target_arch = "x86")]
+    self.check_x86_owner()?;
+
+    let handle = self.open(THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT)?;
+    let _guard = self.suspend_guard()?;
+
+    ThreadContext::get(handle, self.wow64)
+  }
+
+  /// Applies registers to the thread, which must already be suspended
+  /// 
+  /// Setting registers of a running thread would race whatever it's executing,
+  /// so that fails with [`io::ErrorKind::InvalidInput`] and changes nothing.
+  /// Keep a [`SuspendGuard`] alive between [`Thread::context`] and this.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.threads().expect("Couldn't get threads").remove(0);
+  /// let _guard = thread.suspend_guard().expect("Couldn't suspend");
+  /// let mut context = thread.context().expect("Couldn't get context");
+  /// context.set_debug_register(0, 0x7FF600001000);
+  /// context.set_debug_register(7, 1);
+  /// thread.set_context(&context).expect("Couldn't set context");
+  /// ```
+  #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+  pub fn set_context(&self, context: &ThreadContext) -> io::Result<()> {
+    if context.is_wow64() != self.wow64 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("context bitness doesn't match {:?}", self)
+      ));
+    }
+    #[cfg(target_arch = "x86")]
+    self.check_x86_owner()?;
+
+    let handle = self.open(THREAD_SUSPEND_RESUME | THREAD_SET_CONTEXT)?;
+    // the count before our own suspend tells whether the caller suspended it,
+    // and ours keeps it suspended if they resume meanwhile
+    let result = match self.suspend()? {
+      0 => Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{:?} must be suspended before setting its context", self)
+      )),
+      _ => context.set(handle)
+    };
+    self.resume()?;
+
+    result
+  }
+
+  /// Fails for threads of x64 processes, whose registers an x86 `CONTEXT`
+  /// can't hold
+  #[cfg(target_arch = "x86")]
+  fn check_x86_owner(&self) -> io::Result<()> {
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, self.process_id) };
+    if process.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+    let process = HandleGuard(process);
+
+    match query_x64(process.get())? {
+      true => Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("x86 callers can't access registers of {:?}, its process is x64", self)
+      )),
+      false => Ok(())
+    }
+  }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn wow64_suspend(handle: HANDLE) -> u32 {
+  unsafe { Wow64SuspendThread(handle) }
+}
+
+/// x86 callers only see x86 targets, no WOW64 split there
+#[cfg(not(target_arch = "x86_64"))]
+fn wow64_suspend(handle: HANDLE) -> u32 {
+  unsafe { SuspendThread(handle) }
+}
+
+/// How [`Process::main_thread`] picks the thread
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MainThreadStrategy {
+  /// Thread with the earliest creation time, the one the process started with
+  /// unless it already exited
+  #[default]
+  CreationTime,
+  /// Thread owning the process's main window (visible, top-level and unowned),
+  /// console daemons have none, so only [`MainThreadStrategy::CreationTime`] works there
+  Window
+}
+
+/// Thread summary from [`Process::threads_with_info`]
+/// 
+/// Fields which couldn't be queried (usually for lack of access) are `None`
+#[derive(Clone, Debug)]
+pub struct ThreadInfo {
+  pub(crate) thread: Thread,
+  pub(crate) start_address: Option<usize>,
+  pub(crate) module: Option<String>,
+  pub(crate) creation_time: Option<SystemTime>
+}
+
+impl ThreadInfo {
+  /// Returns the thread
+  pub fn get_thread(&self) -> &Thread {
+    &self.thread
+  }
+
+  /// Returns thread id
+  pub fn get_id(&self) -> &u32 {
+    &self.thread.id
+  }
+
+  /// Returns address the thread was started at
+  pub fn get_start_address(&self) -> Option<usize> {
+    self.start_address
+  }
+
+  /// Returns name of the module containing the start address
+  pub fn get_module(&self) -> Option<&str> {
+    self.module.as_deref()
+  }
+
+  /// Returns when the thread was created
+  pub fn get_creation_time(&self) -> Option<SystemTime> {
+    self.creation_time
+  }
+}
+
+/// Times of a thread, see [`Thread::times`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThreadTimes {
+  pub(crate) creation: SystemTime,
+  pub(crate) exit: Option<SystemTime>,
+  pub(crate) kernel: Duration,
+  pub(crate) user: Duration
+}
+
+impl ThreadTimes {
+  /// Returns when the thread was created
+  pub fn get_creation_time(&self) -> SystemTime {
+    self.creation
+  }
+
+  /// Returns when the thread exited, `None` while it runs
+  pub fn get_exit_time(&self) -> Option<SystemTime> {
+    self.exit
+  }
+
+  /// Returns time spent in kernel mode
+  pub fn get_kernel_time(&self) -> Duration {
+    self.kernel
+  }
+
+  /// Returns time spent in user mode
+  pub fn get_user_time(&self) -> Duration {
+    self.user
+  }
+
+  /// Returns kernel and user time together
+  pub fn get_cpu_time(&self) -> Duration {
+    self.kernel + self.user
+  }
+}
+
+/// Keeps a [`Thread`] suspended, see [`Thread::suspend_guard`]
+pub struct SuspendGuard<'t> {
+  thread: &'t Thread
+}
+
+impl SuspendGuard<'_> {
+  /// Returns the suspended thread
+  pub fn get_thread(&self) -> &Thread {
+    self.thread
+  }
+}
+
+impl Drop for SuspendGuard<'_> {
+  fn drop(&mut self) {
+    let _ = self.thread.resume();
+  }
+}
+
+impl Debug for SuspendGuard<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "suspended {:?}", self.thread)
+  }
+}
+
+impl Display for Thread {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "thread({}) of {}", self.id, self.process_id)
+  }
+}
+
+impl Debug for Thread {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Thread created in the target by [`Process::create_thread`]
+/// 
+/// Dropping it closes the thread handle, the thread itself keeps running
+pub struct RemoteThread {
+  pub(crate) handle: HandleGuard,
+  pub(crate) id: u32
+}
+
+impl RemoteThread {
+  /// Returns thread id
+  pub fn get_id(&self) -> &u32 {
+    &self.id
+  }
+
+  /// Returns thread handle
+  pub fn get_handle(&self) -> HANDLE {
+    self.handle.get()
+  }
+
+  /// Waits for the thread to exit and returns its exit code
+  /// 
+  /// Gives up after `timeout` with an [`io::ErrorKind::TimedOut`] error, `None` waits forever
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.create_thread(0x7FF600001000, 0).expect("Couldn't create thread");
+  /// let code = thread.join(Some(Duration::from_secs(5))).expect("Couldn't join");
+  /// ```
+  pub fn join(&self, timeout: Option<Duration>) -> io::Result<u32> {
+    let millis = timeout.map_or(INFINITE, |timeout| timeout.as_millis().min(INFINITE as u128 - 1) as u32);
+    match unsafe { WaitForSingleObject(self.handle.get(), millis) } {
+      WAIT_FAILED => return Err(io::Error::last_os_error()),
+      WAIT_TIMEOUT => return Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("thread {} didn't exit in time", self.id)
+      )),
+      _ => {}
+    }
+
+    self.exit_code()
+  }
+
+  /// Returns has the thread exited
+  pub fn is_finished(&self) -> io::Result<bool> {
+    match unsafe { WaitForSingleObject(self.handle.get(), 0) } {
+      WAIT_FAILED => Err(io::Error::last_os_error()),
+      WAIT_OBJECT_0 => Ok(true),
+      _ => Ok(false)
+    }
+  }
+
+  /// Resumes suspended thread, returns the previous suspend count
+  pub fn resume(&self) -> io::Result<u32> {
+    match unsafe { ResumeThread(self.handle.get()) } {
+      u32::MAX => Err(io::Error::last_os_error()),
+      count => Ok(count)
+    }
+  }
+
+  /// Forcibly ends the thread with `code`
+  /// 
+  /// The thread gets no chance to clean up, locks it holds stay taken
+  pub fn terminate(&self, code: u32) -> io::Result<()> {
+    if unsafe { TerminateThread(self.handle.get(), code) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+  }
+
+  fn exit_code(&self) -> io::Result<u32> {
+    let mut code = 0;
+    if unsafe { GetExitCodeThread(self.handle.get(), &mut code) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    Ok(code)
+  }
+}
+
+impl Display for RemoteThread {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "thread({})", self.id)
+  }
+}
+
+impl Debug for RemoteThread {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Lists threads of the process from a toolhelp snapshot, no handles are opened
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let threads = process.threads().expect("Couldn't get threads");
+  /// println!("{} has {} threads", process, threads.len());
+  /// ```
+  pub fn threads(&self) -> io::Result<Vec<Thread>> {
+    let wow64 = cfg!(target_pointer_width = "64") && !self.is_x64()?;
+
+    Ok(
+      thread_ids(self.id)?
+        .into_iter()
+        .map(|id| Thread::new(id, self.id, wow64))
+        .collect()
+    )
+  }
+
+  /// Lists threads with their start address, its module and creation time
+  /// 
+  /// Threads which can't be queried are kept with `None` fields
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// for info in process.threads_with_info().expect("Couldn't get threads") {
+  ///   println!("{} starts in {:?}", info.get_id(), info.get_module());
+  /// }
+  /// ```
+  pub fn threads_with_info(&self) -> io::Result<Vec<ThreadInfo>> {
+    Ok(
+      self.threads()?
+        .into_iter()
+        .map(|thread| {
+          let start_address = thread.start_address().ok();
+          let module = start_address
+            .and_then(|address| self.module_at(address).ok().flatten())
+            .map(|module| module.name);
+          let creation_time = thread.creation_time().ok();
+
+          ThreadInfo { thread, start_address, module, creation_time }
+        })
+        .collect()
+    )
+  }
+
+  /// Samples CPU time of every thread twice, `interval` apart, and returns
+  /// the `n` threads which used the most of it meanwhile, busiest first
+  /// 
+  /// Threads which exit or can't be queried by the second sample are left out,
+  /// ones missing from the first, like threads created in between, count all
+  /// of their CPU time
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// for (thread, used) in process.busiest_threads(5, Duration::from_secs(1)).expect("Couldn't sample threads") {
+  ///   println!("{:?} used {:?}", thread, used);
+  /// }
+  /// ```
+  pub fn busiest_threads(&self, n: usize, interval: Duration) -> io::Result<Vec<(Thread, Duration)>> {
+    let before = self.threads()?
+      .into_iter()
+      .filter_map(|thread| thread.times().ok().map(|times| (thread.id, times.get_cpu_time())))
+      .collect::<HashMap<u32, Duration>>();
+
+    std::thread::sleep(interval);
+
+    let mut busiest = self.threads()?
+      .into_iter()
+      .filter_map(|thread| {
+        let after = thread.times().ok()?;
+        if after.exit.is_some() {
+          return None;
+        }
+
+        let used = before.get(&thread.id).copied().unwrap_or_default();
+        Some((thread, after.get_cpu_time().saturating_sub(used)))
+      })
+      .collect::<Vec<_>>();
+
+    busiest.sort_by_key(|(_, used)| std::cmp::Reverse(*used));
+    busiest.truncate(n);
+    Ok(busiest)
+  }
+
+  /// Picks "the" thread of the process, see [`MainThreadStrategy`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{MainThreadStrategy, Process};
+  /// let process = Process::find("notepad.exe").expect("no such process");
+  /// let thread = process.main_thread(MainThreadStrategy::Window).expect("no main thread");
+  /// println!("{:?} starts in {:?}", thread, thread.start_module(&process));
+  /// ```
+  pub fn main_thread(&self, strategy: MainThreadStrategy) -> io::Result<Thread> {
+    let threads = self.threads()?;
+
+    let found = match strategy {
+      MainThreadStrategy::CreationTime => threads
+        .into_iter()
+        .filter_map(|thread| thread.creation_time().ok().map(|time| (time, thread)))
+        .min_by_key(|(time, _)| *time)
+        .map(|(_, thread)| thread),
+      MainThreadStrategy::Window => {
+        let owner = top_level_windows()?
+          .into_iter()
+          .filter(|hwnd| is_main_window(*hwnd))
+          .map(window_owner)
+          .find(|(process_id, _)| process_id == &self.id)
+          .map(|(_, thread_id)| thread_id);
+        owner.and_then(|id| threads.into_iter().find(|thread| thread.id == id))
+      }
+    };
+
+    found.ok_or_else(|| io::Error::new(
+      io::ErrorKind::NotFound,
+      format!("no main thread of {}({}) found by {:?}", self.name, self.id, strategy)
+    ))
+  }
+
+  /// Starts thread in the target at `start` with one pointer-sized `parameter`
+  /// 
+  /// Fails with [`io::ErrorKind::PermissionDenied`] when the process handle
+  /// lacks `PROCESS_CREATE_THREAD`
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.create_thread(0x7FF600001000, 0).expect("Couldn't create thread");
+  /// let code = thread.join(None).expect("Couldn't join");
+  /// ```
+  pub fn create_thread(&self, start: usize, parameter: usize) -> io::Result<RemoteThread> {
+    self.spawn_thread(start, parameter, 0)
+  }
+
+  /// Same as [`Process::create_thread`], but the thread doesn't run until [`RemoteThread::resume`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let thread = process.create_thread_suspended(0x7FF600001000, 0).expect("Couldn't create thread");
+  /// thread.resume().expect("Couldn't resume");
+  /// ```
+  pub fn create_thread_suspended(&self, start: usize, parameter: usize) -> io::Result<RemoteThread> {
+    self.spawn_thread(start, parameter, CREATE_SUSPENDED)
+  }
+
+  fn spawn_thread(&self, start: usize, parameter: usize, flags: u32) -> io::Result<RemoteThread> {
+    let routine = unsafe { mem::transmute::<usize, LPTHREAD_START_ROUTINE>(start) };
+
+    let mut id = 0;
+    let thread = unsafe {
+      CreateRemoteThread(self.handle.get(), ptr::null_mut(), 0, routine, parameter as *mut _, flags, &mut id)
+    };
+
+    if thread.is_null() {
+      let error = io::Error::last_os_error();
+      if error.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+        return Err(io::Error::new(
+          io::ErrorKind::PermissionDenied,
+          format!("handle of {}({}) lacks PROCESS_CREATE_THREAD", self.name, self.id)
+        ));
+      }
+      return Err(error);
+    }
+
+    Ok(RemoteThread { handle: HandleGuard(thread), id })
+  }
+}
@@ -0,0 +1,308 @@
+use std::ffi::OsString;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::mem;
+use std::os::windows::ffi::OsStringExt;
+use std::ptr;
+
+use winapi::shared::sddl::ConvertSidToStringSidW;
+use winapi::shared::winerror::ERROR_ACCESS_DENIED;
+use winapi::shared::winerror::ERROR_INSUFFICIENT_BUFFER;
+use winapi::um::processthreadsapi::GetCurrentProcess;
+use winapi::um::processthreadsapi::GetCurrentProcessId;
+use winapi::um::processthreadsapi::OpenProcessToken;
+use winapi::um::securitybaseapi::GetSidSubAuthority;
+use winapi::um::securitybaseapi::GetSidSubAuthorityCount;
+use winapi::um::securitybaseapi::GetTokenInformation;
+use winapi::um::winbase::LocalFree;
+use winapi::um::winbase::LookupAccountSidW;
+use winapi::um::winnt::TokenElevation;
+use winapi::um::winnt::TokenIntegrityLevel;
+use winapi::um::winnt::TokenUser;
+use winapi::um::winnt::HANDLE;
+use winapi::um::winnt::PSID;
+use winapi::um::winnt::SECURITY_MANDATORY_HIGH_RID;
+use winapi::um::winnt::SECURITY_MANDATORY_LOW_RID;
+use winapi::um::winnt::SECURITY_MANDATORY_MEDIUM_RID;
+use winapi::um::winnt::SECURITY_MANDATORY_SYSTEM_RID;
+use winapi::um::winnt::TOKEN_ELEVATION;
+use winapi::um::winnt::TOKEN_INFORMATION_CLASS;
+use winapi::um::winnt::TOKEN_MANDATORY_LABEL;
+use winapi::um::winnt::TOKEN_QUERY;
+use winapi::um::winnt::TOKEN_USER;
+
+use crate::error::CuralError;
+use crate::handle::HandleGuard;
+use crate::Process;
+
+/// Mandatory integrity level of a process token
+/// 
+/// A process can only be opened for writing by one at the same or a higher
+/// level, so levels compare in that order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IntegrityLevel {
+  /// Below `SECURITY_MANDATORY_LOW_RID`, e.g. anonymous tokens
+  Untrusted,
+  /// Sandboxes like protected mode browsers
+  Low,
+  /// Regular processes of a user, including medium plus ones
+  Medium,
+  /// Elevated processes, "run as administrator"
+  High,
+  /// Services and the system, including protected processes
+  System
+}
+
+impl From<u32> for IntegrityLevel {
+  fn from(rid: u32) -> Self {
+    match rid {
+      SECURITY_MANDATORY_SYSTEM_RID.. => Self::System,
+      SECURITY_MANDATORY_HIGH_RID.. => Self::High,
+      SECURITY_MANDATORY_MEDIUM_RID.. => Self::Medium,
+      SECURITY_MANDATORY_LOW_RID.. => Self::Low,
+      _ => Self::Untrusted
+    }
+  }
+}
+
+/// Account which owns a process, see [`Process::owner`]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Owner {
+  pub(crate) domain: String,
+  pub(crate) username: String,
+  pub(crate) sid: String
+}
+
+impl Owner {
+  /// Returns domain of the account, the machine name for local accounts
+  pub fn get_domain(&self) -> &String {
+    &self.domain
+  }
+
+  /// Returns name of the account
+  pub fn get_username(&self) -> &String {
+    &self.username
+  }
+
+  /// Returns SID of the account as a string, e.g. `S-1-5-18`
+  pub fn get_sid(&self) -> &String {
+    &self.sid
+  }
+
+  /// Returns whether `name` is the account, either `username` or
+  /// `domain\username`, ignoring case like Windows does
+  pub fn is(&self, name: &str) -> bool {
+    match name.split_once('\\') {
+      Some((domain, username)) => domain.eq_ignore_ascii_case(&self.domain) && username.eq_ignore_ascii_case(&self.username),
+      None => name.eq_ignore_ascii_case(&self.username)
+    }
+  }
+}
+
+impl Display for Owner {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}\\{} ({})", self.domain, self.username, self.sid)
+  }
+}
+
+impl Debug for Owner {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+/// Queries token information into a buffer sized by the first call, SIDs in it
+/// point into the buffer itself
+fn token_information(token: HANDLE, class: TOKEN_INFORMATION_CLASS) -> io::Result<Vec<u64>> {
+  let mut len = 0;
+  unsafe { GetTokenInformation(token, class, ptr::null_mut(), 0, &mut len) };
+  if len == 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  // u64 backing keeps the structures aligned
+  let mut buffer = vec![0u64; (len as usize).div_ceil(mem::size_of::<u64>())];
+  if unsafe { GetTokenInformation(token, class, buffer.as_mut_ptr() as *mut _, len, &mut len) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  Ok(buffer)
+}
+
+/// Opens token of the process for querying, access denied is reported as
+/// [`CuralError::TokenAccessDenied`] so it isn't mistaken for an answer
+fn open_token(process: HANDLE, id: u32) -> io::Result<HandleGuard> {
+  let mut token = ptr::null_mut();
+  if unsafe { OpenProcessToken(process, TOKEN_QUERY, &mut token) } == 0 {
+    let error = io::Error::last_os_error();
+    return Err(match error.raw_os_error() {
+      Some(code) if code == ERROR_ACCESS_DENIED as i32 => CuralError::TokenAccessDenied(id).into(),
+      _ => error
+    });
+  }
+
+  Ok(HandleGuard(token))
+}
+
+fn is_token_elevated(token: HANDLE) -> io::Result<bool> {
+  let buffer = token_information(token, TokenElevation)?;
+  if buffer.len() * mem::size_of::<u64>() < mem::size_of::<TOKEN_ELEVATION>() {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "short TOKEN_ELEVATION"));
+  }
+
+  let elevation = unsafe { *(buffer.as_ptr() as *const TOKEN_ELEVATION) };
+  Ok(elevation.TokenIsElevated != 0)
+}
+
+fn sid_to_string(sid: PSID) -> io::Result<String> {
+  let mut string = ptr::null_mut();
+  if unsafe { ConvertSidToStringSidW(sid, &mut string) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  let len = (0..).take_while(|index| unsafe { *string.add(*index) } != 0).count();
+  let sid = OsString::from_wide(unsafe { std::slice::from_raw_parts(string, len) });
+  unsafe { LocalFree(string as *mut _) };
+
+  Ok(sid.to_string_lossy().into_owned())
+}
+
+/// Resolves account name and domain of the SID
+fn lookup_account(sid: PSID) -> io::Result<(String, String)> {
+  let mut name_len = 0;
+  let mut domain_len = 0;
+  let mut usage = 0;
+  unsafe { LookupAccountSidW(ptr::null(), sid, ptr::null_mut(), &mut name_len, ptr::null_mut(), &mut domain_len, &mut usage) };
+  let error = io::Error::last_os_error();
+  if error.raw_os_error() != Some(ERROR_INSUFFICIENT_BUFFER as i32) {
+    return Err(error);
+  }
+
+  let mut name = vec![0u16; name_len as usize];
+  let mut domain = vec![0u16; domain_len as usize];
+  if unsafe {
+    LookupAccountSidW(
+      ptr::null(),
+      sid,
+      name.as_mut_ptr(),
+      &mut name_len,
+      domain.as_mut_ptr(),
+      &mut domain_len,
+      &mut usage
+    )
+  } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  // on success the lengths exclude the terminator
+  Ok((
+    OsString::from_wide(&name[..name_len as usize]).to_string_lossy().into_owned(),
+    OsString::from_wide(&domain[..domain_len as usize]).to_string_lossy().into_owned()
+  ))
+}
+
+/// Returns whether the current process runs elevated, "as administrator"
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// let service = Process::find("service.exe").expect("no such process");
+/// if service.is_elevated().unwrap_or(true) && !cural::current_process_is_elevated().expect("Couldn't query token") {
+///   println!("run as administrator to open {}", service.get_name());
+/// }
+/// ```
+pub fn current_process_is_elevated() -> io::Result<bool> {
+  let token = open_token(unsafe { GetCurrentProcess() }, unsafe { GetCurrentProcessId() })?;
+  is_token_elevated(token.get())
+}
+
+impl Process {
+  /// Returns mandatory integrity level of the process token
+  /// 
+  /// Needs `PROCESS_QUERY_LIMITED_INFORMATION`. Compare with the level of the
+  /// current process to tell whether opening it for writing can work at all.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::{IntegrityLevel, Process};
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// if process.integrity_level().expect("Couldn't query token") >= IntegrityLevel::High {
+  ///   println!("run as administrator to write to {}", process.get_name());
+  /// }
+  /// ```
+  pub fn integrity_level(&self) -> io::Result<IntegrityLevel> {
+    let token = open_token(self.handle.get(), self.id)?;
+    let buffer = token_information(token.get(), TokenIntegrityLevel)?;
+
+    let rid = unsafe {
+      let label = &*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL);
+      let sid = label.Label.Sid;
+      let count = *GetSidSubAuthorityCount(sid);
+      if count == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "integrity label without subauthorities"));
+      }
+      *GetSidSubAuthority(sid, count as u32 - 1)
+    };
+
+    Ok(IntegrityLevel::from(rid))
+  }
+
+  /// Returns whether the process token is elevated, needs `PROCESS_QUERY_LIMITED_INFORMATION`
+  /// 
+  /// Fails with [`CuralError::TokenAccessDenied`] when the token can't be
+  /// opened, e.g. for services of another user, so "can't tell" stays apart
+  /// from "not elevated".
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// println!("elevated: {}", process.is_elevated().expect("Couldn't query token"));
+  /// ```
+  pub fn is_elevated(&self) -> io::Result<bool> {
+    let token = open_token(self.handle.get(), self.id)?;
+    is_token_elevated(token.get())
+  }
+
+  /// Returns account which owns the process, needs `PROCESS_QUERY_LIMITED_INFORMATION`
+  /// 
+  /// Fails with [`CuralError::TokenAccessDenied`] for processes whose token
+  /// can't be opened, like protected and system ones.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let owner = process.owner().expect("Couldn't query token");
+  /// println!("{} runs as {}", process.get_name(), owner.get_username());
+  /// ```
+  pub fn owner(&self) -> io::Result<Owner> {
+    let token = open_token(self.handle.get(), self.id)?;
+    let buffer = token_information(token.get(), TokenUser)?;
+
+    let sid = unsafe { (*(buffer.as_ptr() as *const TOKEN_USER)).User.Sid };
+    let (username, domain) = lookup_account(sid)?;
+
+    Ok(Owner { domain, username, sid: sid_to_string(sid)? })
+  }
+
+  /// Gets all processes owned by `name`, either `username` or `domain\username`
+  /// 
+  /// Processes which can't be opened or whose owner can't be queried are skipped
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let mine = Process::all_for_user("alice").expect("Couldn't get any process");
+  /// println!("found {:?}", mine);
+  /// ```
+  pub fn all_for_user(name: &str) -> io::Result<Vec<Process>> {
+    Ok(
+      Process::all()?
+        .into_iter()
+        .filter(|process| process.owner().is_ok_and(|owner| owner.is(name)))
+        .collect()
+    )
+  }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::io;
+use std::time::SystemTime;
+
+use winapi::um::winnt::PROCESS_ALL_ACCESS;
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+use winapi::um::winnt::PROCESS_TERMINATE;
+
+use crate::Process;
+use crate::ProcessEntry;
+
+/// Parent/child relations of every process from a single snapshot, see [`process_tree`]
+/// 
+/// A parent id which was reused by a process created after the child is not
+/// linked, such children are roots like the ones whose parent has exited
+pub struct ProcessTree {
+  pub(crate) entries: Vec<ProcessEntry>,
+  pub(crate) parents: HashMap<u32, usize>,
+  pub(crate) children: HashMap<u32, Vec<usize>>
+}
+
+/// Builds [`ProcessTree`] from one process snapshot
+/// 
+/// Every process is opened with `PROCESS_QUERY_LIMITED_INFORMATION` to compare
+/// creation times, links to or from processes which can't be opened are kept
+/// unverified
+/// 
+/// # Examples
+/// ```
+/// use cural::Process;
+/// let tree = cural::process_tree().expect("Couldn't create snapshot");
+/// let launcher = Process::find("launcher.exe").expect("no such process");
+/// for entry in tree.descendants_of(*launcher.get_id()) {
+///   println!("spawned {}", entry);
+/// }
+/// ```
+pub fn process_tree() -> io::Result<ProcessTree> {
+  let entries = Process::list()?;
+
+  let creation_times = entries.iter()
+    .filter_map(|entry| {
+      let process = Process::open_entry(entry, PROCESS_QUERY_LIMITED_INFORMATION, false).ok()?;
+      Some((entry.id, process.creation_time().ok()?))
+    })
+    .collect::<HashMap<u32, SystemTime>>();
+  let indices = entries.iter()
+    .enumerate()
+    .map(|(index, entry)| (entry.id, index))
+    .collect::<HashMap<u32, usize>>();
+
+  let mut parents = HashMap::new();
+  let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+  for (index, entry) in entries.iter().enumerate() {
+    // the idle process is its own parent
+    if entry.parent_id == entry.id {
+      continue;
+    }
+    let Some(parent) = indices.get(&entry.parent_id) else {
+      continue;
+    };
+
+    // a parent younger than the child only reused the id
+    let reused = match (creation_times.get(&entry.parent_id), creation_times.get(&entry.id)) {
+      (Some(parent), Some(child)) => parent > child,
+      _ => false
+    };
+    if reused {
+      continue;
+    }
+
+    parents.insert(entry.id, *parent);
+    children.entry(entry.parent_id).or_default().push(index);
+  }
+
+  Ok(ProcessTree { entries, parents, children })
+}
+
+/// Opens entries created by `parent_id` after `created` as (id, process and its
+/// creation time), older ones only reused the id and are left out
+fn open_children(entries: &[ProcessEntry], parent_id: u32, created: SystemTime, access: u32) -> Vec<(u32, io::Result<(Process, SystemTime)>)> {
+  entries.iter()
+    .filter(|entry| entry.parent_id == parent_id && entry.id != parent_id)
+    .filter_map(|entry| {
+      let child = Process::open_entry(entry, access, false)
+        .and_then(|child| child.creation_time().map(|child_created| (child, child_created)));
+      match child {
+        Ok((_, child_created)) if child_created < created => None,
+        child => Some((entry.id, child))
+      }
+    })
+    .collect()
+}
+
+/// Pids killed by [`Process::kill_tree`] and the ones which couldn't be
+pub struct KillReport {
+  pub(crate) killed: Vec<u32>,
+  pub(crate) failed: Vec<(u32, io::Error)>
+}
+
+impl KillReport {
+  /// Returns ids of terminated processes in the order they were killed,
+  /// including those which exited on their own meanwhile
+  pub fn get_killed(&self) -> &[u32] {
+    &self.killed
+  }
+
+  /// Returns ids of processes which couldn't be opened or terminated with the error
+  pub fn get_failed(&self) -> &[(u32, io::Error)] {
+    &self.failed
+  }
+
+  /// Returns whether every process of the tree was killed
+  pub fn is_complete(&self) -> bool {
+    self.failed.is_empty()
+  }
+}
+
+impl Display for KillReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "killed {:?}, failed {:?}", self.killed, self.failed)
+  }
+}
+
+impl Debug for KillReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Opens processes created by this one, skipping those which can't be opened
+  /// 
+  /// Takes one snapshot and opens only the matching entries, so it's cheap
+  /// enough to poll. Processes which just reused a child id of an exited
+  /// parent aren't reported, like in [`ProcessTree`].
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::thread;
+  /// use std::time::Duration;
+  /// use cural::Process;
+  /// let launcher = Process::find("launcher.exe").expect("no such process");
+  /// let game = loop {
+  ///   let children = launcher.children().expect("Couldn't create snapshot");
+  ///   if let Some(game) = children.into_iter().find(|child| child.get_name() == "game.exe") {
+  ///     break game;
+  ///   }
+  ///   thread::sleep(Duration::from_millis(100));
+  /// };
+  /// ```
+  pub fn children(&self) -> io::Result<Vec<Process>> {
+    let entries = Process::list()?;
+    let created = self.creation_time()?;
+
+    Ok(
+      open_children(&entries, self.id, created, PROCESS_ALL_ACCESS)
+        .into_iter()
+        .filter_map(|(_, child)| child.ok().map(|(child, _)| child))
+        .collect()
+    )
+  }
+
+  /// Opens children of the process, their children and so on, each one
+  /// followed by its own descendants
+  /// 
+  /// Uses one snapshot with the same checks as [`Process::children`]
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let launcher = Process::find("launcher.exe").expect("no such process");
+  /// for process in launcher.descendants().expect("Couldn't create snapshot") {
+  ///   println!("spawned {}", process);
+  /// }
+  /// ```
+  pub fn descendants(&self) -> io::Result<Vec<Process>> {
+    Ok(
+      self.open_descendants(PROCESS_ALL_ACCESS)?
+        .into_iter()
+        .filter_map(|(_, descendant)| descendant.ok())
+        .collect()
+    )
+  }
+
+  /// Terminates the process and every descendant, leaves first
+  /// 
+  /// Descendants are collected from one snapshot before anything is killed,
+  /// with the same checks as [`Process::descendants`]. Failures don't stop the
+  /// rest of the tree, they're listed in the report. Processes created after
+  /// the snapshot aren't killed. Needs `PROCESS_TERMINATE` and
+  /// `PROCESS_QUERY_LIMITED_INFORMATION` on every process of the tree.
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let launcher = Process::find("launcher.exe").expect("no such process");
+  /// let report = launcher.kill_tree(1).expect("Couldn't create snapshot");
+  /// for (id, error) in report.get_failed() {
+  ///   println!("{} survived: {}", id, error);
+  /// }
+  /// ```
+  pub fn kill_tree(&self, exit_code: u32) -> io::Result<KillReport> {
+    let mut tree = self.open_descendants(PROCESS_TERMINATE | PROCESS_QUERY_LIMITED_INFORMATION)?;
+    tree.insert(0, (self.id, Ok(self.clone())));
+
+    let mut report = KillReport { killed: Vec::new(), failed: Vec::new() };
+    // each descendant comes after its parent, so reversed the leaves go first
+    for (id, process) in tree.into_iter().rev() {
+      match process.and_then(|process| process.kill(exit_code)) {
+        Ok(()) => report.killed.push(id),
+        Err(error) => report.failed.push((id, error))
+      }
+    }
+
+    Ok(report)
+  }
+
+  /// Opens descendants from one snapshot, each one followed by its own descendants
+  fn open_descendants(&self, access: u32) -> io::Result<Vec<(u32, io::Result<Process>)>> {
+    let entries = Process::list()?;
+    let created = self.creation_time()?;
+
+    let mut descendants = Vec::new();
+    let mut visited = HashSet::from([self.id]);
+    // children are popped from the back, reversed to keep snapshot order
+    let mut pending = vec![open_children(&entries, self.id, created, access).into_iter().rev().collect::<Vec<_>>()];
+    while let Some(children) = pending.last_mut() {
+      let Some((id, child)) = children.pop() else {
+        pending.pop();
+        continue;
+      };
+      if !visited.insert(id) {
+        continue;
+      }
+
+      match child {
+        Ok((child, child_created)) => {
+          pending.push(open_children(&entries, id, child_created, access).into_iter().rev().collect());
+          descendants.push((id, Ok(child)));
+        },
+        // unopened processes can't be checked, so their children aren't followed
+        Err(error) => descendants.push((id, Err(error)))
+      }
+    }
+
+    Ok(descendants)
+  }
+}
+
+impl ProcessTree {
+  /// Returns every process of the snapshot
+  pub fn get_entries(&self) -> &[ProcessEntry] {
+    &self.entries
+  }
+
+  /// Returns process by id
+  pub fn get(&self, id: u32) -> Option<&ProcessEntry> {
+    self.entries.iter().find(|entry| entry.id == id)
+  }
+
+  /// Returns parent of the process, `None` when it has exited or its id was reused
+  pub fn parent_of(&self, id: u32) -> Option<&ProcessEntry> {
+    self.parents.get(&id).map(|index| &self.entries[*index])
+  }
+
+  /// Returns processes created directly by the process
+  pub fn children_of(&self, id: u32) -> Vec<&ProcessEntry> {
+    self.children.get(&id)
+      .map(|children| children.iter().map(|index| &self.entries[*index]).collect())
+      .unwrap_or_default()
+  }
+
+  /// Returns children of the process, their children and so on, each one
+  /// followed by its own descendants
+  pub fn descendants_of(&self, id: u32) -> Vec<&ProcessEntry> {
+    let mut descendants = Vec::new();
+    let mut visited = HashSet::from([id]);
+    self.collect_descendants(id, &mut visited, &mut descendants);
+
+    descendants
+  }
+
+  /// Returns processes without a known parent
+  pub fn roots(&self) -> Vec<&ProcessEntry> {
+    self.entries.iter()
+      .filter(|entry| !self.parents.contains_key(&entry.id))
+      .collect()
+  }
+
+  fn collect_descendants<'t>(&'t self, id: u32, visited: &mut HashSet<u32>, descendants: &mut Vec<&'t ProcessEntry>) {
+    for child in self.children_of(id) {
+      // unverified links through reused ids can form a cycle
+      if visited.insert(child.id) {
+        descendants.push(child);
+        self.collect_descendants(child.id, visited, descendants);
+      }
+    }
+  }
+
+  fn render(&self, entry: &ProcessEntry, depth: usize, visited: &mut HashSet<u32>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if !visited.insert(entry.id) {
+      return Ok(());
+    }
+
+    writeln!(f, "{}{}", "  ".repeat(depth), entry)?;
+    for child in self.children_of(entry.id) {
+      self.render(child, depth + 1, visited, f)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Display for ProcessTree {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let mut visited = HashSet::new();
+    for root in self.roots() {
+      self.render(root, 0, &mut visited, f)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Debug for ProcessTree {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
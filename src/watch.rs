@@ -0,0 +1,99 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::Pod;
+use crate::Process;
+
+/// Background watch started by [`Process::watch`], dropping it stops the watch
+pub struct WatchHandle {
+  address: usize,
+  stop: Option<mpsc::Sender<()>>,
+  thread: Option<JoinHandle<()>>
+}
+
+impl WatchHandle {
+  /// Returns watched address
+  pub fn get_address(&self) -> &usize {
+    &self.address
+  }
+
+  /// Returns has the watching thread stopped, it does when the callback panics
+  pub fn is_finished(&self) -> bool {
+    self.thread.as_ref().is_none_or(|thread| thread.is_finished())
+  }
+}
+
+impl Drop for WatchHandle {
+  fn drop(&mut self) {
+    // disconnecting wakes the thread up from its wait
+    self.stop.take();
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+impl Display for WatchHandle {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "watch @ 0x{:X}", self.address)
+  }
+}
+
+impl Debug for WatchHandle {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Process {
+  /// Reads `T` at `address` every `interval` on a background thread and calls
+  /// `on_change(old, new)` whenever it differs from the previous read
+  /// 
+  /// Failed reads are skipped, the first successful one sets the initial
+  /// value without a callback. The watch runs until the handle is dropped.
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::Process;
+  /// let process = Process::find("process.exe").expect("no such process");
+  /// let watch = process.watch(0x1000, Duration::from_millis(100), |old: i32, new: i32| {
+  ///   println!("health {} -> {}", old, new);
+  /// });
+  /// std::thread::sleep(Duration::from_secs(10));
+  /// drop(watch);
+  /// ```
+  pub fn watch<T, F>(&self, address: usize, interval: Duration, on_change: F) -> WatchHandle
+  where
+    T: Pod + PartialEq + Send,
+    F: Fn(T, T) + Send + 'static
+  {
+    let process = self.clone();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let thread = thread::spawn(move || {
+      let mut last = None;
+      loop {
+        if let Ok(value) = process.try_read::<T>(address) {
+          match last {
+            Some(old) if old != value => on_change(old, value),
+            _ => {}
+          }
+          last = Some(value);
+        }
+
+        match stopped.recv_timeout(interval) {
+          Err(RecvTimeoutError::Timeout) => continue,
+          _ => break
+        }
+      }
+    });
+
+    WatchHandle { address, stop: Some(stop), thread: Some(thread) }
+  }
+}
@@ -0,0 +1,150 @@
+use std::ffi::OsStr;
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use winapi::shared::minwindef::BOOL;
+use winapi::shared::minwindef::LPARAM;
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::EnumWindows;
+use winapi::um::winuser::FindWindowW;
+use winapi::um::winuser::GetWindow;
+use winapi::um::winuser::GetWindowTextLengthW;
+use winapi::um::winuser::GetWindowTextW;
+use winapi::um::winuser::GetWindowThreadProcessId;
+use winapi::um::winuser::IsWindowVisible;
+use winapi::um::winuser::PostMessageW;
+use winapi::um::winuser::GW_OWNER;
+use winapi::um::winuser::WM_CLOSE;
+
+use crate::Process;
+
+/// Delay between exit checks of [`Process::try_kill_gracefully`]
+const CLOSE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Returns every top-level window
+pub(crate) fn top_level_windows() -> io::Result<Vec<HWND>> {
+  unsafe extern "system" fn callback(hwnd: HWND, windows: LPARAM) -> BOOL {
+    let windows = &mut *(windows as *mut Vec<HWND>);
+    windows.push(hwnd);
+    1
+  }
+
+  let mut windows = Vec::<HWND>::new();
+  if unsafe { EnumWindows(Some(callback), &mut windows as *mut Vec<HWND> as LPARAM) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  Ok(windows)
+}
+
+/// Returns is the window visible and unowned, as main windows are
+pub(crate) fn is_main_window(hwnd: HWND) -> bool {
+  unsafe { IsWindowVisible(hwnd) != 0 && GetWindow(hwnd, GW_OWNER).is_null() }
+}
+
+/// Returns window title, empty for untitled windows
+pub(crate) fn window_title(hwnd: HWND) -> String {
+  let len = unsafe { GetWindowTextLengthW(hwnd) };
+  if len <= 0 {
+    return String::new();
+  }
+
+  let mut buffer = vec![0u16; len as usize + 1];
+  let copied = unsafe { GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32) };
+
+  String::from_utf16_lossy(&buffer[..copied.max(0) as usize])
+}
+
+/// Returns (process id, thread id) which own the window
+pub(crate) fn window_owner(hwnd: HWND) -> (u32, u32) {
+  let mut process_id = 0;
+  let thread_id = unsafe { GetWindowThreadProcessId(hwnd, &mut process_id) };
+
+  (process_id, thread_id)
+}
+
+impl Process {
+  /// Opens process which owns top-level window with exactly this title
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::from_window_title("Untitled - Notepad").expect("no such window");
+  /// ```
+  pub fn from_window_title(title: &str) -> io::Result<Process> {
+    let wide = OsStr::new(title)
+      .encode_wide()
+      .chain(Some(0))
+      .collect::<Vec<u16>>();
+
+    let hwnd = unsafe { FindWindowW(std::ptr::null(), wide.as_ptr()) };
+    if hwnd.is_null() {
+      return Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no window found with title {}", title)
+      ));
+    }
+
+    Process::open(window_owner(hwnd).0)
+  }
+
+  /// Opens process which owns top-level window whose title contains `part`
+  /// 
+  /// Useful for titles with dynamic content, the first matching window wins
+  /// 
+  /// # Examples
+  /// ```
+  /// use cural::Process;
+  /// let process = Process::from_window_title_contains("Notepad").expect("no such window");
+  /// ```
+  pub fn from_window_title_contains(part: &str) -> io::Result<Process> {
+    let hwnd = top_level_windows()?
+      .into_iter()
+      .find(|hwnd| window_title(*hwnd).contains(part))
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no window found with title containing {}", part)
+      ))?;
+
+    Process::open(window_owner(hwnd).0)
+  }
+
+  /// Posts `WM_CLOSE` to every top-level window of the process and waits up to
+  /// `timeout` for it to exit, then terminates it with exit code `1`
+  /// 
+  /// Returns whether the process exited on its own. A process without windows
+  /// is terminated once the timeout passes. Needs `PROCESS_TERMINATE` and
+  /// `PROCESS_QUERY_LIMITED_INFORMATION`, see [`Process::kill`].
+  /// 
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  /// use cural::Process;
+  /// let process = Process::find("notepad.exe").expect("no such process");
+  /// let graceful = process.try_kill_gracefully(Duration::from_secs(5)).expect("Couldn't kill");
+  /// ```
+  pub fn try_kill_gracefully(&self, timeout: Duration) -> io::Result<bool> {
+    for hwnd in top_level_windows()? {
+      if window_owner(hwnd).0 == self.id {
+        unsafe { PostMessageW(hwnd, WM_CLOSE, 0, 0) };
+      }
+    }
+
+    let start = Instant::now();
+    loop {
+      if self.exit_code()?.is_some() {
+        return Ok(true);
+      }
+      if start.elapsed() >= timeout {
+        break;
+      }
+      thread::sleep(CLOSE_POLL_INTERVAL.min(timeout.saturating_sub(start.elapsed())));
+    }
+
+    self.kill(1)?;
+    Ok(false)
+  }
+}
@@ -0,0 +1,25 @@
+#![cfg(all(target_os = "windows", target_arch = "x86_64"))]
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use cural::MainThreadStrategy;
+
+#[test]
+fn sleeping_thread_is_in_ntdll() {
+  let helper = common::spawn_helper(&[]);
+  // give the main thread time to get into Sleep
+  thread::sleep(Duration::from_millis(500));
+
+  let main = helper.main_thread(MainThreadStrategy::CreationTime).expect("no main thread");
+  let frames = main.backtrace(&helper).expect("Couldn't walk the stack");
+  assert!(
+    frames.iter().any(|frame| frame.get_module().is_some_and(|module| {
+      module.eq_ignore_ascii_case("ntdll.dll") || module.eq_ignore_ascii_case("kernelbase.dll")
+    })),
+    "{:?}",
+    frames
+  );
+}
@@ -0,0 +1,71 @@
+#![cfg(target_os = "windows")]
+
+mod common;
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use cural::{ClrFlavor, ClrInfo, Process, SpawnOptions};
+
+/// Starts a PowerShell which idles, it runs on the CLR `exe` comes with
+fn spawn_powershell(exe: PathBuf) -> common::Child {
+  let options = SpawnOptions {
+    exe,
+    args: ["-NoProfile", "-NonInteractive", "-Command", "Start-Sleep 60"].map(String::from).to_vec(),
+    ..Default::default()
+  };
+  let spawned = Process::spawn(&options).expect("Couldn't spawn PowerShell");
+  spawned.resume().expect("Couldn't resume PowerShell");
+
+  common::Child(spawned.into_process())
+}
+
+/// Polls until the runtime is loaded, PowerShell takes a moment to start it
+fn wait_for_clr(process: &Process) -> ClrInfo {
+  for _ in 0..100 {
+    if let Some(clr) = process.clr_info().expect("Couldn't get modules") {
+      return clr;
+    }
+    thread::sleep(Duration::from_millis(100));
+  }
+
+  panic!("no CLR showed up in {:?}", process);
+}
+
+#[test]
+fn native_process_has_no_clr() {
+  let helper = common::spawn_helper(&[]);
+  // let the loader finish, so the module list is complete
+  thread::sleep(Duration::from_millis(200));
+
+  assert!(helper.clr_info().expect("Couldn't get modules").is_none());
+}
+
+#[test]
+fn windows_powershell_runs_on_framework() {
+  let root = std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into());
+  let exe = Path::new(&root).join(r"System32\WindowsPowerShell\v1.0\powershell.exe");
+  let powershell = spawn_powershell(exe);
+
+  let clr = wait_for_clr(&powershell);
+  assert_eq!(clr.get_flavor(), &ClrFlavor::Framework);
+  assert!(clr.get_module().get_name().eq_ignore_ascii_case("clr.dll"));
+  assert_eq!(clr.get_version().map(|version| version[0]), Some(4));
+}
+
+#[test]
+fn powershell_core_runs_on_core() {
+  let exe = PathBuf::from(r"C:\Program Files\PowerShell\7\pwsh.exe");
+  if !exe.exists() {
+    // PowerShell 7 isn't part of Windows
+    return;
+  }
+  let pwsh = spawn_powershell(exe);
+
+  let clr = wait_for_clr(&pwsh);
+  assert_eq!(clr.get_flavor(), &ClrFlavor::Core);
+  assert!(clr.get_module().get_name().eq_ignore_ascii_case("coreclr.dll"));
+  assert!(clr.get_version().is_some());
+}
@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+
+use std::ops::Deref;
+use std::path::Path;
+use std::path::PathBuf;
+
+use winapi::um::winuser::WaitForInputIdle;
+
+use cural::{Process, SpawnOptions};
+
+/// Process started for a test, killed when dropped so failing tests don't leave it running
+pub struct Child(pub Process);
+
+impl Deref for Child {
+  type Target = Process;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl Drop for Child {
+  fn drop(&mut self) {
+    let _ = self.0.kill(1);
+  }
+}
+
+/// Returns path of notepad, native to the tests unless they run under WOW64
+pub fn notepad() -> PathBuf {
+  let root = std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into());
+  PathBuf::from(root).join("System32").join("notepad.exe")
+}
+
+/// Returns path of the WOW64 notepad, `None` on Windows without WOW64
+pub fn wow64_notepad() -> Option<PathBuf> {
+  let root = std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into());
+  let path = PathBuf::from(root).join("SysWOW64").join("notepad.exe");
+  path.exists().then_some(path)
+}
+
+/// Starts notepad and waits until it's ready for input, so its modules are loaded
+pub fn spawn_notepad() -> Child {
+  let spawned = Process::spawn(&SpawnOptions { exe: notepad(), ..Default::default() })
+    .expect("Couldn't spawn notepad");
+  spawned.resume().expect("Couldn't resume notepad");
+
+  let child = Child(spawned.into_process());
+  unsafe { WaitForInputIdle(child.get_handle(), 10_000) };
+  child
+}
+
+/// Returns path of an example built alongside the tests, see `[[example]]` in Cargo.toml
+pub fn example(file: &str) -> PathBuf {
+  // tests run from target/<profile>/deps, examples land in target/<profile>/examples
+  let exe = std::env::current_exe().expect("Couldn't get current exe");
+  exe.parent()
+    .and_then(Path::parent)
+    .expect("tests don't run from target/<profile>/deps")
+    .join("examples")
+    .join(file)
+}
+
+/// Returns path of the test dll, see tests/helpers/test_dll.rs
+pub fn test_dll() -> PathBuf {
+  example("test_dll.dll")
+}
+
+/// Returns options spawning the helper with the current environment and `env` on top
+pub fn helper_options(env: &[(&str, &str)]) -> SpawnOptions {
+  let mut vars = std::env::vars().collect::<Vec<_>>();
+  vars.extend(env.iter().map(|(name, value)| (name.to_string(), value.to_string())));
+
+  SpawnOptions { exe: example("test_helper.exe"), env: Some(vars), ..Default::default() }
+}
+
+/// Starts the helper, which sleeps until it's killed
+pub fn spawn_helper(env: &[(&str, &str)]) -> Child {
+  let spawned = Process::spawn(&helper_options(env)).expect("Couldn't spawn helper");
+  spawned.resume().expect("Couldn't resume helper");
+
+  Child(spawned.into_process())
+}
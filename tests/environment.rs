@@ -0,0 +1,12 @@
+#![cfg(target_os = "windows")]
+
+mod common;
+
+#[test]
+fn reads_child_variable() {
+  let value = format!("distinctive-{}", std::process::id());
+  let helper = common::spawn_helper(&[("CURAL_TEST_VARIABLE", &value)]);
+
+  let environment = helper.environment().expect("Couldn't read environment");
+  assert_eq!(environment.get("CURAL_TEST_VARIABLE"), Some(&value));
+}
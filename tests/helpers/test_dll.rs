@@ -0,0 +1,17 @@
+//! Dll injected by the integration tests
+//! 
+//! Writes the file named by `CURAL_TEST_MARKER` when loaded, so tests can
+//! tell `DllMain` ran
+
+#[cfg(target_os = "windows")]
+#[no_mangle]
+extern "system" fn DllMain(_module: *mut std::ffi::c_void, reason: u32, _reserved: *mut std::ffi::c_void) -> i32 {
+  const DLL_PROCESS_ATTACH: u32 = 1;
+
+  if reason == DLL_PROCESS_ATTACH {
+    if let Some(marker) = std::env::var_os("CURAL_TEST_MARKER") {
+      let _ = std::fs::write(marker, b"loaded");
+    }
+  }
+  1
+}
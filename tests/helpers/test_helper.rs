@@ -0,0 +1,8 @@
+//! Process spawned by the integration tests, sleeps until they kill it
+
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+  thread::sleep(Duration::from_secs(60));
+}
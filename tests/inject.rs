@@ -0,0 +1,32 @@
+#![cfg(target_os = "windows")]
+
+mod common;
+
+#[test]
+fn injected_dll_appears_in_modules() {
+  let helper = common::spawn_helper(&[]);
+
+  let module = helper.inject_dll(&common::test_dll()).expect("Couldn't inject");
+  assert!(module.get_name().eq_ignore_ascii_case("test_dll.dll"));
+
+  let modules = helper.get_all_modules().expect("Couldn't get modules");
+  assert!(modules.iter().any(|found| found.get_address() == module.get_address()));
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn apc_injection_refuses_wow64_targets() {
+  use std::io;
+
+  use cural::{Process, SpawnOptions};
+
+  let exe = match common::wow64_notepad() {
+    Some(exe) => exe,
+    None => return
+  };
+  let spawned = Process::spawn(&SpawnOptions { exe, ..Default::default() }).expect("Couldn't spawn notepad");
+  let target = common::Child(spawned.into_process());
+
+  let error = target.inject_dll_apc(&common::test_dll()).expect_err("APCs were queued to a WOW64 target");
+  assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+}
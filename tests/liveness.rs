@@ -0,0 +1,26 @@
+#![cfg(target_os = "windows")]
+
+mod common;
+
+use std::time::Duration;
+
+use cural::CuralError;
+
+#[test]
+fn killed_child_stops_running() {
+  let helper = common::spawn_helper(&[]);
+  let base = helper.base_address().expect("no main module");
+  assert!(helper.is_running());
+  helper.try_read::<u16>(base).expect("Couldn't read running child");
+
+  helper.kill(7).expect("Couldn't kill");
+  helper.wait(Some(Duration::from_secs(5))).expect("Couldn't wait");
+  assert!(!helper.is_running());
+
+  let error = helper.try_read::<u16>(base).expect_err("read of an exited child succeeded");
+  assert!(
+    matches!(CuralError::of(&error), Some(CuralError::ProcessExited { exit_code: 7, .. })),
+    "{:?}",
+    error
+  );
+}
@@ -0,0 +1,39 @@
+#![cfg(target_os = "windows")]
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use cural::{MainThreadStrategy, Process, Thread};
+
+fn assert_starts_in_exe(process: &Process, thread: &Thread) {
+  let module = thread.start_module(process)
+    .expect("Couldn't get start address")
+    .expect("thread doesn't start in a module");
+  assert!(module.get_name().eq_ignore_ascii_case("notepad.exe"), "{:?} starts in {:?}", thread, module);
+}
+
+#[test]
+fn main_thread_by_creation_time() {
+  let notepad = common::spawn_notepad();
+
+  let thread = notepad.main_thread(MainThreadStrategy::CreationTime).expect("no main thread");
+  assert_starts_in_exe(&notepad, &thread);
+}
+
+#[test]
+fn main_thread_by_window() {
+  let notepad = common::spawn_notepad();
+
+  // the window can show up a bit after notepad is idle
+  let mut found = notepad.main_thread(MainThreadStrategy::Window);
+  for _ in 0..50 {
+    if found.is_ok() {
+      break;
+    }
+    thread::sleep(Duration::from_millis(100));
+    found = notepad.main_thread(MainThreadStrategy::Window);
+  }
+  assert_starts_in_exe(&notepad, &found.expect("no main window"));
+}
@@ -0,0 +1,32 @@
+#![cfg(all(target_os = "windows", target_arch = "x86_64"))]
+
+mod common;
+
+use std::path::PathBuf;
+
+use cural::{Module, ModuleEnumStrategy};
+
+fn key(module: &Module) -> (String, PathBuf, usize, usize) {
+  (module.get_name().to_ascii_lowercase(), module.get_path().to_path_buf(), *module.get_address(), *module.get_size())
+}
+
+#[test]
+fn psapi_matches_toolhelp() {
+  let notepad = common::spawn_notepad();
+
+  let mut toolhelp = notepad.get_all_modules_using(ModuleEnumStrategy::Toolhelp)
+    .expect("Couldn't snapshot modules")
+    .iter()
+    .map(key)
+    .collect::<Vec<_>>();
+  let mut psapi = notepad.get_all_modules_using(ModuleEnumStrategy::Psapi)
+    .expect("Couldn't enumerate modules")
+    .iter()
+    .map(key)
+    .collect::<Vec<_>>();
+  toolhelp.sort();
+  psapi.sort();
+
+  assert!(toolhelp.iter().any(|(name, _, _, _)| name == "notepad.exe"));
+  assert_eq!(toolhelp, psapi);
+}
@@ -0,0 +1,33 @@
+#![cfg(target_os = "windows")]
+
+mod common;
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use cural::Process;
+
+#[test]
+fn inject_before_entry_point() {
+  let marker = std::env::temp_dir().join(format!("cural-spawn-{}.txt", std::process::id()));
+  let _ = fs::remove_file(&marker);
+
+  let options = common::helper_options(&[("CURAL_TEST_MARKER", &marker.to_string_lossy())]);
+  let spawned = Process::spawn(&options).expect("Couldn't spawn helper");
+  let _child = common::Child((*spawned).clone());
+
+  spawned.inject_dll(&common::test_dll()).expect("Couldn't inject");
+  spawned.resume().expect("Couldn't resume helper");
+
+  let mut created = false;
+  for _ in 0..50 {
+    if marker.exists() {
+      created = true;
+      break;
+    }
+    thread::sleep(Duration::from_millis(100));
+  }
+  let _ = fs::remove_file(&marker);
+  assert!(created, "DllMain didn't create {}", marker.display());
+}